@@ -0,0 +1,275 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::future::Future;
+use core::pin::Pin;
+
+use crate::error::ErrorCode;
+use crate::fuse_handlers::FuseHandlers;
+use crate::protocol;
+use crate::server;
+
+#[cfg(rust_fuse_test = "async_fuse_handlers_test")]
+#[path = "async_fuse_handlers_test.rs"]
+mod async_fuse_handlers_test;
+
+/// A boxed future returned by [`AsyncFuseHandlers`].
+///
+/// [`AsyncFuseHandlers`]: trait.AsyncFuseHandlers.html
+#[cfg_attr(doc, doc(cfg(feature = "respond_async")))]
+pub type HandlerFuture<R> =
+	Pin<Box<dyn Future<Output = Result<R, ErrorCode>> + Send + 'static>>;
+
+/// Runs the futures created by an [`AsyncFuseHandlersAdapter`].
+///
+/// This is implemented for closures, so an executor's spawn function can be
+/// used directly. For example, with Tokio:
+///
+/// ```ignore
+/// let handle = tokio::runtime::Handle::current();
+/// let spawner = move |future| {
+///     handle.spawn(future);
+/// };
+/// ```
+///
+/// [`AsyncFuseHandlersAdapter`]: struct.AsyncFuseHandlersAdapter.html
+#[cfg_attr(doc, doc(cfg(feature = "respond_async")))]
+pub trait Spawn {
+	fn spawn(&self, future: Pin<Box<dyn Future<Output = ()> + Send + 'static>>);
+}
+
+impl<F> Spawn for F
+where
+	F: Fn(Pin<Box<dyn Future<Output = ()> + Send + 'static>>),
+{
+	fn spawn(
+		&self,
+		future: Pin<Box<dyn Future<Output = ()> + Send + 'static>>,
+	) {
+		self(future)
+	}
+}
+
+#[cfg(feature = "tokio")]
+#[cfg_attr(doc, doc(cfg(feature = "tokio")))]
+impl Spawn for tokio::runtime::Handle {
+	fn spawn(&self, future: Pin<Box<dyn Future<Output = ()> + Send + 'static>>) {
+		tokio::runtime::Handle::spawn(self, future);
+	}
+}
+
+fn unhandled<R>() -> HandlerFuture<R> {
+	Box::pin(async { Err(ErrorCode::ENOSYS) })
+}
+
+macro_rules! async_fuse_handlers {
+	($(
+		$(#[$attr:meta])*
+		fn $name:ident($request:ident) -> $response:ident;
+	)*) => {
+		/// User-provided handlers for FUSE operations, returning futures.
+		///
+		/// Each handler is called on the executor's thread with the decoded
+		/// request, and returns a future that resolves to the response. The
+		/// request borrows the executor's receive buffer, so any values it
+		/// contains must be copied out before the future is returned.
+		///
+		/// Responses must not borrow from the handler. Responses that hold
+		/// data should use owned storage, for example
+		/// [`ReadResponse::from_vec`] or [`ReaddirResponse::with_max_size`].
+		///
+		/// The default implementation for all async handlers is to respond
+		/// with [`ErrorCode::ENOSYS`].
+		///
+		/// Use an [`AsyncFuseHandlersAdapter`] to serve an
+		/// `AsyncFuseHandlers` with a [`FuseServerExecutor`].
+		///
+		/// [`ReadResponse::from_vec`]: struct.ReadResponse.html#method.from_vec
+		/// [`ReaddirResponse::with_max_size`]: struct.ReaddirResponse.html#method.with_max_size
+		/// [`ErrorCode::ENOSYS`]: struct.ErrorCode.html#associatedconstant.ENOSYS
+		/// [`AsyncFuseHandlersAdapter`]: struct.AsyncFuseHandlersAdapter.html
+		/// [`FuseServerExecutor`]: struct.FuseServerExecutor.html
+		#[cfg_attr(doc, doc(cfg(feature = "respond_async")))]
+		#[allow(unused_variables)]
+		pub trait AsyncFuseHandlers {
+			/// See [`FuseHandlers::fuse_init`].
+			///
+			/// [`FuseHandlers::fuse_init`]: trait.FuseHandlers.html#method.fuse_init
+			fn fuse_init(
+				&mut self,
+				request: &protocol::FuseInitRequest,
+			) -> protocol::FuseInitResponse {
+				protocol::FuseInitResponse::new()
+			}
+
+			/// See [`FuseHandlers::destroy`].
+			///
+			/// [`FuseHandlers::destroy`]: trait.FuseHandlers.html#method.destroy
+			fn destroy(&mut self) {}
+
+			fn forget(
+				&self,
+				ctx: server::ServerContext,
+				request: &protocol::ForgetRequest,
+			) {
+			}
+
+			$(
+				$(#[$attr])*
+				fn $name(
+					&self,
+					ctx: server::ServerContext,
+					request: &protocol::$request,
+				) -> HandlerFuture<protocol::$response<'static>> {
+					unhandled()
+				}
+			)*
+		}
+
+		impl<H, S> FuseHandlers for AsyncFuseHandlersAdapter<H, S>
+		where
+			H: AsyncFuseHandlers,
+			S: Spawn,
+		{
+			fn fuse_init(
+				&mut self,
+				request: &protocol::FuseInitRequest,
+			) -> protocol::FuseInitResponse {
+				self.handlers.fuse_init(request)
+			}
+
+			fn destroy(&mut self) {
+				self.handlers.destroy()
+			}
+
+			fn forget(
+				&self,
+				ctx: server::ServerContext,
+				request: &protocol::ForgetRequest,
+			) {
+				self.handlers.forget(ctx, request)
+			}
+
+			$(
+				$(#[$attr])*
+				fn $name(
+					&self,
+					ctx: server::ServerContext,
+					request: &protocol::$request,
+					respond: impl for<'a> server::Respond<
+						protocol::$response<'a>,
+					>,
+				) {
+					let future = self.handlers.$name(ctx, request);
+					self.spawn(future, server::RespondAsync::new(respond));
+				}
+			)*
+		}
+	};
+}
+
+async_fuse_handlers! {
+	fn access(AccessRequest) -> AccessResponse;
+
+	#[cfg(any(doc, feature = "unstable_bmap"))]
+	#[cfg_attr(doc, doc(cfg(feature = "unstable_bmap")))]
+	fn bmap(BmapRequest) -> BmapResponse;
+
+	fn copy_file_range(CopyFileRangeRequest) -> CopyFileRangeResponse;
+	fn create(CreateRequest) -> CreateResponse;
+	fn fallocate(FallocateRequest) -> FallocateResponse;
+	fn flush(FlushRequest) -> FlushResponse;
+	fn fsync(FsyncRequest) -> FsyncResponse;
+	fn fsyncdir(FsyncdirRequest) -> FsyncdirResponse;
+	fn getattr(GetattrRequest) -> GetattrResponse;
+	fn getlk(GetlkRequest) -> GetlkResponse;
+	fn getxattr(GetxattrRequest) -> GetxattrResponse;
+	fn ioctl(IoctlRequest) -> IoctlResponse;
+	fn link(LinkRequest) -> LinkResponse;
+	fn listxattr(ListxattrRequest) -> ListxattrResponse;
+	fn lookup(LookupRequest) -> LookupResponse;
+	fn lseek(LseekRequest) -> LseekResponse;
+	fn mkdir(MkdirRequest) -> MkdirResponse;
+	fn mknod(MknodRequest) -> MknodResponse;
+	fn open(OpenRequest) -> OpenResponse;
+	fn opendir(OpendirRequest) -> OpendirResponse;
+	fn poll(PollRequest) -> PollResponse;
+	fn read(ReadRequest) -> ReadResponse;
+	fn readdir(ReaddirRequest) -> ReaddirResponse;
+	fn readdirplus(ReaddirplusRequest) -> ReaddirplusResponse;
+	fn readlink(ReadlinkRequest) -> ReadlinkResponse;
+	fn release(ReleaseRequest) -> ReleaseResponse;
+	fn releasedir(ReleasedirRequest) -> ReleasedirResponse;
+	fn removexattr(RemovexattrRequest) -> RemovexattrResponse;
+	fn rename(RenameRequest) -> RenameResponse;
+	fn rmdir(RmdirRequest) -> RmdirResponse;
+	fn setattr(SetattrRequest) -> SetattrResponse;
+	fn setlk(SetlkRequest) -> SetlkResponse;
+	fn setxattr(SetxattrRequest) -> SetxattrResponse;
+	fn statfs(StatfsRequest) -> StatfsResponse;
+	fn symlink(SymlinkRequest) -> SymlinkResponse;
+	fn unlink(UnlinkRequest) -> UnlinkResponse;
+	fn write(WriteRequest) -> WriteResponse;
+}
+
+// AsyncFuseHandlersAdapter {{{
+
+/// Serves an [`AsyncFuseHandlers`] with the synchronous [`FuseHandlers`]
+/// interface.
+///
+/// Each request's future is passed to the [`Spawn`] implementation, wrapped
+/// in a task that sends the response once the future completes. The adapter
+/// doesn't depend on any particular async runtime.
+///
+/// [`AsyncFuseHandlers`]: trait.AsyncFuseHandlers.html
+/// [`FuseHandlers`]: trait.FuseHandlers.html
+/// [`Spawn`]: trait.Spawn.html
+#[cfg_attr(doc, doc(cfg(feature = "respond_async")))]
+pub struct AsyncFuseHandlersAdapter<H, S> {
+	handlers: H,
+	spawner: S,
+}
+
+impl<H, S> AsyncFuseHandlersAdapter<H, S>
+where
+	H: AsyncFuseHandlers,
+	S: Spawn,
+{
+	pub fn new(handlers: H, spawner: S) -> Self {
+		Self { handlers, spawner }
+	}
+
+	pub fn handlers(&self) -> &H {
+		&self.handlers
+	}
+
+	fn spawn<R>(
+		&self,
+		future: HandlerFuture<R>,
+		respond: server::RespondAsync<R>,
+	) where
+		R: Send + 'static,
+	{
+		self.spawner.spawn(Box::pin(async move {
+			match future.await {
+				Ok(response) => respond.ok(&response),
+				Err(err) => respond.err(err),
+			}
+		}));
+	}
+}
+
+// }}}
@@ -0,0 +1,151 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::sync::Arc;
+use std::task::Wake;
+use std::thread;
+
+use crate::internal::testutil::{hello_id, hello_name, HELLO_WORLD};
+use crate::testing::FuseClient;
+use crate::{
+	ErrorCode,
+	LookupRequest,
+	LookupResponse,
+	NodeName,
+	ReadRequest,
+	ReadResponse,
+	ServerContext,
+	ROOT_ID,
+};
+
+use super::{AsyncFuseHandlers, AsyncFuseHandlersAdapter, HandlerFuture};
+
+struct TestFS;
+
+impl AsyncFuseHandlers for TestFS {
+	fn lookup(
+		&self,
+		_ctx: ServerContext,
+		request: &LookupRequest,
+	) -> HandlerFuture<LookupResponse<'static>> {
+		let found = request.name() == hello_name();
+		Box::pin(async move {
+			YieldNow(false).await;
+			if !found {
+				return Err(ErrorCode::ENOENT);
+			}
+			let mut resp = LookupResponse::new();
+			resp.node_mut().set_id(hello_id());
+			resp.node_mut()
+				.attr_mut()
+				.set_size(HELLO_WORLD.len() as u64);
+			Ok(resp)
+		})
+	}
+
+	fn read(
+		&self,
+		_ctx: ServerContext,
+		request: &ReadRequest,
+	) -> HandlerFuture<ReadResponse<'static>> {
+		let start = (request.offset() as usize).min(HELLO_WORLD.len());
+		let end = (start + request.size() as usize).min(HELLO_WORLD.len());
+		Box::pin(async move {
+			YieldNow(false).await;
+			Ok(ReadResponse::from_vec(HELLO_WORLD[start..end].to_vec()))
+		})
+	}
+}
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+// Runs each future to completion on its own thread.
+fn spawn_thread(future: BoxFuture) {
+	thread::spawn(move || block_on(future));
+}
+
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+	fn wake(self: Arc<Self>) {
+		self.0.unpark();
+	}
+}
+
+fn block_on(mut future: BoxFuture) {
+	let waker = Arc::new(ThreadWaker(thread::current())).into();
+	let mut cx = Context::from_waker(&waker);
+	while future.as_mut().poll(&mut cx).is_pending() {
+		thread::park();
+	}
+}
+
+// Returns `Pending` once, waking the task so that it's polled again.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+	type Output = ();
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+		if self.0 {
+			return Poll::Ready(());
+		}
+		self.0 = true;
+		cx.waker().wake_by_ref();
+		Poll::Pending
+	}
+}
+
+fn serve() -> FuseClient {
+	let mut client =
+		FuseClient::serve(AsyncFuseHandlersAdapter::new(TestFS, spawn_thread));
+	client.fuse_init().unwrap();
+	client
+}
+
+#[test]
+fn async_response() {
+	let mut client = serve();
+
+	let node = client.lookup(ROOT_ID, hello_name()).unwrap();
+	assert_eq!(node.id(), Some(hello_id()));
+	assert_eq!(node.attr().size(), HELLO_WORLD.len() as u64);
+	assert_eq!(client.read(hello_id(), 0, 7, 5).unwrap(), b"world");
+
+	client.destroy().unwrap();
+}
+
+#[test]
+fn async_error() {
+	let mut client = serve();
+
+	let missing = NodeName::from_bytes(b"missing.txt").unwrap();
+	let err = client.lookup(ROOT_ID, missing).unwrap_err();
+	assert_eq!(err, ErrorCode::ENOENT);
+
+	client.destroy().unwrap();
+}
+
+#[test]
+fn async_unimplemented() {
+	let mut client = serve();
+	assert_eq!(client.opendir(ROOT_ID, 0), Err(ErrorCode::ENOSYS));
+	assert_eq!(client.open(hello_id(), 0), Err(ErrorCode::ENOSYS));
+	client.destroy().unwrap();
+}
@@ -19,10 +19,16 @@ use core::convert::TryInto;
 
 use core::mem::{self, MaybeUninit};
 
+#[cfg(feature = "std")]
+use core::marker::PhantomData;
 #[cfg(feature = "std")]
 use std::io::{self, IoSlice, Read, Write};
+#[cfg(feature = "std")]
+use std::os::unix::io::RawFd;
 
 use crate::error::{Error, ErrorCode};
+#[cfg(feature = "std")]
+use crate::internal::fuse_kernel;
 
 pub trait Channel {
 	type Error: ChannelError;
@@ -35,12 +41,196 @@ pub trait Channel {
 	) -> Result<(), Self::Error>;
 
 	fn receive(&self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+
+	/// Receives a request, possibly leaving the payload of a `FUSE_WRITE`
+	/// request in a pipe.
+	///
+	/// If the payload was left in a pipe, only the `fuse_in_header` and
+	/// `fuse_write_in` are written to `buf`, and the read end of the pipe is
+	/// returned along with their size. The pipe must remain valid until the
+	/// next call to `receive_spliced()` on the same thread.
+	///
+	/// The default implementation calls [`receive`](#tymethod.receive) and
+	/// never splices.
+	#[cfg(feature = "std")]
+	#[cfg_attr(doc, doc(cfg(feature = "std")))]
+	fn receive_spliced(
+		&self,
+		buf: &mut [u8],
+	) -> Result<(usize, Option<RawFd>), Self::Error> {
+		Ok((self.receive(buf)?, None))
+	}
+
+	/// Sends a message assembled from any number of buffers.
+	///
+	/// The default implementation copies the buffers into one contiguous
+	/// buffer and passes it to [`send`](#tymethod.send).
+	#[cfg(feature = "std")]
+	#[cfg_attr(doc, doc(cfg(feature = "std")))]
+	fn send_vectored_slices(
+		&self,
+		bufs: &[IoSlice],
+	) -> Result<(), Self::Error> {
+		let mut buf = Vec::with_capacity(bufs.iter().map(|b| b.len()).sum());
+		for b in bufs {
+			buf.extend_from_slice(b);
+		}
+		self.send(&buf)
+	}
+
+	/// Sends a response with a payload read from a file descriptor.
+	///
+	/// The default implementation reads the payload into memory with
+	/// [`FdResponse::send_buffered`]. Channels backed by a file descriptor
+	/// may instead splice the payload, so that it's never copied into
+	/// userspace.
+	///
+	/// [`FdResponse::send_buffered`]: struct.FdResponse.html#method.send_buffered
+	#[cfg(feature = "std")]
+	#[cfg_attr(doc, doc(cfg(feature = "std")))]
+	fn send_from_fd(&self, response: &FdResponse) -> Result<(), Self::Error> {
+		response.send_buffered(self)
+	}
 }
 
 pub trait ChannelError: From<Error> {
 	fn error_code(&self) -> Option<ErrorCode>;
 }
 
+// FdResponse {{{
+
+/// A response with a payload read from a file descriptor.
+///
+/// See [`Channel::send_from_fd`].
+///
+/// [`Channel::send_from_fd`]: trait.Channel.html#method.send_from_fd
+#[cfg(feature = "std")]
+#[cfg_attr(doc, doc(cfg(feature = "std")))]
+pub struct FdResponse<'a> {
+	phantom: PhantomData<&'a ()>,
+	request_id: u64,
+	fd: RawFd,
+	offset: u64,
+	len: u32,
+}
+
+#[cfg(feature = "std")]
+impl FdResponse<'_> {
+	pub(crate) fn new(
+		request_id: u64,
+		fd: RawFd,
+		offset: u64,
+		len: u32,
+	) -> Self {
+		Self {
+			phantom: PhantomData,
+			request_id,
+			fd,
+			offset,
+			len,
+		}
+	}
+
+	/// The file descriptor to read the payload from.
+	pub fn fd(&self) -> RawFd {
+		self.fd
+	}
+
+	/// The position in [`fd`](#method.fd) to read the payload from.
+	pub fn offset(&self) -> u64 {
+		self.offset
+	}
+
+	/// The maximum size of the payload. The payload is shorter if the end of
+	/// the file is reached first.
+	pub fn len(&self) -> u32 {
+		self.len
+	}
+
+	/// Returns `true` if the maximum size of the payload is zero.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns the response header for a payload of `payload_len` bytes,
+	/// which must be sent before the payload.
+	pub fn header(&self, payload_len: u32) -> [u8; 16] {
+		self.out_header(HEADER_LEN + payload_len as usize, 0)
+	}
+
+	/// Reads the payload with `pread()` and sends it as an ordinary response.
+	///
+	/// If the payload can't be read, an error response is sent instead.
+	pub fn send_buffered<C: Channel + ?Sized>(
+		&self,
+		channel: &C,
+	) -> Result<(), C::Error> {
+		let mut buf = vec![0u8; self.len as usize];
+		match self.read_at(&mut buf) {
+			Ok(size) => {
+				let header = self.header(size as u32);
+				channel.send_vectored(&[&header, &buf[..size]])
+			},
+			Err(err) => {
+				let code = err.raw_os_error().unwrap_or(EIO);
+				channel.send(&self.out_header(HEADER_LEN, -code))
+			},
+		}
+	}
+
+	fn read_at(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
+		use std::os::unix::fs::FileExt;
+		use std::os::unix::io::FromRawFd;
+
+		// The file descriptor is borrowed, so it must not be closed.
+		let file = mem::ManuallyDrop::new(unsafe {
+			std::fs::File::from_raw_fd(self.fd)
+		});
+		let mut total = 0;
+		while total < buf.len() {
+			match file.read_at(&mut buf[total..], self.offset + total as u64) {
+				Ok(0) => break,
+				Ok(size) => total += size,
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+				Err(err) => return Err(err),
+			}
+		}
+		Ok(total)
+	}
+
+	fn out_header(&self, len: usize, error: i32) -> [u8; 16] {
+		let out_hdr = fuse_kernel::fuse_out_header {
+			len: len as u32,
+			error,
+			unique: self.request_id,
+		};
+		unsafe { mem::transmute(out_hdr) }
+	}
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Debug for FdResponse<'_> {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		fmt.debug_struct("FdResponse")
+			.field("request_id", &self.request_id)
+			.field("fd", &self.fd)
+			.field("offset", &self.offset)
+			.field("len", &self.len)
+			.finish()
+	}
+}
+
+#[cfg(feature = "std")]
+const HEADER_LEN: usize = mem::size_of::<fuse_kernel::fuse_out_header>();
+
+#[cfg(feature = "std")]
+const EIO: i32 = 5;
+
+#[cfg(feature = "std")]
+const IOV_MAX: usize = 1024;
+
+// }}}
+
 #[cfg(feature = "std")]
 pub(crate) struct FileChannel {
 	file: std::fs::File,
@@ -57,6 +247,17 @@ impl FileChannel {
 			file: self.file.try_clone()?,
 		})
 	}
+
+	#[cfg_attr(
+		not(any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount",
+		)),
+		allow(dead_code)
+	)]
+	pub(crate) fn file(&self) -> &std::fs::File {
+		&self.file
+	}
 }
 
 #[cfg(feature = "std")]
@@ -99,6 +300,26 @@ impl Channel for FileChannel {
 		Ok(())
 	}
 
+	fn send_vectored_slices(&self, bufs: &[IoSlice]) -> Result<(), io::Error> {
+		// The kernel rejects `writev()` calls with more than `IOV_MAX`
+		// buffers, and the message must be sent in a single write.
+		if bufs.len() > IOV_MAX {
+			let mut buf =
+				Vec::with_capacity(bufs.iter().map(|b| b.len()).sum());
+			for b in bufs {
+				buf.extend_from_slice(b);
+			}
+			return self.send(&buf);
+		}
+
+		let bufs_len: usize = bufs.iter().map(|b| b.len()).sum();
+		let write_size = Write::write_vectored(&mut &self.file, bufs)?;
+		if write_size < bufs_len {
+			return Err(io::Error::other("incomplete send"));
+		}
+		Ok(())
+	}
+
 	fn receive(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
 		loop {
 			match Read::read(&mut &self.file, buf) {
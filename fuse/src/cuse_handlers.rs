@@ -45,8 +45,6 @@ pub trait CuseHandlers {
 		server::unhandled_request(respond);
 	}
 
-	#[cfg(any(doc, feature = "unstable_ioctl"))]
-	#[cfg_attr(doc, doc(cfg(feature = "unstable_ioctl")))]
 	fn ioctl(
 		&self,
 		ctx: server::ServerContext,
@@ -65,6 +63,15 @@ pub trait CuseHandlers {
 		server::unhandled_request(respond);
 	}
 
+	fn poll(
+		&self,
+		ctx: server::ServerContext,
+		request: &protocol::PollRequest,
+		respond: impl for<'a> server::Respond<protocol::PollResponse<'a>>,
+	) {
+		server::unhandled_request(respond);
+	}
+
 	fn read(
 		&self,
 		ctx: server::ServerContext,
@@ -16,7 +16,7 @@
 
 use core::{cmp, fmt};
 
-#[cfg(feature = "respond_async")]
+#[cfg(feature = "std")]
 use std::sync::Arc;
 
 use crate::channel;
@@ -25,6 +25,8 @@ use crate::error::{Error, ErrorCode};
 use crate::internal::fuse_io::{self, AlignedBuffer, DecodeRequest};
 use crate::internal::fuse_kernel;
 use crate::internal::types::ProtocolVersion;
+#[cfg(feature = "std")]
+use crate::notify::CuseNotifier;
 use crate::protocol::common::{
 	DebugBytesAsString,
 	RequestHeader,
@@ -212,6 +214,7 @@ pub struct CuseServer<Channel, Handlers, Hooks> {
 	hooks: Option<Arc<Hooks>>,
 	version: ProtocolVersion,
 	read_buf_size: usize,
+	inflight: Arc<server::InflightRequests>,
 }
 
 #[cfg(not(feature = "respond_async"))]
@@ -237,6 +240,7 @@ where
 		let hooks = hooks.map(|h| Arc::new(h));
 		let version = init_response.version();
 		let read_buf_size = server::read_buf_size(init_response.max_write());
+		let inflight = Arc::new(server::InflightRequests::new());
 
 		let executor = CuseServerExecutor {
 			channel: channel.clone(),
@@ -244,6 +248,7 @@ where
 			hooks: hooks.clone(),
 			version,
 			read_buf_size,
+			inflight: inflight.clone(),
 		};
 
 		Ok(Self {
@@ -253,6 +258,7 @@ where
 			hooks,
 			version,
 			read_buf_size,
+			inflight,
 		})
 	}
 
@@ -283,6 +289,20 @@ where
 		&mut self.executor
 	}
 
+	/// Returns a [`CuseNotifier`] that can send notifications to the kernel
+	/// from any thread.
+	///
+	/// [`CuseNotifier`]: struct.CuseNotifier.html
+	#[cfg(feature = "std")]
+	#[cfg_attr(doc, doc(cfg(feature = "std")))]
+	pub fn notifier(&self) -> Result<CuseNotifier<C>, C::Error> {
+		#[cfg(feature = "respond_async")]
+		let channel = self.channel.clone();
+		#[cfg(not(feature = "respond_async"))]
+		let channel = Arc::new(self.executor.channel.try_clone()?);
+		Ok(CuseNotifier::new(channel))
+	}
+
 	#[cfg(feature = "respond_async")]
 	#[cfg_attr(doc, doc(cfg(feature = "respond_async")))]
 	pub fn new_executor(
@@ -295,6 +315,7 @@ where
 			hooks: self.hooks.as_ref().map(|h| h.clone()),
 			version: self.version,
 			read_buf_size: self.read_buf_size,
+			inflight: self.inflight.clone(),
 		})
 	}
 }
@@ -310,6 +331,7 @@ pub struct CuseServerExecutor<Channel, Handlers, Hooks> {
 	hooks: Option<Arc<Hooks>>,
 	version: ProtocolVersion,
 	read_buf_size: usize,
+	inflight: Arc<server::InflightRequests>,
 }
 
 #[cfg(not(feature = "respond_async"))]
@@ -339,6 +361,10 @@ where
 		let hooks = self.hooks.as_deref();
 		let mut buf = fuse_io::AlignedVec::new(self.read_buf_size);
 		server::main_loop(channel, &mut buf, self.version, CUSE, |dec| {
+			let inflight = match self.inflight.begin_request(channel, &dec)? {
+				Some(x) => x,
+				None => return Ok(()),
+			};
 			let mut channel_err = Ok(());
 			let respond = server::RespondRef::new(
 				channel,
@@ -348,6 +374,7 @@ where
 				self.version,
 				&self.channel,
 				self.hooks.as_ref(),
+				inflight,
 			);
 			cuse_request_dispatch::<C, Handlers, Hooks>(
 				dec,
@@ -356,7 +383,8 @@ where
 				self.hooks.as_ref(),
 			)?;
 			channel_err
-		})
+		})?;
+		Ok(())
 	}
 
 	#[cfg(not(feature = "respond_async"))]
@@ -391,7 +419,8 @@ where
 				dec, handlers, respond, hooks,
 			)?;
 			channel_error
-		})
+		})?;
+		Ok(())
 	}
 }
 
@@ -412,6 +441,9 @@ where
 	use server::ServerHooks;
 
 	let header = request_decoder.header();
+	#[cfg(feature = "respond_async")]
+	let ctx = server::ServerContext::new(*header, respond.cancellation().clone());
+	#[cfg(not(feature = "respond_async"))]
 	let ctx = server::ServerContext::new(*header);
 
 	if let Some(hooks) = hooks {
@@ -439,9 +471,9 @@ where
 	match header.opcode {
 		fuse_kernel::FUSE_FLUSH => do_dispatch!(flush),
 		fuse_kernel::FUSE_FSYNC => do_dispatch!(fsync),
-		#[cfg(feature = "unstable_ioctl")]
 		fuse_kernel::FUSE_IOCTL => do_dispatch!(ioctl),
 		fuse_kernel::FUSE_OPEN => do_dispatch!(open),
+		fuse_kernel::FUSE_POLL => do_dispatch!(poll),
 		fuse_kernel::FUSE_READ => do_dispatch!(read),
 		fuse_kernel::FUSE_RELEASE => do_dispatch!(release),
 		fuse_kernel::FUSE_WRITE => do_dispatch!(write),
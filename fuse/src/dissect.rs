@@ -0,0 +1,283 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Pretty-printing of raw FUSE traffic.
+//!
+//! A [`Dissector`] splits a byte stream into FUSE messages and prints each
+//! one. Requests are decoded with [`io::decode_request`] and printed with
+//! their `Debug` representation. Responses and notifications are printed
+//! by header, and responses are annotated with the opcode of the request
+//! they answer when that request has been seen.
+//!
+//! Requests with unknown opcodes are flagged, and requests that fail to
+//! decode are printed with the decode error. A frame whose length is
+//! inconsistent with the stream ends the dissection, because the start of
+//! the next message can't be found.
+//!
+//! [`Dissector`]: struct.Dissector.html
+//! [`io::decode_request`]: ../io/fn.decode_request.html
+
+use core::mem::size_of;
+use core::{fmt, ptr};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::internal::fuse_io::{self, AlignedBuffer};
+use crate::internal::fuse_kernel;
+use crate::internal::types::ProtocolVersion;
+use crate::protocol::common::{RequestHeader, ResponseHeader};
+use crate::record::{RecordKind, Recording};
+use crate::sans_io::{self, FuseRequest};
+use crate::server;
+
+#[cfg(rust_fuse_test = "dissect_test")]
+#[path = "dissect_test.rs"]
+mod dissect_test;
+
+const IN_HEADER_LEN: usize = size_of::<fuse_kernel::fuse_in_header>();
+const OUT_HEADER_LEN: usize = size_of::<fuse_kernel::fuse_out_header>();
+
+/// Prints the messages in a stream of FUSE traffic.
+///
+/// Requests are decoded according to the dissector's protocol version,
+/// which defaults to the latest version supported by this library. When a
+/// `FUSE_INIT` request is dissected, the version is updated to the one this
+/// library would negotiate with the kernel that sent it.
+pub struct Dissector {
+	version: ProtocolVersion,
+	requests: HashMap<u64, RequestHeader>,
+}
+
+impl Dissector {
+	pub fn new() -> Dissector {
+		Self {
+			version: ProtocolVersion::LATEST,
+			requests: HashMap::new(),
+		}
+	}
+
+	pub fn version(&self) -> ProtocolVersion {
+		self.version
+	}
+
+	pub fn set_version(&mut self, version: ProtocolVersion) {
+		self.version = version;
+	}
+
+	/// Prints each request in `stream`, a sequence of messages sent by the
+	/// kernel.
+	pub fn dissect_requests(
+		&mut self,
+		stream: &[u8],
+		out: &mut dyn Write,
+	) -> Result<(), io::Error> {
+		self.dissect_stream(stream, IN_HEADER_LEN, out, |d, frame, out| {
+			d.dissect_request(frame, out)
+		})
+	}
+
+	/// Prints each response or notification in `stream`, a sequence of
+	/// messages sent to the kernel.
+	pub fn dissect_responses(
+		&mut self,
+		stream: &[u8],
+		out: &mut dyn Write,
+	) -> Result<(), io::Error> {
+		self.dissect_stream(stream, OUT_HEADER_LEN, out, |d, frame, out| {
+			d.dissect_response(frame, out)
+		})
+	}
+
+	/// Prints each message in a recording, prefixed by its timestamp.
+	pub fn dissect_recording(
+		&mut self,
+		recording: &Recording,
+		out: &mut dyn Write,
+	) -> Result<(), io::Error> {
+		for entry in recording.entries() {
+			let timestamp = entry.timestamp();
+			write!(
+				out,
+				"[{:>5}.{:06}] ",
+				timestamp.as_secs(),
+				timestamp.subsec_micros()
+			)?;
+			let data = entry.data();
+			let header_len = match entry.kind() {
+				RecordKind::Request => IN_HEADER_LEN,
+				_ => OUT_HEADER_LEN,
+			};
+			if let Err(err) = check_frame(data, header_len) {
+				writeln!(out, "malformed frame: {}", err)?;
+				continue;
+			}
+			match entry.kind() {
+				RecordKind::Request => self.dissect_request(data, out)?,
+				_ => self.dissect_response(data, out)?,
+			}
+		}
+		Ok(())
+	}
+
+	fn dissect_stream(
+		&mut self,
+		stream: &[u8],
+		header_len: usize,
+		out: &mut dyn Write,
+		mut dissect_frame: impl FnMut(
+			&mut Self,
+			&[u8],
+			&mut dyn Write,
+		) -> Result<(), io::Error>,
+	) -> Result<(), io::Error> {
+		let mut offset = 0;
+		while offset < stream.len() {
+			let rest = &stream[offset..];
+			let frame_len = match check_frame(rest, header_len) {
+				Ok(len) => len,
+				Err(err) => {
+					writeln!(
+						out,
+						"offset {}: malformed frame: {}",
+						offset, err
+					)?;
+					return Ok(());
+				},
+			};
+			dissect_frame(self, &rest[..frame_len], out)?;
+			offset += frame_len;
+		}
+		Ok(())
+	}
+
+	fn dissect_request(
+		&mut self,
+		frame: &[u8],
+		out: &mut dyn Write,
+	) -> Result<(), io::Error> {
+		let mut buf = fuse_io::AlignedVec::new(frame.len());
+		buf.get_mut().copy_from_slice(frame);
+		let buf = buf.get();
+
+		// The frame was checked by `check_frame()`, so these can't fail.
+		let header = *sans_io::request_header(buf).unwrap();
+		self.requests.insert(header.request_id(), header);
+
+		write!(
+			out,
+			"request {} ({} bytes): ",
+			header.request_id(),
+			buf.len()
+		)?;
+		match sans_io::decode_request(buf, self.version) {
+			Err(err) => {
+				writeln!(
+					out,
+					"{}: malformed request: {}",
+					OpcodeName(&header),
+					err
+				)?;
+				writeln!(out, "{:#?}", header)?;
+			},
+			Ok(FuseRequest::Unknown(request)) => {
+				writeln!(out, "unknown opcode {}", header.opcode())?;
+				writeln!(out, "{:#?}", request)?;
+			},
+			Ok(request) => {
+				writeln!(out, "{}", OpcodeName(&header))?;
+				if let FuseRequest::FuseInit(init) = &request {
+					if let Some(v) = server::negotiate_version(init.version()) {
+						self.version = v;
+					}
+				}
+				writeln!(out, "{:#?}", request)?;
+			},
+		}
+		Ok(())
+	}
+
+	fn dissect_response(
+		&mut self,
+		frame: &[u8],
+		out: &mut dyn Write,
+	) -> Result<(), io::Error> {
+		let raw: fuse_kernel::fuse_out_header =
+			unsafe { ptr::read_unaligned(frame.as_ptr() as *const _) };
+		let header = *ResponseHeader::new_ref(&raw);
+
+		if raw.unique == 0 {
+			// Notifications store their code in the `error` field.
+			writeln!(
+				out,
+				"notification ({} bytes): code {}",
+				frame.len(),
+				raw.error
+			)?;
+			return Ok(());
+		}
+
+		write!(out, "response {} ({} bytes)", raw.unique, frame.len())?;
+		match self.requests.remove(&raw.unique) {
+			Some(request) => writeln!(out, ": {}", OpcodeName(&request))?,
+			None => writeln!(out, ": unknown request")?,
+		}
+		writeln!(out, "{:#?}", header)?;
+		Ok(())
+	}
+}
+
+impl fmt::Debug for Dissector {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("Dissector")
+			.field("version", &self.version)
+			.finish()
+	}
+}
+
+// Returns the length of the frame at the start of `buf`.
+fn check_frame(buf: &[u8], header_len: usize) -> Result<usize, String> {
+	if buf.len() < header_len {
+		return Err(format!(
+			"{} bytes remaining, expected a {}-byte header",
+			buf.len(),
+			header_len
+		));
+	}
+	// Both `fuse_in_header` and `fuse_out_header` start with a `u32` length.
+	let len = u32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+	if len < header_len {
+		return Err(format!("length {} is smaller than the header", len));
+	}
+	if len > buf.len() {
+		return Err(format!(
+			"length {} exceeds the {} bytes remaining",
+			len,
+			buf.len()
+		));
+	}
+	Ok(len)
+}
+
+struct OpcodeName<'a>(&'a RequestHeader);
+
+impl fmt::Display for OpcodeName<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		match self.0.opcode_enum() {
+			Some(opcode) => write!(fmt, "{:?}", opcode),
+			None => write!(fmt, "opcode {}", self.0.opcode()),
+		}
+	}
+}
@@ -0,0 +1,118 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::internal::fuse_kernel;
+use crate::internal::testutil::MessageBuilder;
+
+use super::Dissector;
+
+fn dissect_requests(stream: &[u8]) -> String {
+	let mut out = Vec::new();
+	Dissector::new().dissect_requests(stream, &mut out).unwrap();
+	String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn requests() {
+	let mut stream = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_LOOKUP;
+			h.unique = 1;
+			h.nodeid = 1;
+		})
+		.push_bytes(b"hello.txt\x00")
+		.build();
+	stream.extend(
+		MessageBuilder::new()
+			.set_header(|h| {
+				h.opcode = fuse_kernel::Opcode(0xFFFF);
+				h.unique = 2;
+			})
+			.push_bytes(&[1, 2, 3])
+			.build(),
+	);
+	stream.extend(
+		MessageBuilder::new()
+			.set_header(|h| {
+				h.opcode = fuse_kernel::FUSE_READ;
+				h.unique = 3;
+				h.nodeid = 1;
+			})
+			.push_bytes(&[0; 4])
+			.build(),
+	);
+
+	let out = dissect_requests(&stream);
+	let lines: Vec<&str> = out.lines().collect();
+	assert_eq!(lines[0], "request 1 (50 bytes): FUSE_LOOKUP");
+	assert!(out.contains("name: \"hello.txt\""));
+	assert!(out.contains("request 2 (43 bytes): unknown opcode 65535\n"));
+	assert!(out.contains(
+		"request 3 (44 bytes): FUSE_READ: malformed request: \
+		 Error { kind: UnexpectedEof }\n"
+	));
+}
+
+#[test]
+fn malformed_frame() {
+	let mut stream = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_LOOKUP;
+			h.unique = 1;
+			h.nodeid = 1;
+		})
+		.push_bytes(b"hello.txt\x00")
+		.build();
+	stream.truncate(stream.len() - 1);
+
+	let out = dissect_requests(&stream);
+	assert_eq!(
+		out,
+		"offset 0: malformed frame: length 50 exceeds the 49 bytes remaining\n"
+	);
+}
+
+#[test]
+fn responses() {
+	let request = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_FLUSH;
+			h.unique = 7;
+			h.nodeid = 1;
+		})
+		.push_sized(&fuse_kernel::fuse_flush_in {
+			fh: 0,
+			unused: 0,
+			padding: 0,
+			lock_owner: 0,
+		})
+		.build();
+	let response = MessageBuilder::new()
+		.push_sized(&fuse_kernel::fuse_out_header {
+			len: 16,
+			error: 0,
+			unique: 7,
+		})
+		.build();
+
+	let mut dissector = Dissector::new();
+	let mut out = Vec::new();
+	dissector.dissect_requests(&request, &mut out).unwrap();
+	out.clear();
+	dissector.dissect_responses(&response, &mut out).unwrap();
+	let out = String::from_utf8(out).unwrap();
+	assert!(out.starts_with("response 7 (16 bytes): FUSE_FLUSH\n"));
+}
@@ -31,6 +31,8 @@ pub enum ErrorKind {
 	ExpectedCuseInit(u32),
 	ExpectedFuseInit(u32),
 	InvalidLockType(u32),
+	UnalignedBuffer,
+	NotifyDataTooLong(usize),
 }
 
 impl Error {
@@ -63,6 +65,18 @@ impl Error {
 			kind: ErrorKind::UnexpectedEof,
 		}
 	}
+
+	pub(crate) fn unaligned_buffer() -> Error {
+		Error {
+			kind: ErrorKind::UnalignedBuffer,
+		}
+	}
+
+	pub(crate) fn notify_data_too_long(len: usize) -> Error {
+		Error {
+			kind: ErrorKind::NotifyDataTooLong(len),
+		}
+	}
 }
 
 impl fmt::Display for Error {
@@ -106,6 +120,17 @@ impl From<Error> for std::io::Error {
 					lock_type,
 				),
 			),
+			ErrorKind::UnalignedBuffer => io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"Message buffer is not aligned to 8 bytes",
+			),
+			ErrorKind::NotifyDataTooLong(len) => io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!(
+					"Notification data length {} exceeds the maximum message size",
+					len,
+				),
+			),
 		}
 	}
 }
@@ -279,23 +304,27 @@ impl PartialEq<ErrorCode> for isize {
 }
 
 impl ErrorCode {
+	pub const EAGAIN: ErrorCode = target::EAGAIN;
 	pub const EINTR: ErrorCode = target::EINTR;
 	pub const EIO: ErrorCode = target::EIO;
 	pub const E2BIG: ErrorCode = target::E2BIG;
 	pub const ENODEV: ErrorCode = target::ENODEV;
 	pub const ENOENT: ErrorCode = target::ENOENT;
 	pub const ENOSYS: ErrorCode = target::ENOSYS;
+	pub const ENOTTY: ErrorCode = target::ENOTTY;
 	pub const ERANGE: ErrorCode = target::ERANGE;
 	pub const ENOATTR: ErrorCode = target::ENOATTR;
 
 	fn name_impl(&self) -> Option<&'static str> {
 		match *self {
+			Self::EAGAIN => Some("EAGAIN"),
 			Self::EINTR => Some("EINTR"),
 			Self::EIO => Some("EIO"),
 			Self::E2BIG => Some("E2BIG"),
 			Self::ENODEV => Some("ENODEV"),
 			Self::ENOENT => Some("ENOENT"),
 			Self::ENOSYS => Some("ENOSYS"),
+			Self::ENOTTY => Some("ENOTTY"),
 			Self::ERANGE => Some("ERANGE"),
 			Self::ENOATTR => Some("ENOATTR"),
 			_ => None,
@@ -317,12 +346,14 @@ macro_rules! target_error_codes {
 
 #[cfg(target_os = "freebsd")]
 target_error_codes! {
+	EAGAIN: 35,
 	EINTR: 4,
 	EIO: 5,
 	E2BIG: 7,
 	ENODEV: 19,
 	ENOENT: 2,
 	ENOSYS: 78,
+	ENOTTY: 25,
 	ERANGE: 34,
 	ENOATTR: 87,
 }
@@ -336,12 +367,14 @@ target_error_codes! {
 	),
 ))]
 target_error_codes! {
+	EAGAIN: 11,
 	EINTR: 4,
 	EIO: 5,
 	E2BIG: 7,
 	ENODEV: 19,
 	ENOENT: 2,
 	ENOSYS: 38,
+	ENOTTY: 25,
 	ERANGE: 34,
 	ENOATTR: 61,
 }
@@ -40,6 +40,13 @@ pub trait FuseHandlers {
 		protocol::FuseInitResponse::new()
 	}
 
+	/// Called once when the session ends, either because the kernel sent
+	/// `FUSE_DESTROY` or because the connection was closed.
+	///
+	/// If the server has multiple executors, this is called after the last
+	/// one has stopped running.
+	fn destroy(&mut self) {}
+
 	fn access(
 		&self,
 		ctx: server::ServerContext,
@@ -60,6 +67,22 @@ pub trait FuseHandlers {
 		server::unhandled_request(respond);
 	}
 
+	/// Copy a range of data from one file to another.
+	///
+	/// If this handler is not implemented, the kernel falls back to copying
+	/// through the page cache with [`read`] and [`write`].
+	///
+	/// [`read`]: #method.read
+	/// [`write`]: #method.write
+	fn copy_file_range(
+		&self,
+		ctx: server::ServerContext,
+		request: &protocol::CopyFileRangeRequest,
+		respond: impl for<'a> server::Respond<protocol::CopyFileRangeResponse<'a>>,
+	) {
+		server::unhandled_request(respond);
+	}
+
 	fn create(
 		&self,
 		ctx: server::ServerContext,
@@ -139,8 +162,13 @@ pub trait FuseHandlers {
 		server::unhandled_request(respond);
 	}
 
-	#[cfg(any(doc, feature = "unstable_ioctl"))]
-	#[cfg_attr(doc, doc(cfg(feature = "unstable_ioctl")))]
+	/// Handles a restricted `ioctl(2)` on an open file.
+	///
+	/// Ioctls on directories are rejected with `ENOTTY` unless the
+	/// [`FuseInitFlags::has_ioctl_dir`] flag was set in the response to
+	/// [`fuse_init`](#method.fuse_init).
+	///
+	/// [`FuseInitFlags::has_ioctl_dir`]: protocol/struct.FuseInitFlags.html#structfield.has_ioctl_dir
 	fn ioctl(
 		&self,
 		ctx: server::ServerContext,
@@ -222,6 +250,15 @@ pub trait FuseHandlers {
 		server::unhandled_request(respond);
 	}
 
+	fn poll(
+		&self,
+		ctx: server::ServerContext,
+		request: &protocol::PollRequest,
+		respond: impl for<'a> server::Respond<protocol::PollResponse<'a>>,
+	) {
+		server::unhandled_request(respond);
+	}
+
 	fn read(
 		&self,
 		ctx: server::ServerContext,
@@ -240,6 +277,15 @@ pub trait FuseHandlers {
 		server::unhandled_request(respond);
 	}
 
+	fn readdirplus(
+		&self,
+		ctx: server::ServerContext,
+		request: &protocol::ReaddirplusRequest,
+		respond: impl for<'a> server::Respond<protocol::ReaddirplusResponse<'a>>,
+	) {
+		server::unhandled_request(respond);
+	}
+
 	fn readlink(
 		&self,
 		ctx: server::ServerContext,
@@ -294,8 +340,6 @@ pub trait FuseHandlers {
 		server::unhandled_request(respond);
 	}
 
-	#[cfg(any(doc, feature = "unstable_setattr"))]
-	#[cfg_attr(doc, doc(cfg(feature = "unstable_setattr")))]
 	fn setattr(
 		&self,
 		ctx: server::ServerContext,
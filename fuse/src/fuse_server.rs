@@ -18,8 +18,18 @@
 use core::cmp;
 
 #[cfg(feature = "respond_async")]
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "respond_async")]
+use core::time::Duration;
+
+#[cfg(feature = "std")]
 use std::sync::Arc;
 
+#[cfg(feature = "respond_async")]
+use std::io;
+#[cfg(feature = "respond_async")]
+use std::sync::RwLock;
+
 use crate::channel;
 use crate::error::{Error, ErrorCode};
 use crate::fuse_handlers::FuseHandlers;
@@ -31,20 +41,47 @@ use crate::internal::fuse_io::{
 };
 use crate::internal::fuse_kernel;
 use crate::internal::types::ProtocolVersion;
+#[cfg(feature = "std")]
+use crate::notify::{Notifier, NotifyReplies};
 use crate::protocol::common::{RequestHeader, UnknownRequest};
-use crate::protocol::{FuseInitRequest, FuseInitResponse};
+use crate::protocol::ioctl::ioctl_is_dir;
+use crate::protocol::{FuseInitFlags, FuseInitRequest, FuseInitResponse};
 use crate::server;
 
+#[cfg(rust_fuse_test = "fuse_server_test")]
+#[path = "fuse_server_test.rs"]
+mod fuse_server_test;
+
 const FUSE: fuse_io::Semantics = fuse_io::Semantics::FUSE;
 
 // FuseServerBuilder {{{
 
-pub trait FuseServerChannel: server::ServerChannel {}
+pub trait FuseServerChannel: server::ServerChannel {
+	/// Ends the FUSE session, for example by unmounting the filesystem.
+	///
+	/// Executors blocked waiting for a request should wake up once the
+	/// session has ended, but responses to requests already received should
+	/// still be delivered. The default implementation does nothing, in which
+	/// case executors stop only after receiving their next request.
+	fn shutdown(&self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+
+	/// Forcibly ends a FUSE session that has been shut down, for example by
+	/// aborting the connection.
+	///
+	/// Responses can no longer be sent once the session has been aborted.
+	/// The default implementation does nothing.
+	fn abort(&self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
 
 pub struct FuseServerBuilder<Channel, Handlers, Hooks> {
 	channel: Channel,
 	handlers: Handlers,
 	hooks: Option<Hooks>,
+	splice_writes: bool,
 }
 
 impl<C, Handlers, Hooks> FuseServerBuilder<C, Handlers, Hooks>
@@ -61,6 +98,7 @@ where
 			channel,
 			handlers,
 			hooks: None,
+			splice_writes: false,
 		}
 	}
 
@@ -69,9 +107,31 @@ where
 		self
 	}
 
+	/// Receives requests with [`Channel::receive_spliced`], so that the
+	/// payload of large `FUSE_WRITE` requests can be left in a pipe. See
+	/// [`WriteRequest::value_fd`] for details.
+	///
+	/// Spliced writes are disabled by default. They are not used if the
+	/// kernel's FUSE protocol is older than v7.9.
+	///
+	/// [`Channel::receive_spliced`]: io/trait.Channel.html#method.receive_spliced
+	/// [`WriteRequest::value_fd`]: protocol/struct.WriteRequest.html#method.value_fd
+	#[cfg(feature = "std")]
+	#[cfg_attr(doc, doc(cfg(feature = "std")))]
+	pub fn set_splice_writes(mut self, splice_writes: bool) -> Self {
+		self.splice_writes = splice_writes;
+		self
+	}
+
 	pub fn build(mut self) -> Result<FuseServer<C, Handlers, Hooks>, C::Error> {
 		let init_response = self.fuse_handshake()?;
-		FuseServer::new(self.channel, self.handlers, self.hooks, &init_response)
+		FuseServer::new(
+			self.channel,
+			self.handlers,
+			self.hooks,
+			&init_response,
+			self.splice_writes && init_response.version().minor() >= 9,
+		)
 	}
 
 	fn fuse_handshake(&mut self) -> Result<FuseInitResponse, C::Error> {
@@ -118,6 +178,7 @@ where
 			#[allow(unused_mut)]
 			let mut init_response = self.handlers.fuse_init(&init_request);
 			init_response.set_version(version);
+			init_response.negotiate_max_pages(&init_request);
 
 			#[cfg(not(feature = "std"))]
 			init_response.set_max_write(cmp::min(
@@ -135,18 +196,6 @@ where
 
 // FuseServer {{{
 
-#[cfg(feature = "respond_async")]
-pub struct FuseServer<Channel, Handlers, Hooks> {
-	executor: FuseServerExecutor<Channel, Handlers, Hooks>,
-
-	channel: Arc<Channel>,
-	handlers: Arc<Handlers>,
-	hooks: Option<Arc<Hooks>>,
-	version: ProtocolVersion,
-	read_buf_size: usize,
-}
-
-#[cfg(not(feature = "respond_async"))]
 pub struct FuseServer<Channel, Handlers, Hooks> {
 	executor: FuseServerExecutor<Channel, Handlers, Hooks>,
 }
@@ -163,28 +212,21 @@ where
 		handlers: Handlers,
 		hooks: Option<Hooks>,
 		init_response: &FuseInitResponse,
+		splice_writes: bool,
 	) -> Result<FuseServer<C, Handlers, Hooks>, C::Error> {
-		let channel = Arc::new(channel);
-		let handlers = Arc::new(handlers);
-		let hooks = hooks.map(|h| Arc::new(h));
-		let version = init_response.version();
-		let read_buf_size = server::read_buf_size(init_response.max_write());
-
-		let executor = FuseServerExecutor {
-			channel: channel.clone(),
-			handlers: handlers.clone(),
-			hooks: hooks.clone(),
-			version,
-			read_buf_size,
-		};
-
 		Ok(Self {
-			executor,
-			channel,
-			handlers,
-			hooks,
-			version,
-			read_buf_size,
+			executor: FuseServerExecutor {
+				channel: Arc::new(channel),
+				handlers: Arc::new(RwLock::new(HandlersCell::new(handlers))),
+				hooks: hooks.map(|h| Arc::new(h)),
+				version: init_response.version(),
+				init_flags: *init_response.flags(),
+				read_buf_size: server::read_buf_size(init_response.max_write()),
+				splice_writes,
+				inflight: Arc::new(server::InflightRequests::new()),
+				notify_replies: Arc::new(NotifyReplies::new()),
+				stop: Arc::new(AtomicBool::new(false)),
+			},
 		})
 	}
 
@@ -194,17 +236,22 @@ where
 		handlers: Handlers,
 		hooks: Option<Hooks>,
 		init_response: &FuseInitResponse,
+		splice_writes: bool,
 	) -> Result<FuseServer<C, Handlers, Hooks>, C::Error> {
 		#[cfg(feature = "std")]
 		let read_buf_size = server::read_buf_size(init_response.max_write());
 		Ok(Self {
 			executor: FuseServerExecutor {
 				channel,
-				handlers,
+				handlers: HandlersCell::new(handlers),
 				hooks,
 				version: init_response.version(),
+				init_flags: *init_response.flags(),
 				#[cfg(feature = "std")]
 				read_buf_size,
+				splice_writes,
+				#[cfg(feature = "std")]
+				notify_replies: Arc::new(NotifyReplies::new()),
 			},
 		})
 	}
@@ -215,20 +262,157 @@ where
 		&mut self.executor
 	}
 
+	/// Returns a [`Notifier`] that can send notifications to the kernel
+	/// from any thread.
+	///
+	/// [`Notifier`]: struct.Notifier.html
+	#[cfg(feature = "std")]
+	#[cfg_attr(doc, doc(cfg(feature = "std")))]
+	pub fn notifier(&self) -> Result<Notifier<C>, C::Error> {
+		#[cfg(feature = "respond_async")]
+		let channel = self.executor.channel.clone();
+		#[cfg(not(feature = "respond_async"))]
+		let channel = Arc::new(self.executor.channel.try_clone()?);
+		let replies = self.executor.notify_replies.clone();
+		Ok(Notifier::new(channel, replies))
+	}
+
+	// Replaces the server's channel, for example to switch it into
+	// non-blocking mode once the `FUSE_INIT` handshake has completed.
+	//
+	// Must be called before any executors have been created.
+	#[cfg(all(
+		feature = "tokio",
+		any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount"
+		),
+	))]
+	pub(crate) fn map_channel<C2, E>(
+		self,
+		f: impl FnOnce(C) -> Result<C2, E>,
+	) -> Result<FuseServer<C2, Handlers, Hooks>, E> {
+		let executor = self.executor;
+		let channel = match Arc::try_unwrap(executor.channel) {
+			Ok(channel) => channel,
+			Err(_) => panic!("FuseServer::map_channel: channel is shared"),
+		};
+		Ok(FuseServer {
+			executor: FuseServerExecutor {
+				channel: Arc::new(f(channel)?),
+				handlers: executor.handlers,
+				hooks: executor.hooks,
+				version: executor.version,
+				init_flags: executor.init_flags,
+				read_buf_size: executor.read_buf_size,
+				splice_writes: executor.splice_writes,
+				inflight: executor.inflight,
+				notify_replies: executor.notify_replies,
+				stop: executor.stop,
+			},
+		})
+	}
+
 	#[cfg(feature = "respond_async")]
 	#[cfg_attr(doc, doc(cfg(feature = "respond_async")))]
 	pub fn new_executor(
 		&self,
 	) -> Result<FuseServerExecutor<C, Handlers, Hooks>, C::Error> {
-		let channel = self.channel.as_ref().try_clone()?;
+		let executor = &self.executor;
+		let channel = executor.channel.as_ref().try_clone()?;
 		Ok(FuseServerExecutor {
 			channel: Arc::new(channel),
-			handlers: self.handlers.clone(),
-			hooks: self.hooks.as_ref().map(|h| h.clone()),
-			version: self.version,
-			read_buf_size: self.read_buf_size,
+			handlers: executor.handlers.clone(),
+			hooks: executor.hooks.as_ref().map(|h| h.clone()),
+			version: executor.version,
+			init_flags: executor.init_flags,
+			read_buf_size: executor.read_buf_size,
+			splice_writes: executor.splice_writes,
+			inflight: executor.inflight.clone(),
+			notify_replies: executor.notify_replies.clone(),
+			stop: executor.stop.clone(),
 		})
 	}
+
+	// Returns a `ShutdownHandle` that can end the session from any thread.
+	#[cfg(feature = "respond_async")]
+	pub(crate) fn shutdown_handle(&self) -> ShutdownHandle<C> {
+		let executor = &self.executor;
+		ShutdownHandle {
+			channel: executor.channel.clone(),
+			inflight: executor.inflight.clone(),
+			stop: executor.stop.clone(),
+		}
+	}
+}
+
+// }}}
+
+// ShutdownHandle {{{
+
+// Ends a FUSE session from any thread.
+//
+// The handle doesn't know which threads are running the server's executors,
+// so callers that want the executors' results must collect them themselves.
+// `FuseWorkerPoolShutdownHandle` is the public API built on it.
+#[cfg(feature = "respond_async")]
+pub(crate) struct ShutdownHandle<Channel> {
+	channel: Arc<Channel>,
+	inflight: Arc<server::InflightRequests>,
+	stop: Arc<AtomicBool>,
+}
+
+#[cfg(feature = "respond_async")]
+impl<C> ShutdownHandle<C>
+where
+	C: FuseServerChannel,
+	C::Error: From<io::Error>,
+{
+	// Stops the server's executors and ends the session.
+	//
+	// Executors are asked to stop before their next request, then the
+	// session is ended with `FuseServerChannel::shutdown()` so that executors
+	// blocked waiting for a request wake up. Requests still being handled
+	// with `RespondAsync` are given until `timeout` to send their reply,
+	// after which the session is aborted with `FuseServerChannel::abort()`.
+	//
+	// Returns an error of kind `TimedOut` if requests were still in flight
+	// after `timeout`.
+	pub(crate) fn shutdown(&self, timeout: Duration) -> Result<(), C::Error> {
+		self.stop.store(true, Ordering::Release);
+		self.channel.shutdown()?;
+		let drained = self.inflight.wait_drained(timeout);
+		// The session has already ended, so failing to abort it (for
+		// example because the FUSE control filesystem is read-only) only
+		// delays executors until the last open file is closed.
+		let _ = self.channel.abort();
+		if !drained {
+			let err = io::Error::new(
+				io::ErrorKind::TimedOut,
+				"requests did not complete",
+			);
+			return Err(err.into());
+		}
+		Ok(())
+	}
+}
+
+#[cfg(feature = "respond_async")]
+impl<C> Clone for ShutdownHandle<C> {
+	fn clone(&self) -> Self {
+		Self {
+			channel: self.channel.clone(),
+			inflight: self.inflight.clone(),
+			stop: self.stop.clone(),
+		}
+	}
+}
+
+#[cfg(feature = "respond_async")]
+impl<C> core::fmt::Debug for ShutdownHandle<C> {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		fmt.debug_struct("ShutdownHandle").finish()
+	}
 }
 
 // }}}
@@ -238,20 +422,29 @@ where
 #[cfg(feature = "respond_async")]
 pub struct FuseServerExecutor<Channel, Handlers, Hooks> {
 	channel: Arc<Channel>,
-	handlers: Arc<Handlers>,
+	handlers: Arc<RwLock<HandlersCell<Handlers>>>,
 	hooks: Option<Arc<Hooks>>,
 	version: ProtocolVersion,
+	init_flags: FuseInitFlags,
 	read_buf_size: usize,
+	splice_writes: bool,
+	inflight: Arc<server::InflightRequests>,
+	notify_replies: Arc<NotifyReplies>,
+	stop: Arc<AtomicBool>,
 }
 
 #[cfg(not(feature = "respond_async"))]
 pub struct FuseServerExecutor<Channel, Handlers, Hooks> {
 	channel: Channel,
-	handlers: Handlers,
+	handlers: HandlersCell<Handlers>,
 	hooks: Option<Hooks>,
 	version: ProtocolVersion,
+	init_flags: FuseInitFlags,
 	#[cfg(feature = "std")]
 	read_buf_size: usize,
+	splice_writes: bool,
+	#[cfg(feature = "std")]
+	notify_replies: Arc<NotifyReplies>,
 }
 
 impl<C, Handlers, Hooks> FuseServerExecutor<C, Handlers, Hooks>
@@ -260,6 +453,19 @@ where
 	Handlers: FuseHandlers,
 	Hooks: server::ServerHooks,
 {
+	/// Serves requests until the session ends.
+	///
+	/// This method returns `Ok(())` when the kernel sends `FUSE_DESTROY` or
+	/// the filesystem is unmounted.
+	///
+	/// The executor that receives `FUSE_DESTROY` stops the other executors,
+	/// waits for requests in flight to be answered, and then calls
+	/// [`FuseHandlers::destroy`] before answering the kernel. If the
+	/// filesystem was unmounted, [`FuseHandlers::destroy`] is called before
+	/// returning if this executor holds the only reference to the server's
+	/// handlers, or otherwise when the last executor is dropped.
+	///
+	/// [`FuseHandlers::destroy`]: trait.FuseHandlers.html#method.destroy
 	#[cfg(feature = "respond_async")]
 	pub fn run(&mut self) -> Result<(), C::Error>
 	where
@@ -267,28 +473,146 @@ where
 		Hooks: Send + Sync + 'static,
 	{
 		let channel = self.channel.as_ref();
-		let handlers = self.handlers.as_ref();
-		let hooks = self.hooks.as_deref();
 		let mut buf = fuse_io::AlignedVec::new(self.read_buf_size);
-		server::main_loop(channel, &mut buf, self.version, FUSE, |dec| {
-			let mut channel_err = Ok(());
-			let respond = server::RespondRef::new(
-				channel,
-				hooks,
-				&mut channel_err,
-				RequestHeader::new_ref(dec.header()),
-				self.version,
-				&self.channel,
-				self.hooks.as_ref(),
-			);
-			fuse_request_dispatch::<C, Handlers, Hooks>(
-				dec,
-				handlers,
-				respond,
-				self.hooks.as_ref(),
-			)?;
-			channel_err
-		})
+		let stop = Some(self.stop.as_ref());
+		let destroy_request_id = server::main_loop_until(
+			channel,
+			&mut buf,
+			self.version,
+			FUSE,
+			stop,
+			self.splice_writes,
+			|dec| self.dispatch(dec),
+		)?;
+		self.finish(destroy_request_id)
+	}
+
+	#[cfg(feature = "respond_async")]
+	pub(crate) fn dispatch(
+		&self,
+		dec: fuse_io::RequestDecoder,
+	) -> Result<(), C::Error>
+	where
+		C: Send + Sync + 'static,
+		Hooks: Send + Sync + 'static,
+	{
+		if dec.header().opcode == fuse_kernel::FUSE_NOTIFY_REPLY {
+			return Ok(self.notify_replies.dispatch(dec)?);
+		}
+		let channel = self.channel.as_ref();
+		let inflight = match self.inflight.begin_request(channel, &dec)? {
+			Some(x) => x,
+			None => return Ok(()),
+		};
+		let mut channel_err = Ok(());
+		let respond = server::RespondRef::new(
+			channel,
+			self.hooks.as_deref(),
+			&mut channel_err,
+			RequestHeader::new_ref(dec.header()),
+			self.version,
+			&self.channel,
+			self.hooks.as_ref(),
+			inflight,
+		);
+		let handlers = self.handlers.read().unwrap();
+		fuse_request_dispatch::<C, Handlers, Hooks>(
+			dec,
+			handlers.get(),
+			respond,
+			self.hooks.as_ref(),
+			&self.init_flags,
+		)?;
+		channel_err
+	}
+
+	// Called once the main loop has stopped. If the kernel sent
+	// `FUSE_DESTROY`, it's answered after requests in flight have been
+	// answered and the handlers have been destroyed.
+	#[cfg(feature = "respond_async")]
+	pub(crate) fn finish(
+		&mut self,
+		destroy_request_id: Option<u64>,
+	) -> Result<(), C::Error> {
+		if destroy_request_id.is_some() {
+			self.stop_all();
+			self.inflight.wait_until_drained();
+		}
+		self.finish_drained(destroy_request_id)
+	}
+
+	// Like `finish`, but for callers that have already stopped the other
+	// executors and waited for requests in flight.
+	//
+	// Other executors hold a read lock on the handlers while dispatching a
+	// request, so taking the write lock waits until they're done with it.
+	#[cfg(feature = "respond_async")]
+	pub(crate) fn finish_drained(
+		&mut self,
+		destroy_request_id: Option<u64>,
+	) -> Result<(), C::Error> {
+		if destroy_request_id.is_some() {
+			self.handlers.write().unwrap().destroy();
+		} else if let Some(handlers) = Arc::get_mut(&mut self.handlers) {
+			handlers.get_mut().unwrap().destroy();
+		}
+		respond_destroy(self.channel.as_ref(), self.version, destroy_request_id)
+	}
+
+	// Asks this executor, and all others serving the same session, to stop
+	// before receiving their next request.
+	#[cfg(feature = "respond_async")]
+	pub(crate) fn stop_all(&self) {
+		self.stop.store(true, Ordering::Release);
+	}
+
+	#[cfg(feature = "respond_async")]
+	pub(crate) fn channel(&self) -> &Arc<C> {
+		&self.channel
+	}
+
+	#[cfg(all(
+		feature = "tokio",
+		any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount"
+		),
+	))]
+	pub(crate) fn inflight(&self) -> &Arc<server::InflightRequests> {
+		&self.inflight
+	}
+
+	#[cfg(all(
+		feature = "tokio",
+		any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount"
+		),
+	))]
+	pub(crate) fn stop_flag(&self) -> &Arc<AtomicBool> {
+		&self.stop
+	}
+
+	#[cfg(all(
+		feature = "tokio",
+		any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount"
+		),
+	))]
+	pub(crate) fn version(&self) -> ProtocolVersion {
+		self.version
+	}
+
+	#[cfg(all(
+		feature = "tokio",
+		any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount"
+		),
+	))]
+	pub(crate) fn read_buf_size(&self) -> usize {
+		self.read_buf_size
 	}
 
 	#[cfg(not(feature = "respond_async"))]
@@ -299,30 +623,116 @@ where
 		self.run_local()
 	}
 
+	/// Serves requests until the session ends.
+	///
+	/// This method returns `Ok(())` when the kernel sends `FUSE_DESTROY` or
+	/// the filesystem is unmounted. [`FuseHandlers::destroy`] is called
+	/// before returning.
+	///
+	/// [`FuseHandlers::destroy`]: trait.FuseHandlers.html#method.destroy
 	#[cfg(any(doc, not(feature = "respond_async")))]
 	#[cfg_attr(doc, doc(cfg(not(feature = "respond_async"))))]
 	pub fn run_local(&mut self) -> Result<(), C::Error> {
 		let channel = &self.channel;
-		let handlers = &self.handlers;
+		let handlers = self.handlers.get();
 		let hooks = self.hooks.as_ref();
 		#[cfg(feature = "std")]
 		let mut buf = fuse_io::AlignedVec::new(self.read_buf_size);
 		#[cfg(not(feature = "std"))]
 		let mut buf = fuse_io::MinReadBuffer::new();
-		server::main_loop(channel, &mut buf, self.version, FUSE, |dec| {
-			let mut channel_error = Ok(());
-			let respond = server::RespondRef::new(
-				channel,
-				hooks,
-				&mut channel_error,
-				RequestHeader::new_ref(dec.header()),
-				self.version,
-			);
-			fuse_request_dispatch::<C, Handlers, Hooks>(
-				dec, handlers, respond, hooks,
-			)?;
-			channel_error
-		})
+		let destroy_request_id = server::main_loop_until(
+			channel,
+			&mut buf,
+			self.version,
+			FUSE,
+			None,
+			self.splice_writes,
+			|dec| {
+				#[cfg(feature = "std")]
+				if dec.header().opcode == fuse_kernel::FUSE_NOTIFY_REPLY {
+					return Ok(self.notify_replies.dispatch(dec)?);
+				}
+				let mut channel_error = Ok(());
+				let respond = server::RespondRef::new(
+					channel,
+					hooks,
+					&mut channel_error,
+					RequestHeader::new_ref(dec.header()),
+					self.version,
+				);
+				fuse_request_dispatch::<C, Handlers, Hooks>(
+					dec,
+					handlers,
+					respond,
+					hooks,
+					&self.init_flags,
+				)?;
+				channel_error
+			},
+		)?;
+
+		self.handlers.destroy();
+		respond_destroy(&self.channel, self.version, destroy_request_id)
+	}
+}
+
+fn respond_destroy<C: channel::Channel>(
+	channel: &C,
+	version: ProtocolVersion,
+	request_id: Option<u64>,
+) -> Result<(), C::Error> {
+	match request_id {
+		None => Ok(()),
+		Some(request_id) => {
+			fuse_io::ResponseEncoder::new(channel, request_id, version)
+				.encode_header_only()
+		},
+	}
+}
+
+// }}}
+
+// HandlersCell {{{
+
+// Owns the user's handlers, and ensures that `FuseHandlers::destroy()` is
+// called exactly once: either when an executor stops running while it has
+// exclusive access to the handlers, or when the last reference is dropped.
+//
+// With `respond_async` the cell is shared by all executors behind a `RwLock`,
+// which the executor that receives `FUSE_DESTROY` locks for writing to call
+// `destroy()`.
+//
+// The destroy function is stored as a function pointer so that the `Drop`
+// impl does not need a `FuseHandlers` bound.
+struct HandlersCell<Handlers> {
+	handlers: Handlers,
+	destroy: Option<fn(&mut Handlers)>,
+}
+
+impl<Handlers: FuseHandlers> HandlersCell<Handlers> {
+	fn new(handlers: Handlers) -> Self {
+		Self {
+			handlers,
+			destroy: Some(Handlers::destroy),
+		}
+	}
+}
+
+impl<Handlers> HandlersCell<Handlers> {
+	fn get(&self) -> &Handlers {
+		&self.handlers
+	}
+
+	fn destroy(&mut self) {
+		if let Some(destroy) = self.destroy.take() {
+			destroy(&mut self.handlers);
+		}
+	}
+}
+
+impl<Handlers> Drop for HandlersCell<Handlers> {
+	fn drop(&mut self) {
+		self.destroy();
 	}
 }
 
@@ -334,6 +744,7 @@ fn fuse_request_dispatch<C, Handlers, Hooks>(
 	respond: server::RespondRef<C::T, Hooks::T>,
 	#[cfg(feature = "respond_async")] hooks: Option<&Arc<Hooks::T>>,
 	#[cfg(not(feature = "respond_async"))] hooks: Option<&Hooks::T>,
+	init_flags: &FuseInitFlags,
 ) -> Result<(), <<C as server::MaybeSendChannel>::T as channel::Channel>::Error>
 where
 	C: server::MaybeSendChannel,
@@ -343,6 +754,9 @@ where
 	use crate::server::ServerHooks;
 
 	let header = request_decoder.header();
+	#[cfg(feature = "respond_async")]
+	let ctx = server::ServerContext::new(*header, respond.cancellation().clone());
+	#[cfg(not(feature = "respond_async"))]
 	let ctx = server::ServerContext::new(*header);
 
 	if let Some(hooks) = hooks {
@@ -367,6 +781,7 @@ where
 		fuse_kernel::FUSE_ACCESS => do_dispatch!(access),
 		#[cfg(feature = "unstable_bmap")]
 		fuse_kernel::FUSE_BMAP => do_dispatch!(bmap),
+		fuse_kernel::FUSE_COPY_FILE_RANGE => do_dispatch!(copy_file_range),
 		fuse_kernel::FUSE_CREATE => do_dispatch!(create),
 		fuse_kernel::FUSE_FALLOCATE => do_dispatch!(fallocate),
 		fuse_kernel::FUSE_FLUSH => do_dispatch!(flush),
@@ -379,8 +794,15 @@ where
 		fuse_kernel::FUSE_GETATTR => do_dispatch!(getattr),
 		fuse_kernel::FUSE_GETLK => do_dispatch!(getlk),
 		fuse_kernel::FUSE_GETXATTR => do_dispatch!(getxattr),
-		#[cfg(feature = "unstable_ioctl")]
-		fuse_kernel::FUSE_IOCTL => do_dispatch!(ioctl),
+		fuse_kernel::FUSE_IOCTL => {
+			// The kernel sends ioctls on directories regardless of whether
+			// the server opted in with `FUSE_HAS_IOCTL_DIR`.
+			if !init_flags.has_ioctl_dir && ioctl_is_dir(&request_decoder) {
+				respond.encoder().encode_error(ErrorCode::ENOTTY)?;
+			} else {
+				do_dispatch!(ioctl)
+			}
+		},
 		fuse_kernel::FUSE_LINK => do_dispatch!(link),
 		fuse_kernel::FUSE_LISTXATTR => do_dispatch!(listxattr),
 		fuse_kernel::FUSE_LOOKUP => do_dispatch!(lookup),
@@ -389,8 +811,10 @@ where
 		fuse_kernel::FUSE_MKNOD => do_dispatch!(mknod),
 		fuse_kernel::FUSE_OPEN => do_dispatch!(open),
 		fuse_kernel::FUSE_OPENDIR => do_dispatch!(opendir),
+		fuse_kernel::FUSE_POLL => do_dispatch!(poll),
 		fuse_kernel::FUSE_READ => do_dispatch!(read),
 		fuse_kernel::FUSE_READDIR => do_dispatch!(readdir),
+		fuse_kernel::FUSE_READDIRPLUS => do_dispatch!(readdirplus),
 		fuse_kernel::FUSE_READLINK => do_dispatch!(readlink),
 		fuse_kernel::FUSE_RELEASE => do_dispatch!(release),
 		fuse_kernel::FUSE_RELEASEDIR => do_dispatch!(releasedir),
@@ -399,7 +823,6 @@ where
 			do_dispatch!(rename)
 		},
 		fuse_kernel::FUSE_RMDIR => do_dispatch!(rmdir),
-		#[cfg(feature = "unstable_setattr")]
 		fuse_kernel::FUSE_SETATTR => do_dispatch!(setattr),
 		fuse_kernel::FUSE_SETLK | fuse_kernel::FUSE_SETLKW => {
 			do_dispatch!(setlk)
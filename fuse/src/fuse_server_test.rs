@@ -0,0 +1,153 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::time::Duration;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use crate::protocol::{Opcode, OpendirRequest, OpendirResponse};
+use crate::server::{NoopServerHooks, Respond, RespondAsync, ServerContext};
+use crate::testing::FuseClient;
+use crate::{FuseHandlers, ROOT_ID};
+
+use super::FuseServerBuilder;
+
+struct TestFS {
+	destroyed: Arc<AtomicUsize>,
+}
+
+impl FuseHandlers for TestFS {
+	fn destroy(&mut self) {
+		self.destroyed.fetch_add(1, Ordering::SeqCst);
+	}
+}
+
+#[test]
+fn destroy_with_multiple_executors() {
+	let (mut client, channel) = FuseClient::new();
+	let destroyed = Arc::new(AtomicUsize::new(0));
+	let handlers = TestFS {
+		destroyed: destroyed.clone(),
+	};
+	let builder = thread::spawn(move || {
+		FuseServerBuilder::<_, _, NoopServerHooks>::new(channel, handlers)
+			.build()
+	});
+	client.fuse_init().unwrap();
+	let server = builder.join().unwrap().unwrap();
+
+	let mut threads = Vec::new();
+	for _ in 0..2 {
+		let mut executor = server.new_executor().unwrap();
+		threads.push(thread::spawn(move || executor.run()));
+	}
+
+	// The server itself holds a reference to the handlers, so the executor
+	// that receives `FUSE_DESTROY` never has exclusive access to them. The
+	// handlers must still be destroyed before `FUSE_DESTROY` is answered.
+	client.destroy().unwrap();
+	assert_eq!(destroyed.load(Ordering::SeqCst), 1);
+
+	for thread in threads {
+		thread.join().unwrap().unwrap();
+	}
+	drop(server);
+	assert_eq!(destroyed.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn shutdown_handle() {
+	let (mut client, channel) = FuseClient::new();
+	let destroyed = Arc::new(AtomicUsize::new(0));
+	let handlers = TestFS {
+		destroyed: destroyed.clone(),
+	};
+	let builder = thread::spawn(move || {
+		FuseServerBuilder::<_, _, NoopServerHooks>::new(channel, handlers)
+			.build()
+	});
+	client.fuse_init().unwrap();
+	let server = builder.join().unwrap().unwrap();
+
+	let mut threads = Vec::new();
+	for _ in 0..2 {
+		let mut executor = server.new_executor().unwrap();
+		threads.push(thread::spawn(move || executor.run()));
+	}
+
+	let shutdown = server.shutdown_handle();
+	shutdown.shutdown(Duration::from_secs(5)).unwrap();
+	for thread in threads {
+		thread.join().unwrap().unwrap();
+	}
+	assert_eq!(destroyed.load(Ordering::SeqCst), 0);
+	drop(server);
+	assert_eq!(destroyed.load(Ordering::SeqCst), 1);
+}
+
+// Responds to `FUSE_OPENDIR` from another thread after a delay.
+struct SlowFS {
+	started: Mutex<mpsc::Sender<()>>,
+}
+
+impl FuseHandlers for SlowFS {
+	fn opendir(
+		&self,
+		_ctx: ServerContext,
+		_request: &OpendirRequest,
+		respond: impl for<'a> Respond<OpendirResponse<'a>>,
+	) {
+		let respond: RespondAsync<OpendirResponse<'static>> =
+			RespondAsync::new(respond);
+		self.started.lock().unwrap().send(()).unwrap();
+		thread::spawn(move || {
+			thread::sleep(Duration::from_millis(50));
+			let mut resp = OpendirResponse::new();
+			resp.set_handle(1002);
+			respond.ok(&resp);
+		});
+	}
+}
+
+#[test]
+fn shutdown_handle_drains_requests() {
+	let (mut client, channel) = FuseClient::new();
+	let (started_tx, started_rx) = mpsc::channel();
+	let handlers = SlowFS {
+		started: Mutex::new(started_tx),
+	};
+	let builder = thread::spawn(move || {
+		FuseServerBuilder::<_, _, NoopServerHooks>::new(channel, handlers)
+			.build()
+	});
+	client.fuse_init().unwrap();
+	let server = builder.join().unwrap().unwrap();
+
+	let mut executor = server.new_executor().unwrap();
+	let thread = thread::spawn(move || executor.run());
+
+	let request_id =
+		client.send_request(Opcode::FUSE_OPENDIR, ROOT_ID.get(), &[0; 8]);
+	started_rx.recv().unwrap();
+
+	// The session isn't aborted until the request in flight has been
+	// answered, so the response reaches the client.
+	let shutdown = server.shutdown_handle();
+	shutdown.shutdown(Duration::from_secs(5)).unwrap();
+	assert!(client.receive_response(request_id).is_ok());
+	thread.join().unwrap().unwrap();
+}
@@ -14,7 +14,6 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-#[cfg(feature = "std")]
 use core::mem::align_of;
 use core::mem::size_of;
 #[cfg(feature = "std")]
@@ -79,6 +78,12 @@ pub(crate) struct AlignedSlice<'a> {
 }
 
 impl<'a> AlignedSlice<'a> {
+	// The caller must ensure that `buf` is aligned to 8 bytes.
+	pub(crate) fn new(buf: &'a [u8]) -> Self {
+		debug_assert_eq!(buf.as_ptr() as usize % align_of::<u64>(), 0);
+		Self { buf }
+	}
+
 	pub fn get(self) -> &'a [u8] {
 		self.buf
 	}
@@ -151,6 +156,11 @@ pub(crate) struct RequestDecoder<'a> {
 	version: ProtocolVersion,
 	semantics: Semantics,
 	consumed: u32,
+	// The end of the part of the request that's in `buf`. Equal to
+	// `header.len` unless the payload was spliced into a pipe.
+	limit: u32,
+	#[cfg(feature = "std")]
+	spliced_payload: Option<std::os::unix::io::RawFd>,
 }
 
 impl<'a> RequestDecoder<'a> {
@@ -187,6 +197,40 @@ impl<'a> RequestDecoder<'a> {
 			version,
 			semantics,
 			consumed: size_of::<fuse_kernel::fuse_in_header>() as u32,
+			limit: header.len,
+			#[cfg(feature = "std")]
+			spliced_payload: None,
+		})
+	}
+
+	// Decodes a `FUSE_WRITE` request whose payload was left in a pipe by
+	// `Channel::receive_spliced`. Only the request headers are in `buf`.
+	#[cfg(feature = "std")]
+	pub(crate) fn new_spliced(
+		buf: AlignedSlice<'a>,
+		version: ProtocolVersion,
+		semantics: Semantics,
+		payload_fd: std::os::unix::io::RawFd,
+	) -> Result<Self, Error> {
+		let buf = buf.get();
+		if buf.len() < size_of::<fuse_kernel::fuse_in_header>() {
+			return Err(Error::unexpected_eof());
+		}
+
+		let header: &'a fuse_kernel::fuse_in_header =
+			unsafe { &*(buf.as_ptr() as *const fuse_kernel::fuse_in_header) };
+		if (header.len as usize) < buf.len() {
+			return Err(Error::unexpected_eof());
+		}
+
+		Ok(RequestDecoder {
+			buf,
+			header,
+			version,
+			semantics,
+			consumed: size_of::<fuse_kernel::fuse_in_header>() as u32,
+			limit: buf.len() as u32,
+			spliced_payload: Some(payload_fd),
 		})
 	}
 
@@ -202,13 +246,25 @@ impl<'a> RequestDecoder<'a> {
 		self.semantics == Semantics::CUSE
 	}
 
+	// The pipe holding the rest of the request, if it was spliced. Its size
+	// is `header.len` minus the bytes consumed so far.
+	#[cfg(feature = "std")]
+	pub(crate) fn spliced_payload(&self) -> Option<std::os::unix::io::RawFd> {
+		self.spliced_payload
+	}
+
+	#[cfg(feature = "std")]
+	pub(crate) fn consumed(&self) -> u32 {
+		self.consumed
+	}
+
 	fn consume(&self, len: u32) -> Result<u32, Error> {
 		let new_consumed: u32;
 		let eof: bool;
 		match self.consumed.checked_add(len) {
 			Some(x) => {
 				new_consumed = x;
-				eof = new_consumed > self.header.len;
+				eof = new_consumed > self.limit;
 			},
 			None => {
 				new_consumed = 0;
@@ -218,7 +274,7 @@ impl<'a> RequestDecoder<'a> {
 		if eof {
 			return Err(Error::unexpected_eof());
 		}
-		debug_assert!(new_consumed <= self.header.len);
+		debug_assert!(new_consumed <= self.limit);
 		Ok(new_consumed)
 	}
 
@@ -251,7 +307,7 @@ impl<'a> RequestDecoder<'a> {
 	pub(crate) fn next_nul_terminated_bytes(
 		&mut self,
 	) -> Result<NulTerminatedBytes<'a>, Error> {
-		for off in self.consumed..self.header.len {
+		for off in self.consumed..self.limit {
 			if self.buf[off as usize] == 0 {
 				let len = off - self.consumed;
 				if len == 0 {
@@ -381,6 +437,18 @@ impl<Chan: Channel> ResponseEncoder<'_, Chan> {
 		self.channel.send(out_hdr_buf)
 	}
 
+	#[cfg(feature = "std")]
+	pub(crate) fn encode_fd(
+		self,
+		fd: std::os::unix::io::RawFd,
+		offset: u64,
+		len: u32,
+	) -> Result<(), Chan::Error> {
+		let response =
+			crate::channel::FdResponse::new(self.request_id, fd, offset, len);
+		self.channel.send_from_fd(&response)
+	}
+
 	pub(crate) fn encode_bytes(self, bytes: &[u8]) -> Result<(), Chan::Error> {
 		let mut len = size_of::<fuse_kernel::fuse_out_header>();
 
@@ -410,6 +478,44 @@ impl<Chan: Channel> ResponseEncoder<'_, Chan> {
 		self.channel.send_vectored(&[out_hdr_buf, bytes])
 	}
 
+	#[cfg(feature = "std")]
+	pub(crate) fn encode_slices(
+		self,
+		slices: &[std::io::IoSlice],
+	) -> Result<(), Chan::Error> {
+		let mut len = size_of::<fuse_kernel::fuse_out_header>();
+
+		for slice in slices {
+			match len.checked_add(slice.len()) {
+				Some(x) => len = x,
+				None => panic!("{} + {} overflows usize", len, slice.len()),
+			}
+		}
+
+		if size_of::<usize>() > size_of::<u32>() {
+			if len > u32::MAX as usize {
+				panic!("{} overflows u32", len);
+			}
+		}
+
+		let out_hdr = fuse_kernel::fuse_out_header {
+			len: len as u32,
+			error: 0,
+			unique: self.request_id,
+		};
+		let out_hdr_buf: &[u8] = unsafe {
+			core::slice::from_raw_parts(
+				(&out_hdr as *const fuse_kernel::fuse_out_header) as *const u8,
+				size_of::<fuse_kernel::fuse_out_header>(),
+			)
+		};
+
+		let mut bufs = Vec::with_capacity(slices.len() + 1);
+		bufs.push(std::io::IoSlice::new(out_hdr_buf));
+		bufs.extend_from_slice(slices);
+		self.channel.send_vectored_slices(&bufs)
+	}
+
 	pub(crate) fn encode_bytes_2(
 		self,
 		bytes_1: &[u8],
@@ -500,3 +606,108 @@ impl<Chan: Channel> ResponseEncoder<'_, Chan> {
 		])
 	}
 }
+
+// Notifications are sent to the kernel with a request ID of zero, and the
+// notification code stored in the (positive) error field of the header.
+pub(crate) struct NotifyEncoder<'a, Chan> {
+	channel: &'a Chan,
+}
+
+impl<'a, Chan> NotifyEncoder<'a, Chan> {
+	pub(crate) fn new(channel: &'a Chan) -> Self {
+		Self { channel }
+	}
+}
+
+impl<Chan: Channel> NotifyEncoder<'_, Chan> {
+	pub(crate) fn encode_sized<T: Sized>(
+		self,
+		notify_code: u32,
+		t: &T,
+	) -> Result<(), Chan::Error> {
+		let bytes: &[u8] = unsafe {
+			core::slice::from_raw_parts(
+				(t as *const T) as *const u8,
+				size_of::<T>(),
+			)
+		};
+		let len = size_of::<fuse_kernel::fuse_out_header>() + bytes.len();
+		let out_hdr = notify_header(notify_code, len);
+		let out_hdr_buf: &[u8] = unsafe {
+			core::slice::from_raw_parts(
+				(&out_hdr as *const fuse_kernel::fuse_out_header) as *const u8,
+				size_of::<fuse_kernel::fuse_out_header>(),
+			)
+		};
+
+		self.channel.send_vectored(&[out_hdr_buf, bytes])
+	}
+
+	pub(crate) fn encode_sized_bytes<T: Sized>(
+		self,
+		notify_code: u32,
+		t: &T,
+		bytes_2: &[u8],
+	) -> Result<(), Chan::Error> {
+		let bytes_1: &[u8] = unsafe {
+			core::slice::from_raw_parts(
+				(t as *const T) as *const u8,
+				size_of::<T>(),
+			)
+		};
+		let len = size_of::<fuse_kernel::fuse_out_header>()
+			+ bytes_1.len()
+			+ bytes_2.len();
+		let out_hdr = notify_header(notify_code, len);
+		let out_hdr_buf: &[u8] = unsafe {
+			core::slice::from_raw_parts(
+				(&out_hdr as *const fuse_kernel::fuse_out_header) as *const u8,
+				size_of::<fuse_kernel::fuse_out_header>(),
+			)
+		};
+
+		self.channel.send_vectored(&[out_hdr_buf, bytes_1, bytes_2])
+	}
+
+	// Encodes `t` followed by a NUL-terminated name.
+	pub(crate) fn encode_sized_name<T: Sized>(
+		self,
+		notify_code: u32,
+		t: &T,
+		name: &[u8],
+	) -> Result<(), Chan::Error> {
+		let bytes: &[u8] = unsafe {
+			core::slice::from_raw_parts(
+				(t as *const T) as *const u8,
+				size_of::<T>(),
+			)
+		};
+		let len = size_of::<fuse_kernel::fuse_out_header>()
+			+ bytes.len()
+			+ name.len()
+			+ 1;
+		let out_hdr = notify_header(notify_code, len);
+		let out_hdr_buf: &[u8] = unsafe {
+			core::slice::from_raw_parts(
+				(&out_hdr as *const fuse_kernel::fuse_out_header) as *const u8,
+				size_of::<fuse_kernel::fuse_out_header>(),
+			)
+		};
+
+		self.channel
+			.send_vectored(&[out_hdr_buf, bytes, name, b"\0"])
+	}
+}
+
+fn notify_header(notify_code: u32, len: usize) -> fuse_kernel::fuse_out_header {
+	if size_of::<usize>() > size_of::<u32>() {
+		if len > u32::MAX as usize {
+			panic!("{} overflows u32", len);
+		}
+	}
+	fuse_kernel::fuse_out_header {
+		len: len as u32,
+		error: notify_code as i32,
+		unique: 0,
+	}
+}
@@ -18,10 +18,10 @@ use std::mem::size_of;
 
 use crate::error::Error;
 use crate::internal::fuse_kernel;
-use crate::internal::testutil::MessageBuilder;
+use crate::internal::testutil::{FakeChannel, MessageBuilder};
 use crate::internal::types::ProtocolVersion;
 
-use super::{RequestDecoder, Semantics};
+use super::{NotifyEncoder, RequestDecoder, Semantics};
 
 #[test]
 fn request_decoder_new() {
@@ -145,3 +145,54 @@ fn frame_decoder_bytes() {
 	// [8 .. 12) hits EOF
 	assert_eq!(decoder.next_bytes(4), Err(Error::unexpected_eof()));
 }
+
+#[test]
+fn notify_encoder_sized() {
+	let channel = FakeChannel::new();
+	let raw = fuse_kernel::fuse_notify_poll_wakeup_out { kh: 123 };
+	NotifyEncoder::new(&channel)
+		.encode_sized(fuse_kernel::FUSE_NOTIFY_POLL, &raw)
+		.unwrap();
+
+	assert_eq!(
+		channel.expect_write(),
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: (size_of::<fuse_kernel::fuse_out_header>()
+					+ size_of::<fuse_kernel::fuse_notify_poll_wakeup_out>())
+					as u32,
+				error: fuse_kernel::FUSE_NOTIFY_POLL as i32,
+				unique: 0,
+			})
+			.push_sized(&raw)
+			.build()
+	);
+}
+
+#[test]
+fn notify_encoder_sized_name() {
+	let channel = FakeChannel::new();
+	let raw = fuse_kernel::fuse_notify_inval_entry_out {
+		parent: 123,
+		namelen: 3,
+		padding: 0,
+	};
+	NotifyEncoder::new(&channel)
+		.encode_sized_name(fuse_kernel::FUSE_NOTIFY_INVAL_ENTRY, &raw, b"foo")
+		.unwrap();
+
+	assert_eq!(
+		channel.expect_write(),
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: (size_of::<fuse_kernel::fuse_out_header>()
+					+ size_of::<fuse_kernel::fuse_notify_inval_entry_out>()
+					+ 4) as u32,
+				error: fuse_kernel::FUSE_NOTIFY_INVAL_ENTRY as i32,
+				unique: 0,
+			})
+			.push_sized(&raw)
+			.push_bytes(b"foo\0")
+			.build()
+	);
+}
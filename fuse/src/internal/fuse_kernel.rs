@@ -116,6 +116,12 @@
  *
  *  7.27
  *  - add FUSE_ABORT_ERROR
+ *
+ *  7.28
+ *  - add FUSE_COPY_FILE_RANGE
+ *  - add FOPEN_CACHE_DIR
+ *  - add FUSE_MAX_PAGES, add max_pages to init_out
+ *  - add FUSE_CACHE_SYMLINKS
  */
 
 /*
@@ -142,7 +148,7 @@
 pub const FUSE_KERNEL_VERSION: u32 = 7;
 
 /** Minor version number of this interface */
-pub const FUSE_KERNEL_MINOR_VERSION: u32 = 27;
+pub const FUSE_KERNEL_MINOR_VERSION: u32 = 28;
 
 /** The node ID of the root inode */
 pub const FUSE_ROOT_ID: u64 = 1;
@@ -216,10 +222,12 @@ pub const FATTR_CTIME:     u32 = 1 << 10;
  * FOPEN_DIRECT_IO: bypass page cache for this open file
  * FOPEN_KEEP_CACHE: don't invalidate the data cache on open
  * FOPEN_NONSEEKABLE: the file is not seekable
+ * FOPEN_CACHE_DIR: allow caching this directory
  */
 pub const FOPEN_DIRECT_IO:   u32 = 1 <<  0;
 pub const FOPEN_KEEP_CACHE:  u32 = 1 <<  1;
 pub const FOPEN_NONSEEKABLE: u32 = 1 <<  2;
+pub const FOPEN_CACHE_DIR:   u32 = 1 <<  3;
 
 /**
  * INIT request/reply flags
@@ -246,6 +254,8 @@ pub const FOPEN_NONSEEKABLE: u32 = 1 <<  2;
  * FUSE_HANDLE_KILLPRIV: fs handles killing suid/sgid/cap on write/chown/trunc
  * FUSE_POSIX_ACL: filesystem supports posix acls
  * FUSE_ABORT_ERROR: reading the device after abort returns ECONNABORTED
+ * FUSE_MAX_PAGES: init_out.max_pages contains the max number of req pages
+ * FUSE_CACHE_SYMLINKS: cache READLINK responses
  */
 pub const FUSE_ASYNC_READ:       u32 = 1 <<  0;
 pub const FUSE_POSIX_LOCKS:      u32 = 1 <<  1;
@@ -269,6 +279,8 @@ pub const FUSE_PARALLEL_DIROPS:  u32 = 1 << 18;
 pub const FUSE_HANDLE_KILLPRIV:  u32 = 1 << 19;
 pub const FUSE_POSIX_ACL:        u32 = 1 << 20;
 pub const FUSE_ABORT_ERROR:      u32 = 1 << 21;
+pub const FUSE_MAX_PAGES:        u32 = 1 << 22;
+pub const FUSE_CACHE_SYMLINKS:   u32 = 1 << 23;
 
 /**
  * CUSE INIT request/reply flags
@@ -378,6 +390,7 @@ enum_fuse_opcode! {
 	FUSE_READDIRPLUS  = 44,
 	FUSE_RENAME2      = 45,
 	FUSE_LSEEK        = 46,
+	FUSE_COPY_FILE_RANGE = 47,
 
 	/* CUSE specific operations */
 	CUSE_INIT         = 4096,
@@ -665,7 +678,9 @@ pub struct fuse_init_out {
 	pub congestion_threshold: u16,
 	pub max_write:            u32,
 	pub time_gran:            u32,
-	pub unused:               [u32; 9],
+	pub max_pages:            u16,
+	pub padding:              u16,
+	pub unused:               [u32; 8],
 }
 
 pub const CUSE_INIT_INFO_MAX: u32 = 4096;
@@ -736,7 +751,7 @@ pub struct fuse_ioctl_iovec {
 pub struct fuse_ioctl_out {
 	pub result:   i32,
 	pub flags:    u32,
-	pub io_iovs:  u32,
+	pub in_iovs:  u32,
 	pub out_iovs: u32,
 }
 
@@ -884,3 +899,15 @@ pub struct fuse_lseek_in {
 pub struct fuse_lseek_out {
 	pub offset: u64,
 }
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct fuse_copy_file_range_in {
+	pub fh_in:      u64,
+	pub off_in:     u64,
+	pub nodeid_out: u64,
+	pub fh_out:     u64,
+	pub off_out:    u64,
+	pub len:        u64,
+	pub flags:      u64,
+}
@@ -17,14 +17,37 @@
 #![allow(dead_code)]
 #![allow(unused_macros)]
 
+use core::num::NonZeroU64;
 use std::cell::RefCell;
 use std::mem::size_of;
 use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use crate::channel;
 use crate::internal::fuse_io::{self, AlignedBuffer};
 use crate::internal::fuse_kernel;
 use crate::internal::types::ProtocolVersion;
+use crate::{
+	ErrorCode,
+	FileType,
+	FuseHandlers,
+	LookupRequest,
+	LookupResponse,
+	NodeId,
+	NodeName,
+	OpenRequest,
+	OpenResponse,
+	OpendirRequest,
+	OpendirResponse,
+	ReadRequest,
+	ReadResponse,
+	ReaddirRequest,
+	ReaddirResponse,
+	Respond,
+	ServerContext,
+	ROOT_ID,
+};
 
 pub(crate) struct MessageBuilder {
 	header: Option<fuse_kernel::fuse_in_header>,
@@ -250,3 +273,102 @@ macro_rules! encode_request_opts {
 		}
 	};
 }
+
+pub(crate) const HELLO_WORLD: &[u8] = b"Hello, world!\n";
+
+pub(crate) fn hello_id() -> NodeId {
+	NodeId::new(100).unwrap()
+}
+
+pub(crate) fn hello_name() -> &'static NodeName {
+	NodeName::from_bytes(b"hello.txt").unwrap()
+}
+
+// A filesystem with a single file, `hello.txt`, in its root directory.
+pub(crate) struct HelloFS {
+	pub(crate) contents: &'static [u8],
+	pub(crate) destroyed: Arc<AtomicBool>,
+}
+
+impl HelloFS {
+	pub(crate) fn new() -> HelloFS {
+		Self {
+			contents: HELLO_WORLD,
+			destroyed: Arc::new(AtomicBool::new(false)),
+		}
+	}
+}
+
+impl FuseHandlers for HelloFS {
+	fn destroy(&mut self) {
+		self.destroyed.store(true, Ordering::SeqCst);
+	}
+
+	fn lookup(
+		&self,
+		_ctx: ServerContext,
+		request: &LookupRequest,
+		respond: impl for<'a> Respond<LookupResponse<'a>>,
+	) {
+		if request.parent_id() != ROOT_ID || request.name() != hello_name() {
+			respond.err(ErrorCode::ENOENT);
+			return;
+		}
+		let mut resp = LookupResponse::new();
+		resp.node_mut().set_id(hello_id());
+		resp.node_mut()
+			.attr_mut()
+			.set_size(self.contents.len() as u64);
+		respond.ok(&resp);
+	}
+
+	fn open(
+		&self,
+		_ctx: ServerContext,
+		_request: &OpenRequest,
+		respond: impl for<'a> Respond<OpenResponse<'a>>,
+	) {
+		let mut resp = OpenResponse::new();
+		resp.set_handle(1001);
+		respond.ok(&resp);
+	}
+
+	fn read(
+		&self,
+		_ctx: ServerContext,
+		request: &ReadRequest,
+		respond: impl for<'a> Respond<ReadResponse<'a>>,
+	) {
+		let contents = self.contents;
+		let start = (request.offset() as usize).min(contents.len());
+		let end = (start + request.size() as usize).min(contents.len());
+		respond.ok(&ReadResponse::from_bytes(&contents[start..end]));
+	}
+
+	fn opendir(
+		&self,
+		_ctx: ServerContext,
+		_request: &OpendirRequest,
+		respond: impl for<'a> Respond<OpendirResponse<'a>>,
+	) {
+		let mut resp = OpendirResponse::new();
+		resp.set_handle(1002);
+		respond.ok(&resp);
+	}
+
+	fn readdir(
+		&self,
+		_ctx: ServerContext,
+		request: &ReaddirRequest,
+		respond: impl for<'a> Respond<ReaddirResponse<'a>>,
+	) {
+		if request.cursor().is_some() {
+			respond.ok(ReaddirResponse::EMPTY);
+			return;
+		}
+		let mut resp = ReaddirResponse::with_max_size(request.size());
+		resp.add_entry(hello_id(), hello_name(), NonZeroU64::new(1).unwrap())
+			.set_file_type(FileType::Regular);
+		respond.ok(&resp);
+	}
+}
@@ -44,6 +44,16 @@ pub use self::cuse_server::{
 mod fuse_handlers;
 pub use self::fuse_handlers::FuseHandlers;
 
+#[cfg(feature = "respond_async")]
+mod async_fuse_handlers;
+#[cfg(feature = "respond_async")]
+pub use self::async_fuse_handlers::{
+	AsyncFuseHandlers,
+	AsyncFuseHandlersAdapter,
+	HandlerFuture,
+	Spawn,
+};
+
 mod fuse_server;
 pub use self::fuse_server::{
 	FuseServer,
@@ -51,6 +61,13 @@ pub use self::fuse_server::{
 	FuseServerExecutor,
 };
 
+mod notify;
+#[cfg(feature = "std")]
+pub use self::notify::{CuseNotifier, Notifier};
+
+#[path = "sans_io/sans_io.rs"]
+mod sans_io;
+
 mod server;
 pub use self::server::{
 	ServerContext,
@@ -59,7 +76,7 @@ pub use self::server::{
 };
 
 #[cfg(feature = "respond_async")]
-pub use self::server::RespondAsync;
+pub use self::server::{CancellationHandle, RespondAsync};
 
 pub mod os {
 	#[cfg(any(doc, target_os = "linux"))]
@@ -71,12 +88,37 @@ pub mod io {
 	pub use crate::internal::types::ProtocolVersion;
 	
 	pub use crate::channel::{Channel, ChannelError};
+	#[cfg(feature = "std")]
+	pub use crate::channel::FdResponse;
 	pub use crate::server::ServerChannel;
 
 	pub use crate::cuse_server::CuseServerChannel;
 	pub use crate::fuse_server::FuseServerChannel;
+
+	pub use crate::sans_io::{
+		decode_cuse_request,
+		decode_request,
+		request_header,
+		CuseRequest,
+		EncodeResponse,
+		FuseRequest,
+	};
+	#[cfg(feature = "std")]
+	pub use crate::sans_io::{encode_error, encode_response};
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(doc, doc(cfg(feature = "std")))]
+pub mod dissect;
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc, doc(cfg(feature = "std")))]
+pub mod record;
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc, doc(cfg(feature = "std")))]
+pub mod testing;
+
 pub use crate::error::{Error, ErrorCode};
 
 pub mod protocol;
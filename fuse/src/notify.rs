@@ -0,0 +1,328 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "std")]
+
+use core::fmt;
+use core::mem::size_of;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::channel;
+use crate::error::Error;
+use crate::internal::fuse_io::{self, DecodeRequest};
+use crate::internal::fuse_kernel;
+use crate::protocol::common::{NodeId, NodeName};
+use crate::protocol::{PollHandle, RetrieveReply};
+
+#[cfg(rust_fuse_test = "notify_test")]
+#[path = "notify_test.rs"]
+mod notify_test;
+
+// The longest data that fits in a `FUSE_NOTIFY_STORE` message, whose total
+// length must fit in a `u32`.
+const MAX_STORE_LEN: usize = u32::MAX as usize
+	- size_of::<fuse_kernel::fuse_out_header>()
+	- size_of::<fuse_kernel::fuse_notify_store_out>();
+
+/// Sends unsolicited notifications to the kernel.
+///
+/// A `Notifier` shares the channel of the server it was created from, and
+/// may be cloned and sent to other threads. Notifications can be sent at
+/// any time after the server has been built, independently of any request.
+#[cfg_attr(doc, doc(cfg(feature = "std")))]
+pub struct Notifier<Channel> {
+	channel: Arc<Channel>,
+	replies: Arc<NotifyReplies>,
+}
+
+impl<C: channel::Channel> Notifier<C> {
+	pub(crate) fn new(
+		channel: Arc<C>,
+		replies: Arc<NotifyReplies>,
+	) -> Notifier<C> {
+		Self { channel, replies }
+	}
+
+	/// Wakes up processes waiting in `poll(2)` for a file, using a handle
+	/// obtained from [`PollRequest::poll_handle`].
+	///
+	/// [`PollRequest::poll_handle`]: protocol/struct.PollRequest.html#method.poll_handle
+	pub fn poll_wakeup(&self, poll_handle: PollHandle) -> Result<(), C::Error> {
+		poll_wakeup(self.channel.as_ref(), poll_handle)
+	}
+
+	/// Invalidates cached data and attributes of an inode.
+	///
+	/// The inode's attributes are always invalidated. If `offset` is
+	/// non-negative, cached data starting at `offset` is also invalidated:
+	/// `len` bytes of it, or up to the end of the file if `len` is zero or
+	/// negative.
+	///
+	/// Requires FUSE protocol v7.12 or later.
+	pub fn inval_inode(
+		&self,
+		node_id: NodeId,
+		offset: i64,
+		len: i64,
+	) -> Result<(), C::Error> {
+		let raw = fuse_kernel::fuse_notify_inval_inode_out {
+			ino: node_id.get(),
+			off: offset,
+			len,
+		};
+		fuse_io::NotifyEncoder::new(self.channel.as_ref())
+			.encode_sized(fuse_kernel::FUSE_NOTIFY_INVAL_INODE, &raw)
+	}
+
+	/// Invalidates the directory entry `name` within the directory `parent`,
+	/// along with the parent's cached attributes.
+	///
+	/// Requires FUSE protocol v7.12 or later.
+	pub fn inval_entry(
+		&self,
+		parent: NodeId,
+		name: &NodeName,
+	) -> Result<(), C::Error> {
+		let name = name.as_bytes();
+		let raw = fuse_kernel::fuse_notify_inval_entry_out {
+			parent: parent.get(),
+			namelen: name.len() as u32,
+			padding: 0,
+		};
+		fuse_io::NotifyEncoder::new(self.channel.as_ref()).encode_sized_name(
+			fuse_kernel::FUSE_NOTIFY_INVAL_ENTRY,
+			&raw,
+			name,
+		)
+	}
+
+	/// Notifies the kernel that the directory entry `name` within `parent`,
+	/// which refers to `child`, has been deleted.
+	///
+	/// Unlike [`inval_entry`], the kernel treats the entry as deleted rather
+	/// than merely stale, so processes watching the directory with
+	/// `inotify(7)` will observe the deletion.
+	///
+	/// Requires FUSE protocol v7.18 or later.
+	///
+	/// [`inval_entry`]: #method.inval_entry
+	pub fn delete(
+		&self,
+		parent: NodeId,
+		child: NodeId,
+		name: &NodeName,
+	) -> Result<(), C::Error> {
+		let name = name.as_bytes();
+		let raw = fuse_kernel::fuse_notify_delete_out {
+			parent: parent.get(),
+			child: child.get(),
+			namelen: name.len() as u32,
+			padding: 0,
+		};
+		fuse_io::NotifyEncoder::new(self.channel.as_ref()).encode_sized_name(
+			fuse_kernel::FUSE_NOTIFY_DELETE,
+			&raw,
+			name,
+		)
+	}
+
+	/// Stores data into the kernel's page cache for a node.
+	///
+	/// The cached file size is extended if the data extends past its
+	/// current end. Returns an error if `data` is too long to fit in a
+	/// single message.
+	///
+	/// Requires FUSE protocol v7.15 or later.
+	pub fn store(
+		&self,
+		node_id: NodeId,
+		offset: u64,
+		data: &[u8],
+	) -> Result<(), C::Error> {
+		if data.len() > MAX_STORE_LEN {
+			return Err(Error::notify_data_too_long(data.len()).into());
+		}
+		let raw = fuse_kernel::fuse_notify_store_out {
+			nodeid: node_id.get(),
+			offset,
+			size: data.len() as u32,
+			padding: 0,
+		};
+		fuse_io::NotifyEncoder::new(self.channel.as_ref()).encode_sized_bytes(
+			fuse_kernel::FUSE_NOTIFY_STORE,
+			&raw,
+			data,
+		)
+	}
+
+	/// Requests up to `size` bytes of a node's cached data, starting at
+	/// `offset`.
+	///
+	/// The kernel replies asynchronously, and `callback` is invoked with the
+	/// retrieved data by the executor that receives the reply. The callback
+	/// should return quickly, because that executor will not serve other
+	/// requests while it runs.
+	///
+	/// Requires FUSE protocol v7.15 or later.
+	pub fn retrieve<F>(
+		&self,
+		node_id: NodeId,
+		offset: u64,
+		size: u32,
+		callback: F,
+	) -> Result<(), C::Error>
+	where
+		F: FnOnce(&RetrieveReply) + Send + 'static,
+	{
+		let notify_unique = self.replies.insert(Box::new(callback));
+		let raw = fuse_kernel::fuse_notify_retrieve_out {
+			notify_unique,
+			nodeid: node_id.get(),
+			offset,
+			size,
+			padding: 0,
+		};
+		let result = fuse_io::NotifyEncoder::new(self.channel.as_ref())
+			.encode_sized(fuse_kernel::FUSE_NOTIFY_RETRIEVE, &raw);
+		if result.is_err() {
+			self.replies.remove(notify_unique);
+		}
+		result
+	}
+}
+
+impl<C> Clone for Notifier<C> {
+	fn clone(&self) -> Self {
+		Self {
+			channel: self.channel.clone(),
+			replies: self.replies.clone(),
+		}
+	}
+}
+
+impl<C> fmt::Debug for Notifier<C> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("Notifier").finish()
+	}
+}
+
+// CuseNotifier {{{
+
+/// Sends unsolicited notifications to the kernel from a CUSE server.
+///
+/// CUSE devices have no inodes or page cache, so the only notification they
+/// support is waking up processes waiting in `poll(2)`. Like a [`Notifier`],
+/// a `CuseNotifier` may be cloned and sent to other threads.
+///
+/// [`Notifier`]: struct.Notifier.html
+#[cfg_attr(doc, doc(cfg(feature = "std")))]
+pub struct CuseNotifier<Channel> {
+	channel: Arc<Channel>,
+}
+
+impl<C: channel::Channel> CuseNotifier<C> {
+	pub(crate) fn new(channel: Arc<C>) -> CuseNotifier<C> {
+		Self { channel }
+	}
+
+	/// Wakes up processes waiting in `poll(2)` for the device, using a handle
+	/// obtained from [`PollRequest::poll_handle`].
+	///
+	/// [`PollRequest::poll_handle`]: protocol/struct.PollRequest.html#method.poll_handle
+	pub fn poll_wakeup(&self, poll_handle: PollHandle) -> Result<(), C::Error> {
+		poll_wakeup(self.channel.as_ref(), poll_handle)
+	}
+}
+
+impl<C> Clone for CuseNotifier<C> {
+	fn clone(&self) -> Self {
+		Self {
+			channel: self.channel.clone(),
+		}
+	}
+}
+
+impl<C> fmt::Debug for CuseNotifier<C> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("CuseNotifier").finish()
+	}
+}
+
+// }}}
+
+fn poll_wakeup<C: channel::Channel>(
+	channel: &C,
+	poll_handle: PollHandle,
+) -> Result<(), C::Error> {
+	let raw = fuse_kernel::fuse_notify_poll_wakeup_out { kh: poll_handle.0 };
+	fuse_io::NotifyEncoder::new(channel)
+		.encode_sized(fuse_kernel::FUSE_NOTIFY_POLL, &raw)
+}
+
+// NotifyReplies {{{
+
+type RetrieveCallback = Box<dyn FnOnce(&RetrieveReply) + Send>;
+
+// Callbacks for pending `FUSE_NOTIFY_RETRIEVE` notifications, keyed by the
+// notify unique that the kernel will send back in `FUSE_NOTIFY_REPLY`.
+pub(crate) struct NotifyReplies {
+	state: Mutex<NotifyRepliesState>,
+}
+
+struct NotifyRepliesState {
+	next_unique: u64,
+	callbacks: HashMap<u64, RetrieveCallback>,
+}
+
+impl NotifyReplies {
+	pub(crate) fn new() -> NotifyReplies {
+		Self {
+			state: Mutex::new(NotifyRepliesState {
+				next_unique: 1,
+				callbacks: HashMap::new(),
+			}),
+		}
+	}
+
+	fn insert(&self, callback: RetrieveCallback) -> u64 {
+		let mut state = self.state.lock().unwrap();
+		let notify_unique = state.next_unique;
+		state.next_unique = state.next_unique.wrapping_add(1).max(1);
+		state.callbacks.insert(notify_unique, callback);
+		notify_unique
+	}
+
+	fn remove(&self, notify_unique: u64) -> Option<RetrieveCallback> {
+		self.state.lock().unwrap().callbacks.remove(&notify_unique)
+	}
+
+	// Decodes a `FUSE_NOTIFY_REPLY` message and passes it to the callback
+	// registered for its notify unique. Replies with no registered callback
+	// are ignored. The kernel does not expect a response.
+	pub(crate) fn dispatch(
+		&self,
+		dec: fuse_io::RequestDecoder,
+	) -> Result<(), Error> {
+		let reply = RetrieveReply::decode_request(dec)?;
+		if let Some(callback) = self.remove(reply.notify_unique()) {
+			callback(&reply);
+		}
+		Ok(())
+	}
+}
+
+// }}}
@@ -0,0 +1,55 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+use std::io;
+use std::sync::Arc;
+
+use crate::internal::fuse_kernel;
+use crate::notify::{Notifier, NotifyReplies, MAX_STORE_LEN};
+use crate::testing::FuseClient;
+use crate::ROOT_ID;
+
+// The length of a `FUSE_NOTIFY_STORE` message without its data.
+const STORE_HEADER_LEN: usize = size_of::<fuse_kernel::fuse_out_header>()
+	+ size_of::<fuse_kernel::fuse_notify_store_out>();
+
+#[test]
+fn store() {
+	let (mut client, channel) = FuseClient::new();
+	let notifier =
+		Notifier::new(Arc::new(channel), Arc::new(NotifyReplies::new()));
+
+	notifier.store(ROOT_ID, 0, b"hello").unwrap();
+	let notifications = client.take_notifications();
+	assert_eq!(notifications.len(), 1);
+	assert_eq!(notifications[0].len(), STORE_HEADER_LEN + 5);
+	assert_eq!(&notifications[0][STORE_HEADER_LEN..], b"hello");
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn store_too_long() {
+	let (mut client, channel) = FuseClient::new();
+	let notifier =
+		Notifier::new(Arc::new(channel), Arc::new(NotifyReplies::new()));
+
+	// The allocation is never written to, so it isn't backed by memory.
+	let data = vec![0u8; MAX_STORE_LEN + 1];
+	let err = notifier.store(ROOT_ID, 0, &data).unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+	assert!(client.take_notifications().is_empty());
+}
@@ -43,9 +43,33 @@ impl channel::Channel for DevCuseChannel {
 		self.0.send_vectored(bufs)
 	}
 
+	fn send_vectored_slices(
+		&self,
+		bufs: &[io::IoSlice],
+	) -> Result<(), io::Error> {
+		self.0.send_vectored_slices(bufs)
+	}
+
 	fn receive(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
 		self.0.receive(buf)
 	}
+
+	/// Splices the payload into the channel without copying it into
+	/// userspace, falling back to `pread()` if `fd` doesn't support
+	/// splicing.
+	#[cfg(any(
+		feature = "libc_fuse_mount",
+		feature = "nightly_syscall_fuse_mount",
+	))]
+	fn send_from_fd(
+		&self,
+		response: &channel::FdResponse,
+	) -> Result<(), io::Error> {
+		if super::splice::send_from_fd(self.0.file(), response)? {
+			return Ok(());
+		}
+		response.send_buffered(self)
+	}
 }
 
 impl server::ServerChannel for DevCuseChannel {
@@ -15,47 +15,469 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::io;
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+use std::os::unix::io::RawFd;
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+use std::{fs, path};
 
 use crate::channel;
 use crate::fuse_server;
 use crate::server;
 
 #[cfg_attr(doc, doc(cfg(feature = "std")))]
-pub struct DevFuseChannel(channel::FileChannel);
+pub struct DevFuseChannel {
+	channel: channel::FileChannel,
+	#[cfg(any(
+		feature = "libc_fuse_mount",
+		feature = "nightly_syscall_fuse_mount",
+	))]
+	dev_fuse: path::PathBuf,
+	#[cfg(any(
+		feature = "libc_fuse_mount",
+		feature = "nightly_syscall_fuse_mount",
+	))]
+	mount_target: Option<path::PathBuf>,
+	#[cfg(any(
+		feature = "libc_fuse_mount",
+		feature = "nightly_syscall_fuse_mount",
+	))]
+	unmount_helper: Option<path::PathBuf>,
+	#[cfg(any(
+		feature = "libc_fuse_mount",
+		feature = "nightly_syscall_fuse_mount",
+	))]
+	connection_id: Option<u64>,
+}
 
 impl DevFuseChannel {
 	#[cfg(any(
 		feature = "libc_fuse_mount",
 		feature = "nightly_syscall_fuse_mount",
 	))]
-	pub(super) fn new(file: std::fs::File) -> DevFuseChannel {
-		Self(channel::FileChannel::new(file))
+	pub(super) fn new(
+		file: fs::File,
+		dev_fuse: &path::Path,
+		mount_target: &path::Path,
+	) -> DevFuseChannel {
+		let connection_id = cstr_from_path(mount_target)
+			.and_then(|target| connection_id(AT_FDCWD, &target, 0))
+			.ok();
+		Self {
+			channel: channel::FileChannel::new(file),
+			dev_fuse: dev_fuse.to_path_buf(),
+			mount_target: Some(mount_target.to_path_buf()),
+			unmount_helper: None,
+			connection_id,
+		}
+	}
+
+	// Creates a channel for a mount that hasn't been attached by this
+	// process, so there's no mount target to unmount on shutdown. The
+	// connection can still be aborted, using the ID found from `mount`.
+	#[cfg(any(
+		feature = "libc_fuse_mount",
+		feature = "nightly_syscall_fuse_mount",
+	))]
+	pub(super) fn new_detached(
+		file: fs::File,
+		dev_fuse: &path::Path,
+		mount: &fs::File,
+	) -> DevFuseChannel {
+		use std::os::unix::io::AsRawFd;
+
+		let empty = std::ffi::CString::default();
+		let connection_id =
+			connection_id(mount.as_raw_fd(), &empty, AT_EMPTY_PATH).ok();
+		Self {
+			channel: channel::FileChannel::new(file),
+			dev_fuse: dev_fuse.to_path_buf(),
+			mount_target: None,
+			unmount_helper: None,
+			connection_id,
+		}
+	}
+
+	#[cfg(any(
+		feature = "libc_fuse_mount",
+		feature = "nightly_syscall_fuse_mount",
+	))]
+	pub(super) fn set_unmount_helper(
+		mut self,
+		helper_path: &path::Path,
+	) -> Self {
+		self.unmount_helper = Some(helper_path.to_path_buf());
+		self
+	}
+
+	#[cfg(all(
+		feature = "tokio",
+		any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount"
+		),
+	))]
+	pub(super) fn file(&self) -> &fs::File {
+		self.channel.file()
+	}
+
+	// Opens a new session on the same FUSE connection, so that the clone
+	// has its own queue of requests being processed.
+	//
+	// Kernels older than Linux 4.2 don't support `FUSE_DEV_IOC_CLONE`, in
+	// which case the file descriptor is duplicated instead.
+	#[cfg(any(
+		feature = "libc_fuse_mount",
+		feature = "nightly_syscall_fuse_mount",
+	))]
+	fn clone_session(&self) -> Result<DevFuseChannel, io::Error> {
+		use std::os::unix::io::AsRawFd;
+
+		let file = fs::OpenOptions::new()
+			.read(true)
+			.write(true)
+			.open(&self.dev_fuse)?;
+		let session_fd = self.channel.file().as_raw_fd() as u32;
+		match dev_ioc_clone(file.as_raw_fd(), session_fd) {
+			Ok(()) => Ok(Self {
+				channel: channel::FileChannel::new(file),
+				dev_fuse: self.dev_fuse.clone(),
+				mount_target: self.mount_target.clone(),
+				unmount_helper: self.unmount_helper.clone(),
+				connection_id: self.connection_id,
+			}),
+			Err(err) => match err.raw_os_error() {
+				Some(ENOTTY) | Some(EINVAL) => Ok(Self {
+					channel: self.channel.try_clone()?,
+					dev_fuse: self.dev_fuse.clone(),
+					mount_target: self.mount_target.clone(),
+					unmount_helper: self.unmount_helper.clone(),
+					connection_id: self.connection_id,
+				}),
+				_ => Err(err),
+			},
+		}
+	}
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn dev_ioc_clone(fd: i32, session_fd: u32) -> Result<(), io::Error> {
+	use crate::internal::fuse_kernel::FUSE_DEV_IOC_CLONE;
+
+	let rc = unsafe {
+		libc::ioctl(fd, FUSE_DEV_IOC_CLONE as _, &session_fd as *const u32)
+	};
+	if rc != 0 {
+		return Err(io::Error::last_os_error());
 	}
+	Ok(())
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+fn dev_ioc_clone(fd: i32, session_fd: u32) -> Result<(), io::Error> {
+	use crate::internal::fuse_kernel::FUSE_DEV_IOC_CLONE;
+
+	super::linux_syscalls::ioctl(
+		fd,
+		FUSE_DEV_IOC_CLONE,
+		&session_fd as *const u32 as usize,
+	)
 }
 
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+const ENOTTY: i32 = 25;
+
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+const EINVAL: i32 = 22;
+
 impl channel::Channel for DevFuseChannel {
 	type Error = io::Error;
 
 	fn send(&self, buf: &[u8]) -> Result<(), io::Error> {
-		self.0.send(buf)
+		self.channel.send(buf)
 	}
 
 	fn send_vectored<const N: usize>(
 		&self,
 		bufs: &[&[u8]; N],
 	) -> Result<(), io::Error> {
-		self.0.send_vectored(bufs)
+		self.channel.send_vectored(bufs)
+	}
+
+	fn send_vectored_slices(
+		&self,
+		bufs: &[io::IoSlice],
+	) -> Result<(), io::Error> {
+		self.channel.send_vectored_slices(bufs)
 	}
 
 	fn receive(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
-		self.0.receive(buf)
+		self.channel.receive(buf)
+	}
+
+	/// Splices the payload into the channel without copying it into
+	/// userspace, falling back to `pread()` if `fd` doesn't support
+	/// splicing.
+	#[cfg(any(
+		feature = "libc_fuse_mount",
+		feature = "nightly_syscall_fuse_mount",
+	))]
+	fn send_from_fd(
+		&self,
+		response: &channel::FdResponse,
+	) -> Result<(), io::Error> {
+		if super::splice::send_from_fd(self.channel.file(), response)? {
+			return Ok(());
+		}
+		response.send_buffered(self)
+	}
+
+	/// Splices requests into a pipe, leaving the payload of large
+	/// `FUSE_WRITE` requests in the pipe.
+	///
+	/// Falls back to [`receive`](#method.receive) if requests can't be
+	/// spliced, for example if the pipe can't be made large enough for the
+	/// read buffer.
+	#[cfg(any(
+		feature = "libc_fuse_mount",
+		feature = "nightly_syscall_fuse_mount",
+	))]
+	fn receive_spliced(
+		&self,
+		buf: &mut [u8],
+	) -> Result<(usize, Option<RawFd>), io::Error> {
+		match super::splice::receive(self.channel.file(), buf)? {
+			Some(received) => Ok(received),
+			None => Ok((self.channel.receive(buf)?, None)),
+		}
 	}
 }
 
 impl server::ServerChannel for DevFuseChannel {
+	/// Opens a new session on the same FUSE connection, using the
+	/// `FUSE_DEV_IOC_CLONE` ioctl.
+	///
+	/// Executors running on cloned channels each have their own queue of
+	/// requests being processed, which reduces contention when many
+	/// executors run in parallel.
 	fn try_clone(&self) -> Result<Self, io::Error> {
-		Ok(DevFuseChannel(self.0.try_clone()?))
+		#[cfg(any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount",
+		))]
+		return self.clone_session();
+
+		#[cfg(not(any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount",
+		)))]
+		Ok(Self {
+			channel: self.channel.try_clone()?,
+		})
+	}
+}
+
+impl fuse_server::FuseServerChannel for DevFuseChannel {
+	/// Lazily unmounts the filesystem with `umount2(MNT_DETACH)`, or with
+	/// the helper's lazy unmount if the filesystem was mounted by
+	/// [`FusermountFuseMount`](struct.FusermountFuseMount.html).
+	///
+	/// The mount is removed from the filesystem namespace immediately, and
+	/// the session ends once no files are open. Until then, requests already
+	/// received can still be answered.
+	///
+	/// Channels of detached mounts (see
+	/// [`DetachedFuseMount::into_channel`](struct.DetachedFuseMount.html#method.into_channel))
+	/// don't unmount anything.
+	fn shutdown(&self) -> Result<(), io::Error> {
+		#[cfg(any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount",
+		))]
+		{
+			// Detached mounts are unmounted by whoever attached them.
+			if let Some(mount_target) = &self.mount_target {
+				if let Some(helper_path) = &self.unmount_helper {
+					super::fuse_mount::fusermount_unmount(
+						helper_path,
+						mount_target,
+					)?;
+				} else {
+					match umount_detach(mount_target) {
+						// Already unmounted.
+						Err(err) if err.raw_os_error() == Some(EINVAL) => {},
+						result => result?,
+					}
+				}
+			}
+		}
+		Ok(())
 	}
+
+	/// Aborts the FUSE connection by writing to its `abort` file in
+	/// `/sys/fs/fuse/connections/`.
+	///
+	/// Executors blocked in `receive()` then fail with `ENODEV` and return,
+	/// and requests still waiting for a response fail with `ENOTCONN`.
+	/// Aborting is skipped if the FUSE control filesystem isn't mounted.
+	fn abort(&self) -> Result<(), io::Error> {
+		#[cfg(any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount",
+		))]
+		if let Some(connection_id) = self.connection_id {
+			return abort_connection(connection_id);
+		}
+		Ok(())
+	}
+}
+
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+const MNT_DETACH: u32 = 2;
+
+#[cfg(feature = "libc_fuse_mount")]
+fn umount_detach(mount_target: &path::Path) -> Result<(), io::Error> {
+	let target = cstr_from_path(mount_target)?;
+	let rc = unsafe { libc::umount2(target.as_ptr(), MNT_DETACH as i32) };
+	if rc != 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(())
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+fn umount_detach(mount_target: &path::Path) -> Result<(), io::Error> {
+	let target = cstr_from_path(mount_target)?;
+	super::linux_syscalls::umount2(&target, MNT_DETACH)
+}
+
+// Aborts a FUSE connection through the FUSE control filesystem.
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+fn abort_connection(connection_id: u64) -> Result<(), io::Error> {
+	let abort_path =
+		format!("/sys/fs/fuse/connections/{}/abort", connection_id);
+	match fs::write(abort_path, b"1") {
+		// The control filesystem isn't mounted, or the connection has
+		// already ended.
+		Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+		result => result,
+	}
+}
+
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+const AT_FDCWD: RawFd = -100;
+
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+const AT_EMPTY_PATH: u32 = 0x1000;
+
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+const AT_STATX_DONT_SYNC: u32 = 0x4000;
+
+// Returns the ID of the FUSE connection serving the filesystem at `path`,
+// which is the device number of its superblock.
+//
+// The attributes are read with `AT_STATX_DONT_SYNC` so that the kernel
+// doesn't send `FUSE_GETATTR`, which would block until the session has been
+// initialized.
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+fn connection_id(
+	dirfd: RawFd,
+	path: &std::ffi::CStr,
+	flags: u32,
+) -> Result<u64, io::Error> {
+	// `struct statx` is 256 bytes, with `stx_dev_major` and `stx_dev_minor`
+	// at offsets 136 and 140.
+	let mut statx_buf = [0u32; 64];
+	statx(dirfd, path, flags | AT_STATX_DONT_SYNC, &mut statx_buf)?;
+	let (major, minor) = (statx_buf[34] as u64, statx_buf[35] as u64);
+	// The kernel names connections by its internal encoding of `dev_t`.
+	Ok((major << 20) | minor)
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn statx(
+	dirfd: RawFd,
+	path: &std::ffi::CStr,
+	flags: u32,
+	statx_buf: &mut [u32; 64],
+) -> Result<(), io::Error> {
+	let rc = unsafe {
+		libc::syscall(
+			libc::SYS_statx,
+			dirfd,
+			path.as_ptr(),
+			flags,
+			0u32,
+			statx_buf.as_mut_ptr(),
+		)
+	};
+	if rc != 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(())
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+fn statx(
+	dirfd: RawFd,
+	path: &std::ffi::CStr,
+	flags: u32,
+	statx_buf: &mut [u32; 64],
+) -> Result<(), io::Error> {
+	super::linux_syscalls::statx(
+		dirfd,
+		path,
+		flags,
+		0,
+		statx_buf.as_mut_ptr() as usize,
+	)
 }
 
-impl fuse_server::FuseServerChannel for DevFuseChannel {}
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+fn cstr_from_path(path: &path::Path) -> Result<std::ffi::CString, io::Error> {
+	use std::os::unix::ffi::OsStrExt;
+
+	std::ffi::CString::new(path.as_os_str().as_bytes())
+		.map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+}
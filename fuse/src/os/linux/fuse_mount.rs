@@ -14,19 +14,59 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use std::ffi::{CString, OsStr, OsString};
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::io::Read;
 use std::os::unix::ffi::OsStrExt;
 use std::os::unix::fs::MetadataExt;
-use std::os::unix::io::{AsRawFd, RawFd};
-use std::{fs, io, path};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt;
+use std::{fs, io, mem, path, process};
 
 #[cfg(feature = "nightly_syscall_fuse_mount")]
 use super::linux_syscalls as syscalls;
 use super::DevFuseChannel;
 use super::FuseMount;
 
+#[cfg(all(rust_fuse_test = "fuse_mount_test", feature = "libc_fuse_mount"))]
+#[path = "fuse_mount_test.rs"]
+mod fuse_mount_test;
+
+const MS_RDONLY: u32 = 0x1;
 const MS_NOSUID: u32 = 0x2;
 const MS_NODEV: u32 = 0x4;
+const MS_NOEXEC: u32 = 0x8;
+const MS_SYNCHRONOUS: u32 = 0x10;
+const MS_DIRSYNC: u32 = 0x80;
+const MS_NOATIME: u32 = 0x400;
+const MS_NODIRATIME: u32 = 0x800;
+const MS_RELATIME: u32 = 0x200000;
+const MS_STRICTATIME: u32 = 0x1000000;
+const MS_LAZYTIME: u32 = 0x2000000;
+
+const MOUNT_ATTR_RDONLY: u32 = 0x1;
+const MOUNT_ATTR_NOSUID: u32 = 0x2;
+const MOUNT_ATTR_NODEV: u32 = 0x4;
+const MOUNT_ATTR_NOEXEC: u32 = 0x8;
+const MOUNT_ATTR_NOATIME: u32 = 0x10;
+const MOUNT_ATTR_STRICTATIME: u32 = 0x20;
+const MOUNT_ATTR_NODIRATIME: u32 = 0x80;
+
+const FSOPEN_CLOEXEC: u32 = 0x1;
+const FSMOUNT_CLOEXEC: u32 = 0x1;
+const FSCONFIG_SET_FLAG: u32 = 0;
+const FSCONFIG_SET_STRING: u32 = 1;
+const FSCONFIG_CMD_CREATE: u32 = 6;
+const MOVE_MOUNT_F_EMPTY_PATH: u32 = 0x4;
+const AT_FDCWD: i32 = -100;
+
+const S_IFDIR: u32 = 0o040000;
+
+const F_SETFD: u32 = 2;
+const EINTR: i32 = 4;
+const SOL_SOCKET: i32 = 1;
+const SCM_RIGHTS: i32 = 1;
+const MSG_CMSG_CLOEXEC: u32 = 0x40000000;
 
 // This is technically incorrect, because Linux can be compiled with
 // different page sizes (and often is on e.g. ARM). But we're using this value
@@ -42,8 +82,39 @@ struct FuseMountOptions {
 	user_id: Option<u32>,
 	group_id: Option<u32>,
 	root_mode: Option<u32>,
+	fuseblk: bool,
+	allow_other: bool,
+	default_permissions: bool,
+	max_read: Option<u32>,
+	blksize: Option<u32>,
+	extra_options: Vec<OsString>,
 }
 
+// Options set by `FuseMountOptions` itself, which can't be passed as extra
+// options. `fsname` and `blkdev` are how `fusermount3` spells the mount
+// source and `fuseblk`.
+const RESERVED_OPTIONS: &[&str] = &[
+	"fd",
+	"rootmode",
+	"user_id",
+	"group_id",
+	"allow_other",
+	"default_permissions",
+	"max_read",
+	"blksize",
+	"source",
+	"subtype",
+	"fsname",
+	"blkdev",
+];
+
+// Options that `fusermount3` turns into mount flags, which would conflict
+// with the flags set by `FuseMountOptions`.
+const MOUNT_FLAG_OPTIONS: &[&str] = &[
+	"ro", "rw", "suid", "nosuid", "dev", "nodev", "exec", "noexec", "atime",
+	"noatime",
+];
+
 impl FuseMountOptions {
 	fn new() -> FuseMountOptions {
 		Self {
@@ -54,7 +125,78 @@ impl FuseMountOptions {
 			user_id: None,
 			group_id: None,
 			root_mode: None,
+			fuseblk: false,
+			allow_other: false,
+			default_permissions: false,
+			max_read: None,
+			blksize: None,
+			extra_options: Vec::new(),
+		}
+	}
+
+	fn set_mount_flag(&mut self, flag: u32, enabled: bool) {
+		if enabled {
+			self.mount_flags |= flag;
+		} else {
+			self.mount_flags &= !flag;
+		}
+	}
+
+	fn validate(&self) -> Result<(), io::Error> {
+		let invalid =
+			|msg: String| Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+
+		if self.fuseblk && self.mount_source.is_empty() {
+			return invalid(
+				"fuseblk mounts require a block device as the mount source"
+					.to_string(),
+			);
+		}
+		if let Some(blksize) = self.blksize {
+			if !self.fuseblk {
+				return invalid("blksize requires a fuseblk mount".to_string());
+			}
+			let max_blksize = PAGE_SIZE as u32;
+			if !blksize.is_power_of_two()
+				|| blksize < 512
+				|| blksize > max_blksize
+			{
+				return invalid(format!(
+					"blksize ({}) must be a power of two between 512 and {}",
+					blksize, max_blksize,
+				));
+			}
 		}
+		if self.max_read == Some(0) {
+			return invalid("max_read must be greater than zero".to_string());
+		}
+
+		for option in &self.extra_options {
+			let bytes = option.as_bytes();
+			if bytes.is_empty() || bytes.contains(&b',') {
+				return invalid(format!(
+					"invalid mount option {:?}: must be a single option",
+					option,
+				));
+			}
+			let key = bytes.split(|&b| b == b'=').next().unwrap_or(bytes);
+			let mut reserved =
+				RESERVED_OPTIONS.iter().chain(MOUNT_FLAG_OPTIONS);
+			if reserved.any(|name| name.as_bytes() == key) {
+				return invalid(format!(
+					"mount option {:?} can't be set as an extra option",
+					option,
+				));
+			}
+		}
+		Ok(())
+	}
+
+	fn fs_type(&self) -> &'static str {
+		if self.fuseblk {
+			return "fuseblk";
+		}
+		"fuse"
 	}
 
 	fn mount_source_cstr(&self) -> Result<CString, io::Error> {
@@ -68,10 +210,11 @@ impl FuseMountOptions {
 	fn mount_type_cstr(&self) -> Result<CString, io::Error> {
 		let subtype = &self.mount_subtype;
 		if subtype == "" {
-			return Ok(CString::new("fuse").unwrap());
+			return Ok(CString::new(self.fs_type()).unwrap());
 		}
 
-		let mut buf = OsString::from("fuse.");
+		let mut buf = OsString::from(self.fs_type());
+		buf.push(".");
 		buf.push(subtype);
 		cstr_from_osstr(&buf)
 	}
@@ -87,7 +230,24 @@ impl FuseMountOptions {
 		out.push(format!("fd={},rootmode={:o}", fd, root_mode));
 		out.push(format!("user_id={}", user_id));
 		out.push(format!("group_id={}", group_id));
-		let joined = CString::new(out.join(",")).unwrap();
+		if self.allow_other {
+			out.push("allow_other".to_string());
+		}
+		if self.default_permissions {
+			out.push("default_permissions".to_string());
+		}
+		if let Some(max_read) = self.max_read {
+			out.push(format!("max_read={}", max_read));
+		}
+		if let Some(blksize) = self.blksize {
+			out.push(format!("blksize={}", blksize));
+		}
+		let mut data = out.join(",").into_bytes();
+		for option in &self.extra_options {
+			data.push(b',');
+			data.extend_from_slice(option.as_bytes());
+		}
+		let joined = cstr_from_osstr(OsStr::from_bytes(&data))?;
 
 		let data_length = joined.as_bytes_with_nul().len();
 		if data_length >= PAGE_SIZE {
@@ -105,6 +265,130 @@ impl FuseMountOptions {
 	}
 }
 
+// Setters shared by all mount implementations. `$options` names the field
+// holding the `FuseMountOptions`.
+macro_rules! fuse_mount_options_setters {
+	($options:tt) => {
+		/// Sets the mount source, which is the `fsname` option of libfuse.
+		pub fn set_mount_source(
+			mut self,
+			mount_source: impl AsRef<OsStr>,
+		) -> Self {
+			self.$options.mount_source = mount_source.as_ref().to_os_string();
+			self
+		}
+
+		pub fn set_mount_subtype(
+			mut self,
+			mount_subtype: impl AsRef<OsStr>,
+		) -> Self {
+			self.$options.mount_subtype = mount_subtype.as_ref().to_os_string();
+			self
+		}
+
+		/// Mounts a `fuseblk` filesystem, backed by the block device given as
+		/// the mount source.
+		pub fn set_fuseblk(mut self, fuseblk: bool) -> Self {
+			self.$options.fuseblk = fuseblk;
+			self
+		}
+
+		/// Allows users other than the mount owner to access the filesystem.
+		pub fn set_allow_other(mut self, allow_other: bool) -> Self {
+			self.$options.allow_other = allow_other;
+			self
+		}
+
+		/// Enables permission checking by the kernel, based on file modes.
+		pub fn set_default_permissions(
+			mut self,
+			default_permissions: bool,
+		) -> Self {
+			self.$options.default_permissions = default_permissions;
+			self
+		}
+
+		/// Sets the maximum size of a read request. Must be greater than
+		/// zero.
+		pub fn set_max_read(mut self, max_read: u32) -> Self {
+			self.$options.max_read = Some(max_read);
+			self
+		}
+
+		/// Sets the filesystem block size. Only valid for `fuseblk` mounts.
+		pub fn set_blksize(mut self, blksize: u32) -> Self {
+			self.$options.blksize = Some(blksize);
+			self
+		}
+
+		pub fn set_read_only(mut self, read_only: bool) -> Self {
+			self.$options.set_mount_flag(MS_RDONLY, read_only);
+			self
+		}
+
+		pub fn set_noexec(mut self, noexec: bool) -> Self {
+			self.$options.set_mount_flag(MS_NOEXEC, noexec);
+			self
+		}
+
+		/// Enabled by default.
+		pub fn set_nosuid(mut self, nosuid: bool) -> Self {
+			self.$options.set_mount_flag(MS_NOSUID, nosuid);
+			self
+		}
+
+		/// Enabled by default.
+		pub fn set_nodev(mut self, nodev: bool) -> Self {
+			self.$options.set_mount_flag(MS_NODEV, nodev);
+			self
+		}
+
+		pub fn set_noatime(mut self, noatime: bool) -> Self {
+			self.$options.set_mount_flag(MS_NOATIME, noatime);
+			self
+		}
+
+		/// Adds a filesystem option that doesn't have a typed setter, in either
+		/// `"name"` or `"name=value"` form.
+		pub fn add_mount_option(mut self, option: impl AsRef<OsStr>) -> Self {
+			self.$options
+				.extra_options
+				.push(option.as_ref().to_os_string());
+			self
+		}
+	};
+}
+
+// Setters for options that the kernel accepts but `fusermount3` chooses for
+// itself.
+macro_rules! kernel_mount_options_setters {
+	($options:tt) => {
+		pub fn set_user_id(mut self, uid: u32) -> Self {
+			self.$options.user_id = Some(uid);
+			self
+		}
+
+		pub fn set_group_id(mut self, gid: u32) -> Self {
+			self.$options.group_id = Some(gid);
+			self
+		}
+
+		pub fn set_root_mode(mut self, mode: u32) -> Self {
+			self.$options.root_mode = Some(mode);
+			self
+		}
+
+		/// Sets the path of the FUSE device, which defaults to `/dev/fuse`.
+		pub fn set_dev_fuse(
+			mut self,
+			dev_fuse: impl AsRef<path::Path>,
+		) -> Self {
+			self.$options.dev_fuse = dev_fuse.as_ref().to_path_buf();
+			self
+		}
+	};
+}
+
 #[cfg(any(doc, feature = "libc_fuse_mount"))]
 #[cfg_attr(doc, doc(cfg(feature = "libc_fuse_mount")))]
 pub struct LibcFuseMount(FuseMountOptions);
@@ -115,38 +399,13 @@ impl LibcFuseMount {
 		Self(FuseMountOptions::new())
 	}
 
-	pub fn set_mount_source(mut self, mount_source: impl AsRef<OsStr>) -> Self {
-		self.0.mount_source = mount_source.as_ref().to_os_string();
-		self
-	}
-
-	pub fn set_mount_subtype(
-		mut self,
-		mount_subtype: impl AsRef<OsStr>,
-	) -> Self {
-		self.0.mount_subtype = mount_subtype.as_ref().to_os_string();
-		self
-	}
+	fuse_mount_options_setters!(0);
+	kernel_mount_options_setters!(0);
 
 	pub fn set_mount_flags(mut self, mount_flags: u32) -> Self {
 		self.0.mount_flags = mount_flags;
 		self
 	}
-
-	pub fn set_user_id(mut self, uid: u32) -> Self {
-		self.0.user_id = Some(uid);
-		self
-	}
-
-	pub fn set_group_id(mut self, gid: u32) -> Self {
-		self.0.group_id = Some(gid);
-		self
-	}
-
-	pub fn set_root_mode(mut self, mode: u32) -> Self {
-		self.0.root_mode = Some(mode);
-		self
-	}
 }
 
 #[cfg(any(doc, feature = "libc_fuse_mount"))]
@@ -157,6 +416,7 @@ impl FuseMount for LibcFuseMount {
 		self,
 		mount_target: &path::Path,
 	) -> io::Result<DevFuseChannel> {
+		self.0.validate()?;
 		let mount_target_cstr = cstr_from_osstr(mount_target.as_os_str())?;
 		let mount_source_cstr = self.0.mount_source_cstr()?;
 		let mount_type_cstr = self.0.mount_type_cstr()?;
@@ -193,7 +453,7 @@ impl FuseMount for LibcFuseMount {
 				return Err(std::io::Error::last_os_error());
 			}
 		};
-		Ok(DevFuseChannel::new(file))
+		Ok(DevFuseChannel::new(file, &self.0.dev_fuse, mount_target))
 	}
 }
 
@@ -207,38 +467,13 @@ impl SyscallFuseMount {
 		Self(FuseMountOptions::new())
 	}
 
-	pub fn set_mount_source(mut self, mount_source: impl AsRef<OsStr>) -> Self {
-		self.0.mount_source = mount_source.as_ref().to_os_string();
-		self
-	}
-
-	pub fn set_mount_subtype(
-		mut self,
-		mount_subtype: impl AsRef<OsStr>,
-	) -> Self {
-		self.0.mount_subtype = mount_subtype.as_ref().to_os_string();
-		self
-	}
+	fuse_mount_options_setters!(0);
+	kernel_mount_options_setters!(0);
 
 	pub fn set_mount_flags(mut self, mount_flags: u32) -> Self {
 		self.0.mount_flags = mount_flags;
 		self
 	}
-
-	pub fn set_user_id(mut self, uid: u32) -> Self {
-		self.0.user_id = Some(uid);
-		self
-	}
-
-	pub fn set_group_id(mut self, gid: u32) -> Self {
-		self.0.group_id = Some(gid);
-		self
-	}
-
-	pub fn set_root_mode(mut self, mode: u32) -> Self {
-		self.0.root_mode = Some(mode);
-		self
-	}
 }
 
 #[cfg(any(doc, feature = "nightly_syscall_fuse_mount"))]
@@ -249,6 +484,7 @@ impl FuseMount for SyscallFuseMount {
 		self,
 		mount_target: &path::Path,
 	) -> io::Result<DevFuseChannel> {
+		self.0.validate()?;
 		let mount_target_cstr = cstr_from_osstr(mount_target.as_os_str())?;
 		let mount_source_cstr = self.0.mount_source_cstr()?;
 		let mount_type_cstr = self.0.mount_type_cstr()?;
@@ -279,8 +515,672 @@ impl FuseMount for SyscallFuseMount {
 			mount_data.to_bytes_with_nul(),
 		)?;
 
-		Ok(DevFuseChannel::new(file))
+		Ok(DevFuseChannel::new(file, &self.0.dev_fuse, mount_target))
+	}
+}
+
+/// Mounts a FUSE filesystem by running a setuid helper compatible with
+/// libfuse's `fusermount3`.
+///
+/// The helper opens `/dev/fuse`, performs the mount, and passes the file
+/// descriptor back over a Unix socket. This allows filesystems to be mounted
+/// by users without `CAP_SYS_ADMIN`. The same helper is used to unmount the
+/// filesystem when the channel is shut down.
+///
+/// Mount options are passed to the helper with `-o`. The helper chooses the
+/// root mode, user ID, and group ID itself, and may refuse options that
+/// unprivileged users aren't allowed to set (such as `allow_other` without
+/// `user_allow_other` in `/etc/fuse.conf`).
+pub struct FusermountFuseMount {
+	options: FuseMountOptions,
+	helper_path: path::PathBuf,
+}
+
+impl FusermountFuseMount {
+	pub fn new() -> FusermountFuseMount {
+		Self {
+			options: FuseMountOptions::new(),
+			helper_path: path::PathBuf::from("fusermount3"),
+		}
+	}
+
+	/// Sets the path of the helper binary.
+	///
+	/// Defaults to `fusermount3`, which is looked up in `$PATH`.
+	pub fn set_helper_path(
+		mut self,
+		helper_path: impl AsRef<path::Path>,
+	) -> Self {
+		self.helper_path = helper_path.as_ref().to_path_buf();
+		self
+	}
+
+	fuse_mount_options_setters!(options);
+
+	fn helper_options(&self) -> OsString {
+		let options = &self.options;
+		let mut out = Vec::new();
+		if !options.mount_source.is_empty() {
+			let mut opt = OsString::from("fsname=");
+			opt.push(escape_helper_option(&options.mount_source));
+			out.push(opt);
+		}
+		if !options.mount_subtype.is_empty() {
+			let mut opt = OsString::from("subtype=");
+			opt.push(escape_helper_option(&options.mount_subtype));
+			out.push(opt);
+		}
+		if options.fuseblk {
+			out.push(OsString::from("blkdev"));
+		}
+		for &(flag, set, unset) in HELPER_MOUNT_FLAGS {
+			let name = if options.mount_flags & flag == 0 {
+				unset
+			} else {
+				set
+			};
+			if !name.is_empty() {
+				out.push(OsString::from(name));
+			}
+		}
+		if options.allow_other {
+			out.push(OsString::from("allow_other"));
+		}
+		if options.default_permissions {
+			out.push(OsString::from("default_permissions"));
+		}
+		if let Some(max_read) = options.max_read {
+			out.push(OsString::from(format!("max_read={}", max_read)));
+		}
+		if let Some(blksize) = options.blksize {
+			out.push(OsString::from(format!("blksize={}", blksize)));
+		}
+		for option in &options.extra_options {
+			out.push(escape_helper_option(option));
+		}
+		out.join(OsStr::new(","))
+	}
+}
+
+impl Default for FusermountFuseMount {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl FuseMount for FusermountFuseMount {
+	type Channel = DevFuseChannel;
+
+	fn fuse_mount(
+		self,
+		mount_target: &path::Path,
+	) -> io::Result<DevFuseChannel> {
+		let (socket, helper_socket) = UnixStream::pair()?;
+		let helper_fd = helper_socket.as_raw_fd();
+
+		self.options.validate()?;
+		let mut cmd = process::Command::new(&self.helper_path);
+		cmd.arg("-o").arg(self.helper_options());
+		cmd.arg("--").arg(mount_target);
+		cmd.env("_FUSE_COMMFD", helper_fd.to_string());
+		unsafe {
+			// The helper's end of the socket must survive `exec()`.
+			cmd.pre_exec(move || {
+				super::splice::fcntl(helper_fd, F_SETFD, 0)?;
+				Ok(())
+			});
+		}
+
+		let mut child = cmd.spawn().map_err(|err| {
+			io::Error::new(
+				err.kind(),
+				format!("failed to run {:?}: {}", self.helper_path, err),
+			)
+		})?;
+		drop(helper_socket);
+
+		// If the helper fails it exits without sending a file descriptor,
+		// closing its end of the socket.
+		let received = recv_fd(&socket);
+		let status = child.wait()?;
+		if !status.success() {
+			return Err(io::Error::other(format!(
+				"{:?} failed: {}",
+				self.helper_path, status
+			)));
+		}
+		let file = match received? {
+			Some(file) => file,
+			None => {
+				return Err(io::Error::other(format!(
+					"{:?} exited without sending a file descriptor",
+					self.helper_path,
+				)));
+			},
+		};
+
+		Ok(
+			DevFuseChannel::new(file, &self.options.dev_fuse, mount_target)
+				.set_unmount_helper(&self.helper_path),
+		)
+	}
+}
+
+pub(super) fn fusermount_unmount(
+	helper_path: &path::Path,
+	mount_target: &path::Path,
+) -> io::Result<()> {
+	let status = process::Command::new(helper_path)
+		.args(["-u", "-q", "-z", "--"])
+		.arg(mount_target)
+		.status()?;
+	if !status.success() {
+		return Err(io::Error::other(format!(
+			"{:?} failed: {}",
+			helper_path, status
+		)));
+	}
+	Ok(())
+}
+
+// Mount flags that can be set by `FusermountFuseMount`, with the helper's
+// option names for when the flag is set and unset. The helper defaults to
+// `nosuid,nodev`, so those are passed either way.
+const HELPER_MOUNT_FLAGS: &[(u32, &str, &str)] = &[
+	(MS_RDONLY, "ro", ""),
+	(MS_NOEXEC, "noexec", ""),
+	(MS_NOSUID, "nosuid", "suid"),
+	(MS_NODEV, "nodev", "dev"),
+	(MS_NOATIME, "noatime", ""),
+];
+
+fn escape_helper_option(value: &OsStr) -> OsString {
+	let mut escaped = Vec::with_capacity(value.len());
+	for &b in value.as_bytes() {
+		if b == b',' || b == b'\\' {
+			escaped.push(b'\\');
+		}
+		escaped.push(b);
+	}
+	OsString::from(OsStr::from_bytes(&escaped))
+}
+
+// Kernel ABI for `recvmsg(2)`, which matches the libc definitions on Linux.
+#[repr(C)]
+struct IoVec {
+	base: *mut u8,
+	len: usize,
+}
+
+#[repr(C)]
+struct MsgHdr {
+	name: *mut u8,
+	namelen: u32,
+	iov: *mut IoVec,
+	iovlen: usize,
+	control: *mut u8,
+	controllen: usize,
+	flags: i32,
+}
+
+#[repr(C)]
+struct CmsgHdr {
+	len: usize,
+	level: i32,
+	ty: i32,
+}
+
+fn recv_fd(socket: &UnixStream) -> io::Result<Option<fs::File>> {
+	const CMSG_HDR_LEN: usize = mem::size_of::<CmsgHdr>();
+
+	let mut data = [0u8; 1];
+	let mut iov = IoVec {
+		base: data.as_mut_ptr(),
+		len: data.len(),
+	};
+	let mut control = [0usize; 4];
+	let mut msg = MsgHdr {
+		name: core::ptr::null_mut(),
+		namelen: 0,
+		iov: &mut iov,
+		iovlen: 1,
+		control: control.as_mut_ptr() as *mut u8,
+		controllen: mem::size_of_val(&control),
+		flags: 0,
+	};
+
+	loop {
+		match recvmsg(socket.as_raw_fd(), &mut msg, MSG_CMSG_CLOEXEC) {
+			Err(err) if err.raw_os_error() == Some(EINTR) => continue,
+			Err(err) => return Err(err),
+			Ok(_) => break,
+		}
+	}
+
+	if msg.controllen < CMSG_HDR_LEN + mem::size_of::<i32>() {
+		return Ok(None);
+	}
+	let cmsg = unsafe { &*(control.as_ptr() as *const CmsgHdr) };
+	if cmsg.level != SOL_SOCKET || cmsg.ty != SCM_RIGHTS {
+		return Ok(None);
+	}
+	let fd = unsafe {
+		let fd_ptr = (control.as_ptr() as *const u8).add(CMSG_HDR_LEN);
+		core::ptr::read_unaligned(fd_ptr as *const i32)
+	};
+	Ok(Some(unsafe { fs::File::from_raw_fd(fd) }))
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn recvmsg(fd: RawFd, msg: &mut MsgHdr, flags: u32) -> io::Result<usize> {
+	let msg_ptr = msg as *mut MsgHdr as *mut libc::msghdr;
+	let rc = unsafe { libc::recvmsg(fd, msg_ptr, flags as i32) };
+	if rc < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(rc as usize)
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+fn recvmsg(fd: RawFd, msg: &mut MsgHdr, flags: u32) -> io::Result<usize> {
+	syscalls::recvmsg(fd, msg as *mut MsgHdr as usize, flags)
+}
+
+/// Mounts a FUSE filesystem with the `fsopen()` family of syscalls.
+///
+/// Unlike `mount(2)`, errors include the kernel's description of what went
+/// wrong, and the filesystem can be mounted without attaching it to a
+/// mount target (see [`fuse_mount_detached`](#method.fuse_mount_detached)).
+///
+/// Requires Linux 5.2 or later.
+pub struct FsmountFuseMount(FuseMountOptions);
+
+impl FsmountFuseMount {
+	pub fn new() -> FsmountFuseMount {
+		Self(FuseMountOptions::new())
+	}
+
+	fuse_mount_options_setters!(0);
+	kernel_mount_options_setters!(0);
+
+	/// Sets the mount flags, using the same `MS_*` values as `mount(2)`.
+	///
+	/// Flags that have no equivalent in the `fsmount()` API cause mounting
+	/// to fail with `InvalidInput`.
+	pub fn set_mount_flags(mut self, mount_flags: u32) -> Self {
+		self.0.mount_flags = mount_flags;
+		self
+	}
+
+	/// Creates the mount without attaching it to the filesystem tree.
+	///
+	/// The root mode defaults to a directory if not set.
+	pub fn fuse_mount_detached(self) -> io::Result<DetachedFuseMount> {
+		self.0.validate()?;
+		let (attr_flags, sb_flags) = fsmount_flags(self.0.mount_flags)?;
+		let root_mode = self.0.root_mode.unwrap_or(S_IFDIR);
+
+		let file = fs::OpenOptions::new()
+			.read(true)
+			.write(true)
+			.open(&self.0.dev_fuse)?;
+		let fd = file.as_raw_fd();
+
+		let user_id = self.0.user_id.unwrap_or_else(getuid);
+		let group_id = self.0.group_id.unwrap_or_else(getgid);
+
+		let fs_context = fsopen(&CString::new(self.0.fs_type()).unwrap())?;
+		let set_flag = |key: &[u8]| {
+			let key = CString::new(key).unwrap();
+			fsconfig(&fs_context, FSCONFIG_SET_FLAG, Some(&key), None)
+		};
+		let set_string = |key: &[u8], value: &CStr| {
+			let key = CString::new(key).unwrap();
+			fsconfig(&fs_context, FSCONFIG_SET_STRING, Some(&key), Some(value))
+		};
+		let set_number = |key: &str, value: String| {
+			set_string(key.as_bytes(), &CString::new(value).unwrap())
+		};
+
+		set_string(b"source", &self.0.mount_source_cstr()?)?;
+		if !self.0.mount_subtype.is_empty() {
+			set_string(b"subtype", &cstr_from_osstr(&self.0.mount_subtype)?)?;
+		}
+		set_number("fd", fd.to_string())?;
+		set_number("rootmode", format!("{:o}", root_mode))?;
+		set_number("user_id", user_id.to_string())?;
+		set_number("group_id", group_id.to_string())?;
+		if self.0.allow_other {
+			set_flag(b"allow_other")?;
+		}
+		if self.0.default_permissions {
+			set_flag(b"default_permissions")?;
+		}
+		if let Some(max_read) = self.0.max_read {
+			set_number("max_read", max_read.to_string())?;
+		}
+		if let Some(blksize) = self.0.blksize {
+			set_number("blksize", blksize.to_string())?;
+		}
+		for option in &self.0.extra_options {
+			let option = cstr_from_osstr(option)?;
+			let option = option.as_bytes();
+			match option.iter().position(|&b| b == b'=') {
+				Some(idx) => {
+					let value = CString::new(&option[idx + 1..]).unwrap();
+					set_string(&option[..idx], &value)?;
+				},
+				None => set_flag(option)?,
+			}
+		}
+		for flag in sb_flags {
+			set_flag(flag.as_bytes())?;
+		}
+		fsconfig(&fs_context, FSCONFIG_CMD_CREATE, None, None)?;
+
+		let mount = fsmount(&fs_context, FSMOUNT_CLOEXEC, attr_flags)?;
+		Ok(DetachedFuseMount {
+			file,
+			dev_fuse: self.0.dev_fuse,
+			mount,
+		})
+	}
+}
+
+impl Default for FsmountFuseMount {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl FuseMount for FsmountFuseMount {
+	type Channel = DevFuseChannel;
+
+	fn fuse_mount(
+		mut self,
+		mount_target: &path::Path,
+	) -> io::Result<DevFuseChannel> {
+		if self.0.root_mode.is_none() {
+			let meta = fs::metadata(mount_target)?;
+			self.0.root_mode = Some(meta.mode());
+		}
+		self.fuse_mount_detached()?.attach(mount_target)
+	}
+}
+
+/// A FUSE mount that isn't attached to the filesystem tree.
+///
+/// The mount can be attached with [`attach`](#method.attach), or its file
+/// descriptor can be passed to another process (for example one running in
+/// a different mount namespace) which attaches it with `move_mount()`.
+///
+/// The mount is destroyed if all of its file descriptors are closed before
+/// it has been attached.
+pub struct DetachedFuseMount {
+	file: fs::File,
+	dev_fuse: path::PathBuf,
+	mount: fs::File,
+}
+
+impl DetachedFuseMount {
+	/// Attaches the mount at `mount_target`, returning a channel that will
+	/// unmount it on shutdown.
+	pub fn attach(
+		self,
+		mount_target: &path::Path,
+	) -> io::Result<DevFuseChannel> {
+		let empty_path = CString::new("").unwrap();
+		let mount_target_cstr = cstr_from_osstr(mount_target.as_os_str())?;
+		move_mount(
+			self.mount.as_raw_fd(),
+			&empty_path,
+			AT_FDCWD,
+			&mount_target_cstr,
+			MOVE_MOUNT_F_EMPTY_PATH,
+		)?;
+		Ok(DevFuseChannel::new(self.file, &self.dev_fuse, mount_target))
+	}
+
+	/// Returns a channel for serving the mount, which is attached by some
+	/// other process.
+	///
+	/// Shutting down the channel doesn't unmount the filesystem, but it does
+	/// abort the FUSE connection.
+	pub fn into_channel(self) -> DevFuseChannel {
+		DevFuseChannel::new_detached(self.file, &self.dev_fuse, &self.mount)
+	}
+}
+
+impl AsRawFd for DetachedFuseMount {
+	/// Returns the mount's file descriptor, as used by `move_mount()`.
+	fn as_raw_fd(&self) -> RawFd {
+		self.mount.as_raw_fd()
+	}
+}
+
+// Splits `mount(2)` flags into `MOUNT_ATTR_*` flags for `fsmount()` and
+// superblock flags for `fsconfig()`.
+fn fsmount_flags(mount_flags: u32) -> io::Result<(u32, Vec<&'static str>)> {
+	const ATTR_FLAGS: &[(u32, u32)] = &[
+		(MS_RDONLY, MOUNT_ATTR_RDONLY),
+		(MS_NOSUID, MOUNT_ATTR_NOSUID),
+		(MS_NODEV, MOUNT_ATTR_NODEV),
+		(MS_NOEXEC, MOUNT_ATTR_NOEXEC),
+		(MS_NOATIME, MOUNT_ATTR_NOATIME),
+		(MS_NODIRATIME, MOUNT_ATTR_NODIRATIME),
+		(MS_STRICTATIME, MOUNT_ATTR_STRICTATIME),
+		// Relative atime is the default for `fsmount()`.
+		(MS_RELATIME, 0),
+	];
+	const SB_FLAGS: &[(u32, &str)] = &[
+		(MS_RDONLY, "ro"),
+		(MS_SYNCHRONOUS, "sync"),
+		(MS_DIRSYNC, "dirsync"),
+		(MS_LAZYTIME, "lazytime"),
+	];
+
+	let mut remaining = mount_flags;
+	let mut attr_flags = 0;
+	for &(ms_flag, attr_flag) in ATTR_FLAGS {
+		if mount_flags & ms_flag != 0 {
+			attr_flags |= attr_flag;
+			remaining &= !ms_flag;
+		}
+	}
+	let mut sb_flags = Vec::new();
+	for &(ms_flag, name) in SB_FLAGS {
+		if mount_flags & ms_flag != 0 {
+			sb_flags.push(name);
+			remaining &= !ms_flag;
+		}
 	}
+	if remaining != 0 {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("unsupported mount flags for fsmount(): {:#X}", remaining),
+		));
+	}
+	Ok((attr_flags, sb_flags))
+}
+
+// Adds the messages logged to a filesystem context to an error returned by
+// `fsconfig()` or `fsmount()`.
+fn fs_context_error(fs_context: &fs::File, err: io::Error) -> io::Error {
+	let mut messages = Vec::new();
+	let mut buf = [0u8; 1024];
+	// Each read returns one message, until the log is empty (`ENODATA`).
+	while let Ok(len) = (&*fs_context).read(&mut buf) {
+		if len == 0 {
+			break;
+		}
+		let message = String::from_utf8_lossy(&buf[..len]);
+		let message = message.trim_end();
+		// Messages are prefixed by their level: "e", "w" or "i".
+		let message = match message.get(..2) {
+			Some("e ") | Some("w ") | Some("i ") => &message[2..],
+			_ => message,
+		};
+		messages.push(message.to_string());
+	}
+	if messages.is_empty() {
+		return err;
+	}
+	io::Error::new(err.kind(), format!("{} ({})", messages.join("; "), err))
+}
+
+fn fsconfig(
+	fs_context: &fs::File,
+	cmd: u32,
+	key: Option<&CStr>,
+	value: Option<&CStr>,
+) -> io::Result<()> {
+	sys_fsconfig(fs_context.as_raw_fd(), cmd, key, value)
+		.map_err(|err| fs_context_error(fs_context, err))
+}
+
+fn fsmount(
+	fs_context: &fs::File,
+	flags: u32,
+	attr_flags: u32,
+) -> io::Result<fs::File> {
+	let fd = sys_fsmount(fs_context.as_raw_fd(), flags, attr_flags)
+		.map_err(|err| fs_context_error(fs_context, err))?;
+	Ok(unsafe { fs::File::from_raw_fd(fd) })
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn getuid() -> u32 {
+	unsafe { libc::getuid() }
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn getgid() -> u32 {
+	unsafe { libc::getgid() }
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn fsopen(fsname: &CStr) -> io::Result<fs::File> {
+	let rc = unsafe {
+		libc::syscall(libc::SYS_fsopen, fsname.as_ptr(), FSOPEN_CLOEXEC)
+	};
+	if rc < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(unsafe { fs::File::from_raw_fd(rc as RawFd) })
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn sys_fsconfig(
+	fd: RawFd,
+	cmd: u32,
+	key: Option<&CStr>,
+	value: Option<&CStr>,
+) -> io::Result<()> {
+	let key = key.map_or(core::ptr::null(), |key| key.as_ptr());
+	let value = value.map_or(core::ptr::null(), |value| value.as_ptr());
+	let rc = unsafe {
+		libc::syscall(libc::SYS_fsconfig, fd, cmd, key, value, 0 as libc::c_int)
+	};
+	if rc < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(())
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn sys_fsmount(fd: RawFd, flags: u32, attr_flags: u32) -> io::Result<RawFd> {
+	let rc = unsafe { libc::syscall(libc::SYS_fsmount, fd, flags, attr_flags) };
+	if rc < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(rc as RawFd)
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn move_mount(
+	from_dirfd: RawFd,
+	from_path: &CStr,
+	to_dirfd: RawFd,
+	to_path: &CStr,
+	flags: u32,
+) -> io::Result<()> {
+	let rc = unsafe {
+		libc::syscall(
+			libc::SYS_move_mount,
+			from_dirfd,
+			from_path.as_ptr(),
+			to_dirfd,
+			to_path.as_ptr(),
+			flags,
+		)
+	};
+	if rc < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(())
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+fn getuid() -> u32 {
+	syscalls::getuid()
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+fn getgid() -> u32 {
+	syscalls::getgid()
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+fn fsopen(fsname: &CStr) -> io::Result<fs::File> {
+	let fd = syscalls::fsopen(fsname, FSOPEN_CLOEXEC)?;
+	Ok(unsafe { fs::File::from_raw_fd(fd) })
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+fn sys_fsconfig(
+	fd: RawFd,
+	cmd: u32,
+	key: Option<&CStr>,
+	value: Option<&CStr>,
+) -> io::Result<()> {
+	syscalls::fsconfig(fd, cmd, key, value, 0)
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+fn sys_fsmount(fd: RawFd, flags: u32, attr_flags: u32) -> io::Result<RawFd> {
+	syscalls::fsmount(fd, flags, attr_flags)
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+fn move_mount(
+	from_dirfd: RawFd,
+	from_path: &CStr,
+	to_dirfd: RawFd,
+	to_path: &CStr,
+	flags: u32,
+) -> io::Result<()> {
+	syscalls::move_mount(from_dirfd, from_path, to_dirfd, to_path, flags)
 }
 
 fn cstr_from_osstr(x: &OsStr) -> Result<CString, io::Error> {
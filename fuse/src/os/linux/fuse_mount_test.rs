@@ -0,0 +1,407 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::ffi::OsString;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::UnixDatagram;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{env, fs, io, mem, path, process};
+
+use super::{
+	fs_context_error,
+	fsmount_flags,
+	FuseMount,
+	FuseMountOptions,
+	FusermountFuseMount,
+	MOUNT_ATTR_NODEV,
+	MOUNT_ATTR_NOEXEC,
+	MOUNT_ATTR_NOSUID,
+	MOUNT_ATTR_RDONLY,
+	MS_DIRSYNC,
+	MS_NODEV,
+	MS_NOEXEC,
+	MS_NOSUID,
+	MS_RDONLY,
+	MS_RELATIME,
+};
+
+fn validate_err(options: &FuseMountOptions) -> String {
+	let err = options.validate().unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+	err.to_string()
+}
+
+#[test]
+fn validate_blksize() {
+	let mut options = FuseMountOptions::new();
+	options.blksize = Some(4096);
+	assert_eq!(validate_err(&options), "blksize requires a fuseblk mount");
+
+	options.fuseblk = true;
+	options.mount_source = OsString::from("/dev/loop0");
+	assert!(options.validate().is_ok());
+
+	for &blksize in &[256, 1000, 8192] {
+		options.blksize = Some(blksize);
+		assert_eq!(
+			validate_err(&options),
+			format!(
+				"blksize ({}) must be a power of two between 512 and 4096",
+				blksize,
+			),
+		);
+	}
+}
+
+#[test]
+fn validate_fuseblk_source() {
+	let mut options = FuseMountOptions::new();
+	options.fuseblk = true;
+	assert_eq!(
+		validate_err(&options),
+		"fuseblk mounts require a block device as the mount source",
+	);
+}
+
+#[test]
+fn validate_max_read() {
+	let mut options = FuseMountOptions::new();
+	options.max_read = Some(0);
+	assert_eq!(validate_err(&options), "max_read must be greater than zero");
+
+	options.max_read = Some(4096);
+	assert!(options.validate().is_ok());
+}
+
+#[test]
+fn validate_extra_options() {
+	let mut options = FuseMountOptions::new();
+	options.extra_options.push(OsString::from("nonempty"));
+	options
+		.extra_options
+		.push(OsString::from("max_background=10"));
+	assert!(options.validate().is_ok());
+
+	let reserved = &[
+		"fd=3",
+		"rootmode=40755",
+		"allow_other",
+		"source=x",
+		"fsname=x",
+		"blkdev",
+		"ro",
+		"rw",
+		"suid",
+		"nosuid",
+		"dev",
+		"nodev",
+		"exec",
+		"noexec",
+		"atime",
+		"noatime",
+	];
+	for &option in reserved {
+		let mut options = FuseMountOptions::new();
+		options.extra_options.push(OsString::from(option));
+		assert_eq!(
+			validate_err(&options),
+			format!(
+				"mount option {:?} can't be set as an extra option",
+				option
+			),
+		);
+	}
+
+	for &option in &["", "a,b", "max_read=1,allow_other"] {
+		let mut options = FuseMountOptions::new();
+		options.extra_options.push(OsString::from(option));
+		assert_eq!(
+			validate_err(&options),
+			format!(
+				"invalid mount option {:?}: must be a single option",
+				option
+			),
+		);
+	}
+}
+
+#[test]
+fn mount_data() {
+	let options = FuseMountOptions::new();
+	let data = options.mount_data(3, 0o40755, 1000, 100).unwrap();
+	assert_eq!(
+		data.to_str().unwrap(),
+		"fd=3,rootmode=40755,user_id=1000,group_id=100",
+	);
+
+	let mut options = FuseMountOptions::new();
+	options.fuseblk = true;
+	options.allow_other = true;
+	options.default_permissions = true;
+	options.max_read = Some(65536);
+	options.blksize = Some(1024);
+	options.extra_options.push(OsString::from("nonempty"));
+	options
+		.extra_options
+		.push(OsString::from("max_background=10"));
+	let data = options.mount_data(3, 0o100644, 0, 0).unwrap();
+	assert_eq!(
+		data.to_str().unwrap(),
+		concat!(
+			"fd=3,rootmode=100644,user_id=0,group_id=0,allow_other,",
+			"default_permissions,max_read=65536,blksize=1024,nonempty,",
+			"max_background=10",
+		),
+	);
+}
+
+#[test]
+fn mount_data_too_long() {
+	let mut options = FuseMountOptions::new();
+	options.extra_options.push(OsString::from("x".repeat(4096)));
+	let err = options.mount_data(3, 0o40755, 0, 0).unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn fsmount_flags_default() {
+	let (attr_flags, sb_flags) = fsmount_flags(MS_NOSUID | MS_NODEV).unwrap();
+	assert_eq!(attr_flags, MOUNT_ATTR_NOSUID | MOUNT_ATTR_NODEV);
+	assert!(sb_flags.is_empty());
+}
+
+#[test]
+fn fsmount_flags_read_only() {
+	// Read-only mounts set both the mount attribute and the superblock flag.
+	let flags = MS_RDONLY | MS_NOEXEC | MS_RELATIME | MS_DIRSYNC;
+	let (attr_flags, sb_flags) = fsmount_flags(flags).unwrap();
+	assert_eq!(attr_flags, MOUNT_ATTR_RDONLY | MOUNT_ATTR_NOEXEC);
+	assert_eq!(sb_flags, &["ro", "dirsync"]);
+}
+
+#[test]
+fn fsmount_flags_unsupported() {
+	// MS_REMOUNT | MS_BIND
+	let err = fsmount_flags(MS_NOSUID | 0x20 | 0x1000).unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+	assert_eq!(
+		err.to_string(),
+		"unsupported mount flags for fsmount(): 0x1020",
+	);
+}
+
+// Datagrams stand in for the filesystem context's log, which returns one
+// message per read.
+fn fs_context_log(messages: &[&str]) -> fs::File {
+	let (log, writer) = UnixDatagram::pair().unwrap();
+	for message in messages {
+		writer.send(message.as_bytes()).unwrap();
+	}
+	log.set_nonblocking(true).unwrap();
+	unsafe { fs::File::from_raw_fd(log.into_raw_fd()) }
+}
+
+#[test]
+fn fs_context_error_messages() {
+	let log = fs_context_log(&[
+		"e fuse: Unknown parameter 'bogus'\n",
+		"w fuse: second message",
+	]);
+	let err = io::Error::from_raw_os_error(22);
+	let err = fs_context_error(&log, err);
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+	assert_eq!(
+		err.to_string(),
+		format!(
+			"fuse: Unknown parameter 'bogus'; fuse: second message ({})",
+			io::Error::from_raw_os_error(22),
+		),
+	);
+}
+
+#[test]
+fn fs_context_error_empty_log() {
+	let log = fs_context_log(&[]);
+	let err = fs_context_error(&log, io::Error::from_raw_os_error(22));
+	assert_eq!(err.raw_os_error(), Some(22));
+}
+
+const STAND_IN_HELPER: &str =
+	"os::linux::fuse_mount::fuse_mount_test::stand_in_helper";
+
+// Not a test: the stand-in helper script runs the test binary with this
+// test selected to send `/dev/null` over the socket named by `_FUSE_COMMFD`.
+#[test]
+#[ignore]
+fn stand_in_helper() {
+	let fd: RawFd = match env::var("_FUSE_COMMFD") {
+		Ok(fd) => fd.parse().unwrap(),
+		Err(_) => return,
+	};
+	let file = fs::File::open("/dev/null").unwrap();
+	send_fd(fd, file.as_raw_fd()).unwrap();
+}
+
+#[test]
+fn fusermount_success() {
+	let dir = TempDir::new();
+	let args_path = dir.path().join("args");
+	let helper = dir.write_helper(&format!(
+		concat!(
+			"printf '%s\\n' \"$@\" > '{}'\n",
+			"exec '{}' --ignored --exact {} -q >/dev/null\n",
+		),
+		args_path.display(),
+		env::current_exe().unwrap().display(),
+		STAND_IN_HELPER,
+	));
+	let target = dir.path().join("mnt");
+	fs::create_dir(&target).unwrap();
+
+	let channel = FusermountFuseMount::new()
+		.set_helper_path(&helper)
+		.set_mount_source("source,name")
+		.set_mount_subtype("test")
+		.set_fuseblk(true)
+		.set_read_only(true)
+		.set_nodev(false)
+		.set_allow_other(true)
+		.set_max_read(4096)
+		.set_blksize(1024)
+		.add_mount_option("nonempty")
+		.fuse_mount(&target)
+		.unwrap();
+	drop(channel);
+
+	let args = fs::read_to_string(&args_path).unwrap();
+	assert_eq!(
+		args,
+		format!(
+			concat!(
+				"-o\n",
+				"fsname=source\\,name,subtype=test,blkdev,ro,nosuid,dev,",
+				"allow_other,max_read=4096,blksize=1024,nonempty\n",
+				"--\n{}\n",
+			),
+			target.display(),
+		),
+	);
+}
+
+#[test]
+fn fusermount_invalid_options() {
+	let dir = TempDir::new();
+	let helper = dir.write_helper("exit 0\n");
+
+	let err = FusermountFuseMount::new()
+		.set_helper_path(&helper)
+		.set_blksize(1024)
+		.fuse_mount(dir.path())
+		.err()
+		.unwrap();
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn fusermount_failed() {
+	let dir = TempDir::new();
+	let helper = dir.write_helper("exit 3\n");
+
+	let err = FusermountFuseMount::new()
+		.set_helper_path(&helper)
+		.fuse_mount(dir.path())
+		.err()
+		.unwrap();
+	assert_eq!(
+		err.to_string(),
+		format!("{:?} failed: exit status: 3", helper),
+	);
+}
+
+#[test]
+fn fusermount_no_file_descriptor() {
+	let dir = TempDir::new();
+	let helper = dir.write_helper("exit 0\n");
+
+	let err = FusermountFuseMount::new()
+		.set_helper_path(&helper)
+		.fuse_mount(dir.path())
+		.err()
+		.unwrap();
+	assert_eq!(
+		err.to_string(),
+		format!("{:?} exited without sending a file descriptor", helper),
+	);
+}
+
+struct TempDir(path::PathBuf);
+
+impl TempDir {
+	fn new() -> TempDir {
+		static COUNTER: AtomicUsize = AtomicUsize::new(0);
+		let path = env::temp_dir().join(format!(
+			"fuse_mount_test.{}.{}",
+			process::id(),
+			COUNTER.fetch_add(1, Ordering::SeqCst),
+		));
+		fs::create_dir(&path).unwrap();
+		TempDir(path)
+	}
+
+	fn path(&self) -> &path::Path {
+		&self.0
+	}
+
+	fn write_helper(&self, script: &str) -> path::PathBuf {
+		let path = self.0.join("fusermount");
+		fs::write(&path, format!("#!/bin/sh\n{}", script)).unwrap();
+		fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+		path
+	}
+}
+
+impl Drop for TempDir {
+	fn drop(&mut self) {
+		let _ = fs::remove_dir_all(&self.0);
+	}
+}
+
+fn send_fd(socket: RawFd, fd: RawFd) -> io::Result<()> {
+	let mut data = [0u8; 1];
+	let mut iov = libc::iovec {
+		iov_base: data.as_mut_ptr() as *mut libc::c_void,
+		iov_len: data.len(),
+	};
+	let mut control = [0usize; 4];
+	unsafe {
+		let mut msg: libc::msghdr = mem::zeroed();
+		msg.msg_iov = &mut iov;
+		msg.msg_iovlen = 1;
+		msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+		msg.msg_controllen =
+			libc::CMSG_SPACE(mem::size_of::<RawFd>() as u32) as _;
+		let cmsg = libc::CMSG_FIRSTHDR(&msg);
+		(*cmsg).cmsg_level = libc::SOL_SOCKET;
+		(*cmsg).cmsg_type = libc::SCM_RIGHTS;
+		(*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as u32) as _;
+		core::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd);
+		if libc::sendmsg(socket, &msg, 0) < 0 {
+			return Err(io::Error::last_os_error());
+		}
+	}
+	Ok(())
+}
@@ -14,6 +14,11 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(all(
+	feature = "tokio",
+	any(feature = "libc_fuse_mount", feature = "nightly_syscall_fuse_mount"),
+))]
+use std::io;
 use std::path;
 
 use crate::channel::Channel;
@@ -37,6 +42,7 @@ pub struct FuseServerBuilder<Mount, Handlers, Hooks> {
 	mount: Mount,
 	handlers: Handlers,
 	hooks: Option<Hooks>,
+	splice_writes: bool,
 }
 
 impl<Handlers> FuseServerBuilder<(), Handlers, server::NoopServerHooks> {
@@ -49,6 +55,7 @@ impl<Handlers> FuseServerBuilder<(), Handlers, server::NoopServerHooks> {
 			mount: (),
 			handlers,
 			hooks: None,
+			splice_writes: false,
 		}
 	}
 }
@@ -63,6 +70,7 @@ impl<Mount, Handlers, Hooks> FuseServerBuilder<Mount, Handlers, Hooks> {
 			mount: self.mount,
 			handlers: self.handlers,
 			hooks: Some(hooks),
+			splice_writes: self.splice_writes,
 		}
 	}
 
@@ -75,8 +83,17 @@ impl<Mount, Handlers, Hooks> FuseServerBuilder<Mount, Handlers, Hooks> {
 			mount,
 			handlers: self.handlers,
 			hooks: self.hooks,
+			splice_writes: self.splice_writes,
 		}
 	}
+
+	/// See [`fuse::FuseServerBuilder::set_splice_writes`].
+	///
+	/// [`fuse::FuseServerBuilder::set_splice_writes`]: ../../struct.FuseServerBuilder.html#method.set_splice_writes
+	pub fn set_splice_writes(mut self, splice_writes: bool) -> Self {
+		self.splice_writes = splice_writes;
+		self
+	}
 }
 
 impl<M, Handlers, Hooks> FuseServerBuilder<M, Handlers, Hooks>
@@ -97,6 +114,53 @@ where
 		if let Some(hooks) = self.hooks {
 			builder = builder.set_hooks(hooks);
 		}
+		builder = builder.set_splice_writes(self.splice_writes);
 		builder.build()
 	}
 }
+
+#[cfg(all(
+	feature = "tokio",
+	any(feature = "libc_fuse_mount", feature = "nightly_syscall_fuse_mount"),
+))]
+impl<M, Handlers, Hooks> FuseServerBuilder<M, Handlers, Hooks>
+where
+	M: FuseMount<Channel = super::DevFuseChannel>,
+	Handlers: FuseHandlers,
+	Hooks: server::ServerHooks,
+{
+	/// Like [`build`], but returns a server whose channel is registered with
+	/// the current Tokio runtime. Its executors can be run with
+	/// [`FuseServerExecutor::run_tokio`].
+	///
+	/// The `FUSE_INIT` handshake is performed before the channel is switched
+	/// into non-blocking mode. The kernel sends `FUSE_INIT` as soon as the
+	/// filesystem is mounted, so the calling thread is not blocked for long.
+	///
+	/// Returns an error of kind [`InvalidInput`] if splicing writes was
+	/// enabled with [`set_splice_writes`], which Tokio servers don't support.
+	///
+	/// # Panics
+	///
+	/// Panics if called outside of a Tokio runtime.
+	///
+	/// [`build`]: #method.build
+	/// [`FuseServerExecutor::run_tokio`]: ../../struct.FuseServerExecutor.html#method.run_tokio
+	/// [`InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+	/// [`set_splice_writes`]: #method.set_splice_writes
+	#[cfg_attr(doc, doc(cfg(feature = "tokio")))]
+	pub fn build_tokio(
+		self,
+	) -> Result<
+		FuseServer<super::TokioDevFuseChannel, Handlers, Hooks>,
+		io::Error,
+	> {
+		if self.splice_writes {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"splice_writes is not supported by build_tokio()",
+			));
+		}
+		self.build()?.map_channel(super::TokioDevFuseChannel::new)
+	}
+}
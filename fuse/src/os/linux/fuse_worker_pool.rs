@@ -0,0 +1,488 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use std::{io, panic, thread};
+
+use crate::fuse_handlers::FuseHandlers;
+use crate::fuse_server::{
+	FuseServer,
+	FuseServerChannel,
+	FuseServerExecutor,
+	ShutdownHandle,
+};
+use crate::server;
+
+#[cfg(rust_fuse_test = "fuse_worker_pool_test")]
+#[path = "fuse_worker_pool_test.rs"]
+mod fuse_worker_pool_test;
+
+// FuseWorkerPoolBuilder {{{
+
+/// Builder for a [`FuseWorkerPool`].
+///
+/// [`FuseWorkerPool`]: struct.FuseWorkerPool.html
+#[cfg_attr(doc, doc(cfg(feature = "respond_async")))]
+pub struct FuseWorkerPoolBuilder<Channel, Handlers, Hooks> {
+	server: FuseServer<Channel, Handlers, Hooks>,
+	num_workers: usize,
+	cpu_affinity: Vec<usize>,
+}
+
+impl<C, Handlers, Hooks> FuseWorkerPoolBuilder<C, Handlers, Hooks>
+where
+	C: FuseServerChannel + Send + Sync + 'static,
+	C::Error: From<io::Error> + Send + 'static,
+	Handlers: FuseHandlers + Send + Sync + 'static,
+	Hooks: server::ServerHooks + Send + Sync + 'static,
+{
+	pub fn new(
+		server: FuseServer<C, Handlers, Hooks>,
+	) -> FuseWorkerPoolBuilder<C, Handlers, Hooks> {
+		Self {
+			server,
+			num_workers: 1,
+			cpu_affinity: Vec::new(),
+		}
+	}
+
+	/// Sets the number of worker threads. Each worker has its own channel,
+	/// created with [`ServerChannel::try_clone`].
+	///
+	/// The default is one worker. A value of zero is treated as one.
+	///
+	/// [`ServerChannel::try_clone`]: ../../io/trait.ServerChannel.html#tymethod.try_clone
+	pub fn set_num_workers(mut self, num_workers: usize) -> Self {
+		self.num_workers = num_workers.max(1);
+		self
+	}
+
+	/// Pins worker threads to the given CPUs. Worker `N` is pinned to
+	/// `cpus[N % cpus.len()]`.
+	///
+	/// By default worker threads are not pinned.
+	#[cfg(any(
+		doc,
+		feature = "libc_fuse_mount",
+		feature = "nightly_syscall_fuse_mount",
+	))]
+	#[cfg_attr(
+		doc,
+		doc(cfg(any(
+			feature = "libc_fuse_mount",
+			feature = "nightly_syscall_fuse_mount",
+		)))
+	)]
+	pub fn set_cpu_affinity(mut self, cpus: &[usize]) -> Self {
+		self.cpu_affinity = cpus.to_vec();
+		self
+	}
+
+	/// Creates a channel for each worker, then starts the worker threads.
+	///
+	/// If a channel can't be created, no threads are started and the error
+	/// is returned. If a thread can't be started, the workers that did start
+	/// are stopped and joined before the error is returned. The session is
+	/// not ended in either case.
+	pub fn spawn(
+		mut self,
+	) -> Result<FuseWorkerPool<C, Handlers, Hooks>, C::Error> {
+		let mut executors = Vec::with_capacity(self.num_workers);
+		for _ in 0..self.num_workers {
+			executors.push(self.server.new_executor()?);
+		}
+
+		let state = Arc::new(PoolState::new(executors.len()));
+		let shutdown = FuseWorkerPoolShutdownHandle {
+			shutdown: self.server.shutdown_handle(),
+			state: state.clone(),
+		};
+		let mut workers = Vec::with_capacity(executors.len());
+		for (idx, executor) in executors.into_iter().enumerate() {
+			let cpu = match self.cpu_affinity.len() {
+				0 => None,
+				len => Some(self.cpu_affinity[idx % len]),
+			};
+			let worker_state = state.clone();
+			let spawned = thread::Builder::new()
+				.name(format!("fuse-worker-{}", idx))
+				.spawn(move || run_worker(executor, idx, cpu, &worker_state));
+			match spawned {
+				Ok(handle) => workers.push(handle),
+				Err(err) => {
+					// Workers haven't been released yet, so they stop
+					// without waiting for a request.
+					self.server.executor_mut().stop_all();
+					state.release();
+					for worker in workers {
+						let _ = worker.join();
+					}
+					return Err(err.into());
+				},
+			}
+		}
+		state.release();
+
+		Ok(FuseWorkerPool {
+			server: self.server,
+			workers,
+			state,
+			shutdown,
+		})
+	}
+}
+
+// }}}
+
+// FuseWorkerPool {{{
+
+/// A set of threads serving one FUSE session in parallel.
+///
+/// Workers run until the session ends. If any worker fails, the other
+/// workers are asked to stop but the session is not ended, so the filesystem
+/// stays mounted until it's unmounted by the caller.
+#[cfg_attr(doc, doc(cfg(feature = "respond_async")))]
+pub struct FuseWorkerPool<Channel, Handlers, Hooks>
+where
+	Channel: FuseServerChannel,
+{
+	server: FuseServer<Channel, Handlers, Hooks>,
+	workers: Vec<thread::JoinHandle<()>>,
+	state: Arc<PoolState<Channel::Error>>,
+	shutdown: FuseWorkerPoolShutdownHandle<Channel>,
+}
+
+impl<C, Handlers, Hooks> FuseWorkerPool<C, Handlers, Hooks>
+where
+	C: FuseServerChannel,
+{
+	/// Waits for all workers to stop, or for any worker to fail.
+	///
+	/// Workers are joined in the order they were started. After a worker
+	/// fails, workers still waiting for a request are not joined, because
+	/// they can't be woken without ending the session. They stop once the
+	/// session is ended, for example by
+	/// [`FuseWorkerPoolShutdownHandle::shutdown`]. [`FuseHandlers::destroy`]
+	/// is called after the last worker has stopped, if it was not already
+	/// called.
+	///
+	/// Returns the first error encountered by any worker, unless the worker
+	/// results were already returned by
+	/// [`FuseWorkerPoolShutdownHandle::shutdown`]. If a
+	/// worker panicked, the panic is resumed after all workers have stopped.
+	///
+	/// [`FuseHandlers::destroy`]: ../../trait.FuseHandlers.html#method.destroy
+	/// [`FuseWorkerPoolShutdownHandle::shutdown`]: struct.FuseWorkerPoolShutdownHandle.html#method.shutdown
+	pub fn join(self) -> Result<(), C::Error> {
+		let stopped = {
+			let results = self.state.results.lock().unwrap();
+			let results = self
+				.state
+				.finished
+				.wait_while(results, |r| r.running > 0 && !r.failed)
+				.unwrap();
+			results.stopped.clone()
+		};
+		let mut panic_payload = None;
+		for (worker, stopped) in self.workers.into_iter().zip(stopped) {
+			if !stopped {
+				continue;
+			}
+			if let Err(payload) = worker.join() {
+				panic_payload.get_or_insert(payload);
+			}
+		}
+		drop(self.server);
+
+		if let Some(payload) = panic_payload {
+			panic::resume_unwind(payload);
+		}
+		let mut results = self.state.results.lock().unwrap();
+		match results.first_error.take() {
+			Some(idx) => results.workers[idx].take().unwrap_or(Ok(())),
+			None => Ok(()),
+		}
+	}
+
+	/// Returns a handle that can stop the pool from another thread.
+	pub fn shutdown_handle(&self) -> FuseWorkerPoolShutdownHandle<C> {
+		self.shutdown.clone()
+	}
+}
+
+// }}}
+
+// FuseWorkerPoolShutdownHandle {{{
+
+/// Stops a [`FuseWorkerPool`] from any thread.
+///
+/// [`FuseWorkerPool`]: struct.FuseWorkerPool.html
+#[cfg_attr(doc, doc(cfg(feature = "respond_async")))]
+pub struct FuseWorkerPoolShutdownHandle<Channel>
+where
+	Channel: FuseServerChannel,
+{
+	shutdown: ShutdownHandle<Channel>,
+	state: Arc<PoolState<Channel::Error>>,
+}
+
+impl<C> FuseWorkerPoolShutdownHandle<C>
+where
+	C: FuseServerChannel,
+	C::Error: From<io::Error>,
+{
+	/// Stops the worker pool and returns the result of each worker.
+	///
+	/// Workers are asked to stop before their next request, then the session
+	/// is ended with [`FuseServerChannel::shutdown`] so that workers blocked
+	/// waiting for a request wake up. Requests still being handled with
+	/// [`RespondAsync`] are given until `timeout` to send their reply, after
+	/// which the session is aborted with [`FuseServerChannel::abort`]. Errors
+	/// from aborting the session are ignored. The workers are then given the
+	/// rest of `timeout` to stop.
+	///
+	/// Results are returned in worker order. A worker that panicked or was
+	/// never started has no result; its panic is resumed by
+	/// [`FuseWorkerPool::join`]. Results can only be returned once, later
+	/// calls return an empty `Vec`.
+	///
+	/// Returns an error of kind [`TimedOut`] if requests in flight were not
+	/// answered, or the workers did not stop, within `timeout`.
+	///
+	/// [`FuseServerChannel::shutdown`]: ../../io/trait.FuseServerChannel.html#method.shutdown
+	/// [`FuseServerChannel::abort`]: ../../io/trait.FuseServerChannel.html#method.abort
+	/// [`RespondAsync`]: ../../struct.RespondAsync.html
+	/// [`FuseWorkerPool::join`]: struct.FuseWorkerPool.html#method.join
+	/// [`TimedOut`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.TimedOut
+	pub fn shutdown(
+		&self,
+		timeout: Duration,
+	) -> Result<Vec<Result<(), C::Error>>, C::Error> {
+		let deadline = Instant::now() + timeout;
+		self.shutdown.shutdown(timeout)?;
+
+		let remaining = deadline.saturating_duration_since(Instant::now());
+		let results = self.state.results.lock().unwrap();
+		let (mut results, wait) = self
+			.state
+			.finished
+			.wait_timeout_while(results, remaining, |r| r.running > 0)
+			.unwrap();
+		if wait.timed_out() {
+			return Err(timed_out("workers did not stop"));
+		}
+
+		results.first_error = None;
+		Ok(results
+			.workers
+			.iter_mut()
+			.filter_map(Option::take)
+			.collect())
+	}
+}
+
+impl<C> Clone for FuseWorkerPoolShutdownHandle<C>
+where
+	C: FuseServerChannel,
+{
+	fn clone(&self) -> Self {
+		Self {
+			shutdown: self.shutdown.clone(),
+			state: self.state.clone(),
+		}
+	}
+}
+
+impl<C> core::fmt::Debug for FuseWorkerPoolShutdownHandle<C>
+where
+	C: FuseServerChannel,
+{
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		fmt.debug_struct("FuseWorkerPoolShutdownHandle").finish()
+	}
+}
+
+fn timed_out<E: From<io::Error>>(msg: &str) -> E {
+	io::Error::new(io::ErrorKind::TimedOut, msg).into()
+}
+
+// }}}
+
+struct PoolState<E> {
+	results: Mutex<PoolResults<E>>,
+	finished: Condvar,
+	// Set by `spawn()` once every worker has started, so that a worker
+	// doesn't wait for a request before it's known whether the pool will
+	// run.
+	released: Mutex<bool>,
+	release: Condvar,
+}
+
+struct PoolResults<E> {
+	// One entry per worker, filled in when the worker stops.
+	workers: Vec<Option<Result<(), E>>>,
+	// Whether each worker has stopped, including workers that panicked.
+	stopped: Vec<bool>,
+	// Index of the first worker to fail.
+	first_error: Option<usize>,
+	// Whether any worker failed or panicked.
+	failed: bool,
+	running: usize,
+}
+
+impl<E> PoolState<E> {
+	fn new(num_workers: usize) -> PoolState<E> {
+		let mut workers = Vec::with_capacity(num_workers);
+		workers.resize_with(num_workers, || None);
+		Self {
+			results: Mutex::new(PoolResults {
+				workers,
+				stopped: vec![false; num_workers],
+				first_error: None,
+				failed: false,
+				running: num_workers,
+			}),
+			finished: Condvar::new(),
+			released: Mutex::new(false),
+			release: Condvar::new(),
+		}
+	}
+
+	fn release(&self) {
+		*self.released.lock().unwrap() = true;
+		self.release.notify_all();
+	}
+
+	fn wait_released(&self) {
+		let released = self.released.lock().unwrap();
+		drop(self.release.wait_while(released, |r| !*r).unwrap());
+	}
+
+	// Records the result of worker `idx`, or `None` if it panicked.
+	fn finish(&self, idx: usize, result: Option<Result<(), E>>) {
+		let mut results = self.results.lock().unwrap();
+		match &result {
+			Some(Ok(())) => {},
+			Some(Err(_)) => {
+				results.first_error.get_or_insert(idx);
+				results.failed = true;
+			},
+			None => results.failed = true,
+		}
+		results.workers[idx] = result;
+		results.stopped[idx] = true;
+		results.running -= 1;
+		if results.running == 0 || results.failed {
+			self.finished.notify_all();
+		}
+	}
+}
+
+fn run_worker<C, Handlers, Hooks>(
+	mut executor: FuseServerExecutor<C, Handlers, Hooks>,
+	idx: usize,
+	cpu: Option<usize>,
+	state: &PoolState<C::Error>,
+) where
+	C: FuseServerChannel + Send + Sync + 'static,
+	C::Error: From<io::Error>,
+	Handlers: FuseHandlers,
+	Hooks: server::ServerHooks + Send + Sync + 'static,
+{
+	state.wait_released();
+	let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+		if let Some(cpu) = cpu {
+			set_cpu_affinity(cpu)?;
+		}
+		executor.run()
+	}));
+	match result {
+		Ok(Ok(())) => state.finish(idx, Some(Ok(()))),
+		Ok(Err(err)) => {
+			executor.stop_all();
+			state.finish(idx, Some(Err(err)));
+		},
+		Err(payload) => {
+			executor.stop_all();
+			state.finish(idx, None);
+			panic::resume_unwind(payload);
+		},
+	}
+}
+
+// Matches the size of glibc's `cpu_set_t`, which the kernel accepts for any
+// `CONFIG_NR_CPUS` up to 1024.
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+const CPU_SET_BITS: usize = 1024;
+
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+const USIZE_BITS: usize = 8 * core::mem::size_of::<usize>();
+
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+fn cpu_mask(cpu: usize) -> io::Result<[usize; CPU_SET_BITS / USIZE_BITS]> {
+	if cpu >= CPU_SET_BITS {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("CPU index {} exceeds {}", cpu, CPU_SET_BITS - 1),
+		));
+	}
+	let mut mask = [0usize; CPU_SET_BITS / USIZE_BITS];
+	mask[cpu / USIZE_BITS] |= 1 << (cpu % USIZE_BITS);
+	Ok(mask)
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn set_cpu_affinity(cpu: usize) -> io::Result<()> {
+	let mask = cpu_mask(cpu)?;
+	let rc = unsafe {
+		libc::sched_setaffinity(
+			0,
+			core::mem::size_of_val(&mask),
+			mask.as_ptr() as *const libc::cpu_set_t,
+		)
+	};
+	if rc != 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(())
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+fn set_cpu_affinity(cpu: usize) -> io::Result<()> {
+	super::linux_syscalls::sched_setaffinity(&cpu_mask(cpu)?)
+}
+
+// Pinning can't be requested without a syscall backend.
+#[cfg(not(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+)))]
+fn set_cpu_affinity(_cpu: usize) -> io::Result<()> {
+	Ok(())
+}
@@ -0,0 +1,140 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::time::Duration;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use crate::channel::Channel;
+use crate::fuse_server::{FuseServerBuilder, FuseServerChannel};
+use crate::protocol::Opcode;
+use crate::server::{NoopServerHooks, ServerChannel};
+use crate::testing::{FuseClient, MemoryChannel};
+use crate::FuseHandlers;
+
+use super::FuseWorkerPoolBuilder;
+
+struct TestFS;
+
+impl FuseHandlers for TestFS {}
+
+// Wraps a `MemoryChannel`. Cloned channels fail once they receive a
+// `FUSE_STATFS` request.
+struct FailingChannel {
+	channel: MemoryChannel,
+	receiving: Arc<AtomicUsize>,
+	shutdowns: Arc<AtomicUsize>,
+	fail: bool,
+}
+
+impl Channel for FailingChannel {
+	type Error = io::Error;
+
+	fn send(&self, buf: &[u8]) -> Result<(), io::Error> {
+		self.channel.send(buf)
+	}
+
+	fn send_vectored<const N: usize>(
+		&self,
+		bufs: &[&[u8]; N],
+	) -> Result<(), io::Error> {
+		self.channel.send_vectored(bufs)
+	}
+
+	fn receive(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
+		if !self.fail {
+			return self.channel.receive(buf);
+		}
+		self.receiving.fetch_add(1, Ordering::SeqCst);
+		let len = self.channel.receive(buf)?;
+		// The opcode follows the request length in `fuse_in_header`.
+		let opcode = u32::from_ne_bytes([buf[4], buf[5], buf[6], buf[7]]);
+		if opcode == Opcode::FUSE_STATFS as u32 {
+			return Err(io::Error::other("worker failed"));
+		}
+		Ok(len)
+	}
+}
+
+impl ServerChannel for FailingChannel {
+	fn try_clone(&self) -> Result<Self, io::Error> {
+		Ok(Self {
+			channel: self.channel.try_clone()?,
+			receiving: self.receiving.clone(),
+			shutdowns: self.shutdowns.clone(),
+			fail: true,
+		})
+	}
+}
+
+impl FuseServerChannel for FailingChannel {
+	fn shutdown(&self) -> Result<(), io::Error> {
+		self.shutdowns.fetch_add(1, Ordering::SeqCst);
+		self.channel.shutdown()
+	}
+
+	fn abort(&self) -> Result<(), io::Error> {
+		self.shutdowns.fetch_add(1, Ordering::SeqCst);
+		self.channel.abort()
+	}
+}
+
+#[test]
+fn worker_error_stops_pool() {
+	let (mut client, channel) = FuseClient::new();
+	let receiving = Arc::new(AtomicUsize::new(0));
+	let shutdowns = Arc::new(AtomicUsize::new(0));
+	let channel = FailingChannel {
+		channel,
+		receiving: receiving.clone(),
+		shutdowns: shutdowns.clone(),
+		fail: false,
+	};
+	let builder = thread::spawn(move || {
+		FuseServerBuilder::<_, _, NoopServerHooks>::new(channel, TestFS).build()
+	});
+	client.fuse_init().unwrap();
+	let server = builder.join().unwrap().unwrap();
+
+	let pool = FuseWorkerPoolBuilder::new(server)
+		.set_num_workers(4)
+		.spawn()
+		.unwrap();
+	while receiving.load(Ordering::SeqCst) < 4 {
+		thread::sleep(Duration::from_millis(1));
+	}
+	let shutdown = pool.shutdown_handle();
+	let (result_tx, result_rx) = mpsc::channel();
+	thread::spawn(move || {
+		let _ = result_tx.send(pool.join());
+	});
+
+	// One worker fails on receiving `FUSE_STATFS`. The other workers stay
+	// blocked in `receive()`, but `join()` returns without waiting for them.
+	client.send_request(Opcode::FUSE_STATFS, 1, &[]);
+	let result = result_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+	let err = result.unwrap_err();
+	assert_eq!(err.to_string(), "worker failed");
+
+	// Ending the session is left to the caller, and stops the other workers.
+	assert_eq!(shutdowns.load(Ordering::SeqCst), 0);
+	// The failed worker's result was already returned by `join()`.
+	let results = shutdown.shutdown(Duration::from_secs(5)).unwrap();
+	assert_eq!(results.len(), 3);
+	assert!(results.iter().all(|r| r.is_ok()));
+}
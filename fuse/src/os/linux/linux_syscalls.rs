@@ -39,6 +39,134 @@ pub(crate) fn mount(
 	Err(io::Error::from_raw_os_error(-(rc as isize) as i32))
 }
 
+pub(crate) fn umount2(target: &CStr, flags: u32) -> io::Result<()> {
+	let rc = unsafe { target::umount2(target, flags) };
+	if rc == 0 {
+		return Ok(());
+	}
+	Err(io::Error::from_raw_os_error(-(rc as isize) as i32))
+}
+
+pub(crate) fn ioctl(fd: i32, request: u32, arg: usize) -> io::Result<()> {
+	let rc = unsafe { target::ioctl(fd, request, arg) };
+	if rc == 0 {
+		return Ok(());
+	}
+	Err(io::Error::from_raw_os_error(-(rc as isize) as i32))
+}
+
+pub(crate) fn pipe2(flags: u32) -> io::Result<[i32; 2]> {
+	let mut fds = [-1i32; 2];
+	let rc = unsafe { target::pipe2(&mut fds, flags) };
+	if rc == 0 {
+		return Ok(fds);
+	}
+	Err(io::Error::from_raw_os_error(-(rc as isize) as i32))
+}
+
+pub(crate) fn fcntl(fd: i32, cmd: u32, arg: usize) -> io::Result<usize> {
+	let rc = unsafe { target::fcntl(fd, cmd, arg) } as isize;
+	if rc < 0 {
+		return Err(io::Error::from_raw_os_error(-rc as i32));
+	}
+	Ok(rc as usize)
+}
+
+pub(crate) fn recvmsg(fd: i32, msg: usize, flags: u32) -> io::Result<usize> {
+	let rc = unsafe { target::recvmsg(fd, msg, flags) } as isize;
+	if rc < 0 {
+		return Err(io::Error::from_raw_os_error(-rc as i32));
+	}
+	Ok(rc as usize)
+}
+
+pub(crate) fn splice(
+	fd_in: i32,
+	off_in: Option<&mut i64>,
+	fd_out: i32,
+	len: usize,
+) -> io::Result<usize> {
+	let off_in = match off_in {
+		Some(off_in) => off_in as *mut i64,
+		None => core::ptr::null_mut(),
+	};
+	let rc = unsafe { target::splice(fd_in, off_in, fd_out, len) } as isize;
+	if rc < 0 {
+		return Err(io::Error::from_raw_os_error(-rc as i32));
+	}
+	Ok(rc as usize)
+}
+
+pub(crate) fn statx(
+	dirfd: i32,
+	path: &CStr,
+	flags: u32,
+	mask: u32,
+	statxbuf: usize,
+) -> io::Result<()> {
+	let rc = unsafe { target::statx(dirfd, path, flags, mask, statxbuf) };
+	if rc == 0 {
+		return Ok(());
+	}
+	Err(io::Error::from_raw_os_error(-(rc as isize) as i32))
+}
+
+pub(crate) fn fsopen(fsname: &CStr, flags: u32) -> io::Result<i32> {
+	let rc = unsafe { target::fsopen(fsname, flags) } as isize;
+	if rc < 0 {
+		return Err(io::Error::from_raw_os_error(-rc as i32));
+	}
+	Ok(rc as i32)
+}
+
+pub(crate) fn fsconfig(
+	fd: i32,
+	cmd: u32,
+	key: Option<&CStr>,
+	value: Option<&CStr>,
+	aux: i32,
+) -> io::Result<()> {
+	let key = key.map_or(0, |key| key.as_ptr() as usize);
+	let value = value.map_or(0, |value| value.as_ptr() as usize);
+	let rc = unsafe { target::fsconfig(fd, cmd, key, value, aux) };
+	if rc == 0 {
+		return Ok(());
+	}
+	Err(io::Error::from_raw_os_error(-(rc as isize) as i32))
+}
+
+pub(crate) fn fsmount(fd: i32, flags: u32, attr_flags: u32) -> io::Result<i32> {
+	let rc = unsafe { target::fsmount(fd, flags, attr_flags) } as isize;
+	if rc < 0 {
+		return Err(io::Error::from_raw_os_error(-rc as i32));
+	}
+	Ok(rc as i32)
+}
+
+pub(crate) fn move_mount(
+	from_dirfd: i32,
+	from_path: &CStr,
+	to_dirfd: i32,
+	to_path: &CStr,
+	flags: u32,
+) -> io::Result<()> {
+	let rc = unsafe {
+		target::move_mount(from_dirfd, from_path, to_dirfd, to_path, flags)
+	};
+	if rc == 0 {
+		return Ok(());
+	}
+	Err(io::Error::from_raw_os_error(-(rc as isize) as i32))
+}
+
+pub(crate) fn sched_setaffinity(mask: &[usize]) -> io::Result<()> {
+	let rc = unsafe { target::sched_setaffinity(mask) };
+	if rc == 0 {
+		return Ok(());
+	}
+	Err(io::Error::from_raw_os_error(-(rc as isize) as i32))
+}
+
 #[cfg(target_arch = "arm")] // EABI
 mod target {
 	#![allow(non_upper_case_globals)]
@@ -48,6 +176,18 @@ mod target {
 	const SYS_getuid32: usize = 199;
 	const SYS_getgid32: usize = 200;
 	const SYS_mount: usize = 21;
+	const SYS_umount2: usize = 52;
+	const SYS_ioctl: usize = 54;
+	const SYS_sched_setaffinity: usize = 241;
+	const SYS_fcntl64: usize = 221;
+	const SYS_splice: usize = 340;
+	const SYS_pipe2: usize = 359;
+	const SYS_recvmsg: usize = 297;
+	const SYS_move_mount: usize = 429;
+	const SYS_fsopen: usize = 430;
+	const SYS_fsconfig: usize = 431;
+	const SYS_fsmount: usize = 432;
+	const SYS_statx: usize = 397;
 
 	pub(super) unsafe fn getuid() -> usize {
 		let rc: usize;
@@ -89,6 +229,195 @@ mod target {
 		);
 		rc
 	}
+
+	pub(super) unsafe fn umount2(target: &CStr, flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_umount2,
+			in("r0") target.as_ptr(),
+			in("r1") flags,
+			lateout("r0") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn ioctl(fd: i32, request: u32, arg: usize) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_ioctl,
+			in("r0") fd,
+			in("r1") request,
+			in("r2") arg,
+			lateout("r0") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fsopen(fsname: &CStr, flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_fsopen,
+			in("r0") fsname.as_ptr(),
+			in("r1") flags,
+			lateout("r0") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fsconfig(
+		fd: i32,
+		cmd: u32,
+		key: usize,
+		value: usize,
+		aux: i32,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_fsconfig,
+			in("r0") fd,
+			in("r1") cmd,
+			in("r2") key,
+			in("r3") value,
+			in("r4") aux,
+			lateout("r0") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fsmount(
+		fd: i32,
+		flags: u32,
+		attr_flags: u32,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_fsmount,
+			in("r0") fd,
+			in("r1") flags,
+			in("r2") attr_flags,
+			lateout("r0") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn move_mount(
+		from_dirfd: i32,
+		from_path: &CStr,
+		to_dirfd: i32,
+		to_path: &CStr,
+		flags: u32,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_move_mount,
+			in("r0") from_dirfd,
+			in("r1") from_path.as_ptr(),
+			in("r2") to_dirfd,
+			in("r3") to_path.as_ptr(),
+			in("r4") flags,
+			lateout("r0") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn statx(
+		dirfd: i32,
+		path: &CStr,
+		flags: u32,
+		mask: u32,
+		statxbuf: usize,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_statx,
+			in("r0") dirfd,
+			in("r1") path.as_ptr(),
+			in("r2") flags,
+			in("r3") mask,
+			in("r4") statxbuf,
+			lateout("r0") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn sched_setaffinity(mask: &[usize]) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_sched_setaffinity,
+			in("r0") 0usize,
+			in("r1") mask.len() * core::mem::size_of::<usize>(),
+			in("r2") mask.as_ptr(),
+			lateout("r0") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn pipe2(fds: &mut [i32; 2], flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_pipe2,
+			in("r0") fds.as_mut_ptr(),
+			in("r1") flags,
+			lateout("r0") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fcntl(fd: i32, cmd: u32, arg: usize) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_fcntl64,
+			in("r0") fd,
+			in("r1") cmd,
+			in("r2") arg,
+			lateout("r0") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn recvmsg(fd: i32, msg: usize, flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_recvmsg,
+			in("r0") fd,
+			in("r1") msg,
+			in("r2") flags,
+			lateout("r0") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn splice(
+		fd_in: i32,
+		off_in: *mut i64,
+		fd_out: i32,
+		len: usize,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"swi #0",
+			in("r7") SYS_splice,
+			in("r0") fd_in,
+			in("r1") off_in,
+			in("r2") fd_out,
+			in("r3") 0usize,
+			in("r4") len,
+			in("r5") 0usize,
+			lateout("r0") rc,
+		);
+		rc
+	}
 }
 
 #[cfg(target_arch = "x86")]
@@ -100,6 +429,18 @@ mod target {
 	const SYS_getuid32: usize = 199;
 	const SYS_getgid32: usize = 200;
 	const SYS_mount: usize = 21;
+	const SYS_umount2: usize = 52;
+	const SYS_ioctl: usize = 54;
+	const SYS_sched_setaffinity: usize = 241;
+	const SYS_fcntl64: usize = 221;
+	const SYS_splice: usize = 313;
+	const SYS_pipe2: usize = 331;
+	const SYS_recvmsg: usize = 372;
+	const SYS_move_mount: usize = 429;
+	const SYS_fsopen: usize = 430;
+	const SYS_fsconfig: usize = 431;
+	const SYS_fsmount: usize = 432;
+	const SYS_statx: usize = 383;
 
 	pub(super) unsafe fn getuid() -> usize {
 		let rc: usize;
@@ -141,6 +482,199 @@ mod target {
 		);
 		rc
 	}
+
+	pub(super) unsafe fn umount2(target: &CStr, flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"int 0x80",
+			in("eax") SYS_umount2,
+			in("ebx") target.as_ptr(),
+			in("ecx") flags,
+			lateout("eax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn ioctl(fd: i32, request: u32, arg: usize) -> usize {
+		let mut rc: usize;
+		asm!(
+			"int 0x80",
+			in("eax") SYS_ioctl,
+			in("ebx") fd,
+			in("ecx") request,
+			in("edx") arg,
+			lateout("eax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fsopen(fsname: &CStr, flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"int 0x80",
+			in("eax") SYS_fsopen,
+			in("ebx") fsname.as_ptr(),
+			in("ecx") flags,
+			lateout("eax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fsconfig(
+		fd: i32,
+		cmd: u32,
+		key: usize,
+		value: usize,
+		aux: i32,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"int 0x80",
+			in("eax") SYS_fsconfig,
+			in("ebx") fd,
+			in("ecx") cmd,
+			in("edx") key,
+			in("esi") value,
+			in("edi") aux,
+			lateout("eax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fsmount(
+		fd: i32,
+		flags: u32,
+		attr_flags: u32,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"int 0x80",
+			in("eax") SYS_fsmount,
+			in("ebx") fd,
+			in("ecx") flags,
+			in("edx") attr_flags,
+			lateout("eax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn move_mount(
+		from_dirfd: i32,
+		from_path: &CStr,
+		to_dirfd: i32,
+		to_path: &CStr,
+		flags: u32,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"int 0x80",
+			in("eax") SYS_move_mount,
+			in("ebx") from_dirfd,
+			in("ecx") from_path.as_ptr(),
+			in("edx") to_dirfd,
+			in("esi") to_path.as_ptr(),
+			in("edi") flags,
+			lateout("eax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn statx(
+		dirfd: i32,
+		path: &CStr,
+		flags: u32,
+		mask: u32,
+		statxbuf: usize,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"int 0x80",
+			in("eax") SYS_statx,
+			in("ebx") dirfd,
+			in("ecx") path.as_ptr(),
+			in("edx") flags,
+			in("esi") mask,
+			in("edi") statxbuf,
+			lateout("eax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn sched_setaffinity(mask: &[usize]) -> usize {
+		let mut rc: usize;
+		asm!(
+			"int 0x80",
+			in("eax") SYS_sched_setaffinity,
+			in("ebx") 0usize,
+			in("ecx") mask.len() * core::mem::size_of::<usize>(),
+			in("edx") mask.as_ptr(),
+			lateout("eax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn pipe2(fds: &mut [i32; 2], flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"int 0x80",
+			in("eax") SYS_pipe2,
+			in("ebx") fds.as_mut_ptr(),
+			in("ecx") flags,
+			lateout("eax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fcntl(fd: i32, cmd: u32, arg: usize) -> usize {
+		let mut rc: usize;
+		asm!(
+			"int 0x80",
+			in("eax") SYS_fcntl64,
+			in("ebx") fd,
+			in("ecx") cmd,
+			in("edx") arg,
+			lateout("eax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn recvmsg(fd: i32, msg: usize, flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"int 0x80",
+			in("eax") SYS_recvmsg,
+			in("ebx") fd,
+			in("ecx") msg,
+			in("edx") flags,
+			lateout("eax") rc,
+		);
+		rc
+	}
+
+	// The sixth argument (`flags`) is passed in `ebp`, which can't be used
+	// as an operand. It's always zero.
+	pub(super) unsafe fn splice(
+		fd_in: i32,
+		off_in: *mut i64,
+		fd_out: i32,
+		len: usize,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"push ebp",
+			"xor ebp, ebp",
+			"int 0x80",
+			"pop ebp",
+			in("eax") SYS_splice,
+			in("ebx") fd_in,
+			in("ecx") off_in,
+			in("edx") fd_out,
+			in("esi") 0usize,
+			in("edi") len,
+			lateout("eax") rc,
+		);
+		rc
+	}
 }
 
 #[cfg(target_arch = "x86_64")]
@@ -152,6 +686,18 @@ mod target {
 	const SYS_getuid: usize = 102;
 	const SYS_getgid: usize = 104;
 	const SYS_mount: usize = 165;
+	const SYS_umount2: usize = 166;
+	const SYS_ioctl: usize = 16;
+	const SYS_sched_setaffinity: usize = 203;
+	const SYS_fcntl: usize = 72;
+	const SYS_splice: usize = 275;
+	const SYS_pipe2: usize = 293;
+	const SYS_recvmsg: usize = 47;
+	const SYS_move_mount: usize = 429;
+	const SYS_fsopen: usize = 430;
+	const SYS_fsconfig: usize = 431;
+	const SYS_fsmount: usize = 432;
+	const SYS_statx: usize = 332;
 
 	pub(super) unsafe fn getuid() -> usize {
 		let rc: usize;
@@ -199,4 +745,217 @@ mod target {
 		);
 		rc
 	}
+
+	pub(super) unsafe fn umount2(target: &CStr, flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_umount2,
+			in("rdi") target.as_ptr(),
+			in("rsi") flags,
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn ioctl(fd: i32, request: u32, arg: usize) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_ioctl,
+			in("rdi") fd,
+			in("rsi") request,
+			in("rdx") arg,
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fsopen(fsname: &CStr, flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_fsopen,
+			in("rdi") fsname.as_ptr(),
+			in("rsi") flags,
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fsconfig(
+		fd: i32,
+		cmd: u32,
+		key: usize,
+		value: usize,
+		aux: i32,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_fsconfig,
+			in("rdi") fd,
+			in("rsi") cmd,
+			in("rdx") key,
+			in("r10") value,
+			in("r8") aux,
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fsmount(
+		fd: i32,
+		flags: u32,
+		attr_flags: u32,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_fsmount,
+			in("rdi") fd,
+			in("rsi") flags,
+			in("rdx") attr_flags,
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn move_mount(
+		from_dirfd: i32,
+		from_path: &CStr,
+		to_dirfd: i32,
+		to_path: &CStr,
+		flags: u32,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_move_mount,
+			in("rdi") from_dirfd,
+			in("rsi") from_path.as_ptr(),
+			in("rdx") to_dirfd,
+			in("r10") to_path.as_ptr(),
+			in("r8") flags,
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn statx(
+		dirfd: i32,
+		path: &CStr,
+		flags: u32,
+		mask: u32,
+		statxbuf: usize,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_statx,
+			in("rdi") dirfd,
+			in("rsi") path.as_ptr(),
+			in("rdx") flags,
+			in("r10") mask,
+			in("r8") statxbuf,
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn sched_setaffinity(mask: &[usize]) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_sched_setaffinity,
+			in("rdi") 0usize,
+			in("rsi") mask.len() * core::mem::size_of::<usize>(),
+			in("rdx") mask.as_ptr(),
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn pipe2(fds: &mut [i32; 2], flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_pipe2,
+			in("rdi") fds.as_mut_ptr(),
+			in("rsi") flags,
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn fcntl(fd: i32, cmd: u32, arg: usize) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_fcntl,
+			in("rdi") fd,
+			in("rsi") cmd,
+			in("rdx") arg,
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn recvmsg(fd: i32, msg: usize, flags: u32) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_recvmsg,
+			in("rdi") fd,
+			in("rsi") msg,
+			in("rdx") flags,
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
+
+	pub(super) unsafe fn splice(
+		fd_in: i32,
+		off_in: *mut i64,
+		fd_out: i32,
+		len: usize,
+	) -> usize {
+		let mut rc: usize;
+		asm!(
+			"syscall",
+			in("rax") SYS_splice,
+			in("rdi") fd_in,
+			in("rsi") off_in,
+			in("rdx") fd_out,
+			in("r10") 0usize,
+			in("r8") len,
+			in("r9") 0usize,
+			out("rcx") _,
+			out("r11") _,
+			lateout("rax") rc,
+		);
+		rc
+	}
 }
@@ -19,6 +19,12 @@
 #[cfg(feature = "nightly_syscall_fuse_mount")]
 mod linux_syscalls;
 
+#[cfg(any(
+	feature = "libc_fuse_mount",
+	feature = "nightly_syscall_fuse_mount",
+))]
+mod splice;
+
 mod cuse_server_builder;
 pub use self::cuse_server_builder::*;
 
@@ -31,6 +37,22 @@ pub use self::dev_fuse_channel::*;
 mod fuse_server_builder;
 pub use self::fuse_server_builder::*;
 
+#[cfg(feature = "respond_async")]
+mod fuse_worker_pool;
+#[cfg(feature = "respond_async")]
+pub use self::fuse_worker_pool::*;
+
+#[cfg(all(
+	feature = "tokio",
+	any(feature = "libc_fuse_mount", feature = "nightly_syscall_fuse_mount"),
+))]
+mod tokio_dev_fuse_channel;
+#[cfg(all(
+	feature = "tokio",
+	any(feature = "libc_fuse_mount", feature = "nightly_syscall_fuse_mount"),
+))]
+pub use self::tokio_dev_fuse_channel::*;
+
 #[cfg(any(
 	doc,
 	feature = "libc_fuse_mount",
@@ -0,0 +1,363 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::cell::{Cell, RefCell};
+use core::mem::size_of;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+
+use crate::channel::FdResponse;
+use crate::internal::fuse_kernel;
+
+// Pipes are reused by later responses sent from the same thread.
+thread_local! {
+	static PIPES: RefCell<Option<SplicePipes>> = const { RefCell::new(None) };
+}
+
+// The pipe that spliced requests are received into. Its read end is handed
+// to the handler, and remains valid until the next request is received on
+// the same thread.
+thread_local! {
+	static RECEIVE_PIPE: RefCell<Option<ReceivePipe>> =
+		const { RefCell::new(None) };
+	static RECEIVE_UNSUPPORTED: Cell<bool> = const { Cell::new(false) };
+}
+
+const IN_HEADER_LEN: usize = size_of::<fuse_kernel::fuse_in_header>();
+const WRITE_IN_LEN: usize = size_of::<fuse_kernel::fuse_write_in>();
+
+// Writes with a smaller payload are copied into the read buffer, because
+// handling the pipe would cost more than the copy.
+const MIN_SPLICED_PAYLOAD: usize = PAGE_SIZE;
+
+// Receives a request by splicing it from `/dev/fuse` into a pipe.
+//
+// The headers are read into `buf`. If the request is a large `FUSE_WRITE`,
+// its payload is left in the pipe and the pipe's read end is returned.
+// Otherwise the rest of the request is read into `buf`.
+//
+// Returns `Ok(None)` if requests can't be spliced on this thread, in which
+// case nothing was received.
+pub(super) fn receive(
+	dev: &fs::File,
+	buf: &mut [u8],
+) -> Result<Option<(usize, Option<RawFd>)>, io::Error> {
+	if RECEIVE_UNSUPPORTED.with(|unsupported| unsupported.get()) {
+		return Ok(None);
+	}
+	RECEIVE_PIPE.with(|cached| {
+		let mut cached = cached.borrow_mut();
+		if cached.is_none() {
+			match ReceivePipe::new(buf.len()) {
+				Ok(pipe) => *cached = Some(pipe),
+				Err(_) => {
+					RECEIVE_UNSUPPORTED.with(|u| u.set(true));
+					return Ok(None);
+				},
+			}
+		}
+		let pipe = cached.as_mut().unwrap();
+		if pipe.reserve(buf.len()).is_err() {
+			RECEIVE_UNSUPPORTED.with(|u| u.set(true));
+			return Ok(None);
+		}
+		pipe.discard()?;
+
+		let request_size = loop {
+			match splice(
+				dev.as_raw_fd(),
+				None,
+				pipe.write.as_raw_fd(),
+				buf.len(),
+			) {
+				Ok(size) => break size,
+				Err(err) => match err.raw_os_error() {
+					// Interrupted, try again. See `FileChannel::receive`.
+					Some(EINTR) | Some(ENOENT) => {},
+					Some(EINVAL) => {
+						RECEIVE_UNSUPPORTED.with(|u| u.set(true));
+						return Ok(None);
+					},
+					_ => return Err(err),
+				},
+			}
+		};
+		if request_size < IN_HEADER_LEN {
+			return Err(io::ErrorKind::UnexpectedEof.into());
+		}
+
+		pipe.read.read_exact(&mut buf[..IN_HEADER_LEN])?;
+		let opcode = unsafe {
+			(*(buf.as_ptr() as *const fuse_kernel::fuse_in_header)).opcode
+		};
+		let headers_len = IN_HEADER_LEN + WRITE_IN_LEN;
+		if opcode == fuse_kernel::FUSE_WRITE
+			&& request_size >= headers_len + MIN_SPLICED_PAYLOAD
+		{
+			pipe.read.read_exact(&mut buf[IN_HEADER_LEN..headers_len])?;
+			return Ok(Some((headers_len, Some(pipe.read.as_raw_fd()))));
+		}
+
+		pipe.read
+			.read_exact(&mut buf[IN_HEADER_LEN..request_size])?;
+		Ok(Some((request_size, None)))
+	})
+}
+
+struct ReceivePipe {
+	read: fs::File,
+	write: fs::File,
+	capacity: usize,
+}
+
+impl ReceivePipe {
+	fn new(buf_len: usize) -> Result<ReceivePipe, io::Error> {
+		// The read end is non-blocking so that leftover data can be
+		// discarded without knowing its size.
+		let (read, write) = new_pipe(O_CLOEXEC | O_NONBLOCK)?;
+		let mut pipe = Self {
+			read,
+			write,
+			capacity: DEFAULT_PIPE_CAPACITY,
+		};
+		pipe.reserve(buf_len)?;
+		Ok(pipe)
+	}
+
+	// The kernel fails the splice, discarding the request, if the pipe
+	// doesn't have a free buffer for each page of the request.
+	fn reserve(&mut self, buf_len: usize) -> Result<(), io::Error> {
+		let needed = (buf_len / PAGE_SIZE + 2) * PAGE_SIZE;
+		if needed <= self.capacity {
+			return Ok(());
+		}
+		self.capacity = set_pipe_size(self.write.as_raw_fd(), needed)?;
+		Ok(())
+	}
+
+	// Discards any part of the previous request's payload that the handler
+	// didn't consume.
+	fn discard(&mut self) -> Result<(), io::Error> {
+		let mut scratch = [0u8; 4096];
+		loop {
+			match self.read.read(&mut scratch) {
+				Ok(0) => return Ok(()),
+				Ok(_) => {},
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+					return Ok(())
+				},
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+				Err(err) => return Err(err),
+			}
+		}
+	}
+}
+
+// Sends a response by splicing its payload through a pipe, so that the
+// payload is never copied into userspace.
+//
+// The size of the payload isn't known until it has been read, but the
+// response header must be written to the pipe first. The payload is spliced
+// into one pipe, then moved into a second pipe after the header.
+//
+// Returns `Ok(false)` if the payload can't be spliced (for example if `fd`
+// doesn't support splicing), in which case nothing was sent.
+pub(super) fn send_from_fd(
+	dev: &fs::File,
+	response: &FdResponse,
+) -> Result<bool, io::Error> {
+	PIPES.with(|cached| {
+		let mut pipes = match cached.borrow_mut().take() {
+			Some(pipes) => pipes,
+			None => match SplicePipes::new() {
+				Ok(pipes) => pipes,
+				Err(_) => return Ok(false),
+			},
+		};
+		if pipes.reserve(response.len() as usize).is_err() {
+			cached.replace(Some(pipes));
+			return Ok(false);
+		}
+
+		// If splicing fails part way, the pipes may still hold data and are
+		// dropped instead of being reused.
+		let payload_len = match pipes.fill(response) {
+			Ok(len) => len,
+			Err(_) => return Ok(false),
+		};
+		let total_len = response.header(0).len() + payload_len;
+		let write_size = retry_splice(|| {
+			splice(pipes.msg.0.as_raw_fd(), None, dev.as_raw_fd(), total_len)
+		})?;
+		if write_size < total_len {
+			return Err(io::Error::other("incomplete send"));
+		}
+
+		cached.replace(Some(pipes));
+		Ok(true)
+	})
+}
+
+struct SplicePipes {
+	// (read end, write end)
+	data: (fs::File, fs::File),
+	msg: (fs::File, fs::File),
+	capacity: usize,
+}
+
+impl SplicePipes {
+	fn new() -> Result<SplicePipes, io::Error> {
+		Ok(Self {
+			data: new_pipe(O_CLOEXEC)?,
+			msg: new_pipe(O_CLOEXEC)?,
+			capacity: DEFAULT_PIPE_CAPACITY,
+		})
+	}
+
+	// Grows the pipes so that a payload of `len` bytes can be spliced
+	// without blocking. Each page of the payload occupies one pipe buffer,
+	// plus one for an unaligned start or end and one for the header.
+	fn reserve(&mut self, len: usize) -> Result<(), io::Error> {
+		let needed = (len / PAGE_SIZE + 3) * PAGE_SIZE;
+		if needed <= self.capacity {
+			return Ok(());
+		}
+		set_pipe_size(self.data.1.as_raw_fd(), needed)?;
+		self.capacity = set_pipe_size(self.msg.1.as_raw_fd(), needed)?;
+		Ok(())
+	}
+
+	// Writes the response header followed by the payload into the `msg`
+	// pipe, returning the size of the payload.
+	fn fill(&mut self, response: &FdResponse) -> Result<usize, io::Error> {
+		let max_len = response.len() as usize;
+		let mut offset = response.offset() as i64;
+		let mut payload_len = 0;
+		while payload_len < max_len {
+			let size = retry_splice(|| {
+				splice(
+					response.fd(),
+					Some(&mut offset),
+					self.data.1.as_raw_fd(),
+					max_len - payload_len,
+				)
+			})?;
+			if size == 0 {
+				break;
+			}
+			payload_len += size;
+		}
+
+		self.msg.1.write_all(&response.header(payload_len as u32))?;
+
+		let mut moved = 0;
+		while moved < payload_len {
+			moved += retry_splice(|| {
+				splice(
+					self.data.0.as_raw_fd(),
+					None,
+					self.msg.1.as_raw_fd(),
+					payload_len - moved,
+				)
+			})?;
+		}
+		Ok(payload_len)
+	}
+}
+
+fn retry_splice(
+	mut f: impl FnMut() -> Result<usize, io::Error>,
+) -> Result<usize, io::Error> {
+	loop {
+		match f() {
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+			result => return result,
+		}
+	}
+}
+
+const PAGE_SIZE: usize = 4096;
+const DEFAULT_PIPE_CAPACITY: usize = 16 * PAGE_SIZE;
+
+const O_CLOEXEC: u32 = 0o2000000;
+const O_NONBLOCK: u32 = 0o4000;
+const F_SETPIPE_SZ: u32 = 1031;
+
+const ENOENT: i32 = 2;
+const EINTR: i32 = 4;
+const EINVAL: i32 = 22;
+
+fn new_pipe(flags: u32) -> Result<(fs::File, fs::File), io::Error> {
+	let fds = pipe2(flags)?;
+	Ok(unsafe {
+		(fs::File::from_raw_fd(fds[0]), fs::File::from_raw_fd(fds[1]))
+	})
+}
+
+fn set_pipe_size(fd: i32, size: usize) -> Result<usize, io::Error> {
+	fcntl(fd, F_SETPIPE_SZ, size)
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn pipe2(flags: u32) -> Result<[i32; 2], io::Error> {
+	let mut fds = [-1i32; 2];
+	let rc = unsafe { libc::pipe2(fds.as_mut_ptr(), flags as i32) };
+	if rc != 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(fds)
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+pub(super) fn fcntl(fd: i32, cmd: u32, arg: usize) -> Result<usize, io::Error> {
+	let rc = unsafe { libc::fcntl(fd, cmd as i32, arg as libc::c_int) };
+	if rc < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(rc as usize)
+}
+
+#[cfg(feature = "libc_fuse_mount")]
+fn splice(
+	fd_in: i32,
+	off_in: Option<&mut i64>,
+	fd_out: i32,
+	len: usize,
+) -> Result<usize, io::Error> {
+	let off_in = match off_in {
+		Some(off_in) => off_in as *mut i64,
+		None => core::ptr::null_mut(),
+	};
+	let rc = unsafe {
+		libc::splice(fd_in, off_in, fd_out, core::ptr::null_mut(), len, 0)
+	};
+	if rc < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(rc as usize)
+}
+
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+pub(super) use super::linux_syscalls::fcntl;
+#[cfg(all(
+	feature = "nightly_syscall_fuse_mount",
+	not(feature = "libc_fuse_mount"),
+))]
+use super::linux_syscalls::{pipe2, splice};
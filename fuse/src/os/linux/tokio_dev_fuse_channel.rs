@@ -0,0 +1,243 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::sync::atomic::Ordering;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+use crate::channel::{self, Channel, ChannelError};
+use crate::error::ErrorCode;
+use crate::fuse_handlers::FuseHandlers;
+use crate::fuse_server::{FuseServerChannel, FuseServerExecutor};
+use crate::internal::fuse_io::{self, AlignedBuffer};
+use crate::internal::fuse_kernel;
+use crate::server;
+
+use super::DevFuseChannel;
+
+#[cfg(rust_fuse_test = "tokio_dev_fuse_channel_test")]
+#[path = "tokio_dev_fuse_channel_test.rs"]
+mod tokio_dev_fuse_channel_test;
+
+// TokioDevFuseChannel {{{
+
+/// A [`DevFuseChannel`] in non-blocking mode, registered with a Tokio
+/// runtime.
+///
+/// The async [`receive`] and [`send`] methods wait for the file descriptor
+/// to become ready without blocking the calling thread. The synchronous
+/// [`Channel`] methods are also available: sends complete immediately, but
+/// [`Channel::receive`] fails with [`io::ErrorKind::WouldBlock`] if no
+/// request is available.
+///
+/// [`DevFuseChannel`]: struct.DevFuseChannel.html
+/// [`receive`]: #method.receive
+/// [`send`]: #method.send
+/// [`Channel`]: ../../io/trait.Channel.html
+/// [`Channel::receive`]: ../../io/trait.Channel.html#tymethod.receive
+/// [`io::ErrorKind::WouldBlock`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.WouldBlock
+#[cfg_attr(doc, doc(cfg(feature = "tokio")))]
+pub struct TokioDevFuseChannel {
+	fd: AsyncFd<RegisteredChannel>,
+}
+
+// Gives `AsyncFd` ownership of the channel, so that the file descriptor is
+// deregistered before it's closed.
+struct RegisteredChannel(DevFuseChannel);
+
+impl AsRawFd for RegisteredChannel {
+	fn as_raw_fd(&self) -> RawFd {
+		self.0.file().as_raw_fd()
+	}
+}
+
+impl TokioDevFuseChannel {
+	/// Switches `channel` into non-blocking mode and registers it with the
+	/// current Tokio runtime.
+	///
+	/// # Panics
+	///
+	/// Panics if called outside of a Tokio runtime.
+	pub fn new(channel: DevFuseChannel) -> Result<Self, io::Error> {
+		set_nonblocking(channel.file().as_raw_fd())?;
+		// SAFETY: the file descriptor is owned by the channel, which is owned
+		// by the `AsyncFd` and never replaces its file.
+		let fd = unsafe { AsyncFd::register(RegisteredChannel(channel)) }?;
+		Ok(Self { fd })
+	}
+
+	fn channel(&self) -> &DevFuseChannel {
+		&self.fd.get_ref().0
+	}
+
+	/// Receives a request, waiting until one is available.
+	pub async fn receive(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
+		// Once the FUSE connection has been aborted, `/dev/fuse` polls as
+		// `EPOLLERR` rather than readable. Reading from it then fails with
+		// `ENODEV`.
+		let interest = Interest::READABLE | Interest::ERROR;
+		loop {
+			let mut guard = self.fd.ready(interest).await?;
+			match guard.try_io(|_| self.channel().receive(buf)) {
+				Ok(result) => return result,
+				Err(_would_block) => continue,
+			}
+		}
+	}
+
+	/// Sends a message, waiting until the channel is writable.
+	pub async fn send(&self, buf: &[u8]) -> Result<(), io::Error> {
+		loop {
+			let mut guard = self.fd.writable().await?;
+			match guard.try_io(|_| self.channel().send(buf)) {
+				Ok(result) => return result,
+				Err(_would_block) => continue,
+			}
+		}
+	}
+}
+
+const O_NONBLOCK: usize = 0o4000;
+const F_GETFL: u32 = 3;
+const F_SETFL: u32 = 4;
+
+fn set_nonblocking(fd: RawFd) -> Result<(), io::Error> {
+	let flags = super::splice::fcntl(fd, F_GETFL, 0)?;
+	super::splice::fcntl(fd, F_SETFL, flags | O_NONBLOCK)?;
+	Ok(())
+}
+
+impl channel::Channel for TokioDevFuseChannel {
+	type Error = io::Error;
+
+	fn send(&self, buf: &[u8]) -> Result<(), io::Error> {
+		self.channel().send(buf)
+	}
+
+	fn send_vectored<const N: usize>(
+		&self,
+		bufs: &[&[u8]; N],
+	) -> Result<(), io::Error> {
+		self.channel().send_vectored(bufs)
+	}
+
+	fn send_vectored_slices(
+		&self,
+		bufs: &[io::IoSlice],
+	) -> Result<(), io::Error> {
+		self.channel().send_vectored_slices(bufs)
+	}
+
+	fn send_from_fd(
+		&self,
+		response: &channel::FdResponse,
+	) -> Result<(), io::Error> {
+		self.channel().send_from_fd(response)
+	}
+
+	fn receive(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
+		self.channel().receive(buf)
+	}
+}
+
+impl server::ServerChannel for TokioDevFuseChannel {
+	/// Opens a new session on the same FUSE connection, and registers it
+	/// with the current Tokio runtime.
+	///
+	/// # Panics
+	///
+	/// Panics if called outside of a Tokio runtime.
+	fn try_clone(&self) -> Result<Self, io::Error> {
+		TokioDevFuseChannel::new(self.channel().try_clone()?)
+	}
+}
+
+impl FuseServerChannel for TokioDevFuseChannel {
+	fn shutdown(&self) -> Result<(), io::Error> {
+		self.channel().shutdown()
+	}
+
+	fn abort(&self) -> Result<(), io::Error> {
+		self.channel().abort()
+	}
+}
+
+// }}}
+
+impl<Handlers, Hooks> FuseServerExecutor<TokioDevFuseChannel, Handlers, Hooks>
+where
+	Handlers: FuseHandlers + Send + Sync + 'static,
+	Hooks: server::ServerHooks + Send + Sync + 'static,
+{
+	/// Serves requests until the session ends, without blocking the thread
+	/// while waiting for a request.
+	///
+	/// Requests are passed to the handlers on the task running this future,
+	/// so handlers must not block. Handlers that wait for I/O or other slow
+	/// work should respond asynchronously, for example by using an
+	/// [`AsyncFuseHandlersAdapter`] with a spawner that calls
+	/// `tokio::spawn()`, or by moving a [`RespondAsync`] into a task of
+	/// their own.
+	///
+	/// Like [`run`], this returns `Ok(())` when the kernel sends
+	/// `FUSE_DESTROY` or the filesystem is unmounted.
+	///
+	/// [`AsyncFuseHandlersAdapter`]: ../../struct.AsyncFuseHandlersAdapter.html
+	/// [`RespondAsync`]: ../../struct.RespondAsync.html
+	/// [`run`]: ../../struct.FuseServerExecutor.html#method.run
+	#[cfg_attr(doc, doc(cfg(feature = "tokio")))]
+	pub async fn run_tokio(&mut self) -> Result<(), io::Error> {
+		let mut buf = fuse_io::AlignedVec::new(self.read_buf_size());
+		let destroy_request_id = loop {
+			if self.stop_flag().load(Ordering::Acquire) {
+				break None;
+			}
+			let request_size = match self.channel().receive(buf.get_mut()).await
+			{
+				Ok(size) => size,
+				Err(err) if err.error_code() == Some(ErrorCode::ENODEV) => {
+					break None;
+				},
+				Err(err) => return Err(err),
+			};
+			let request_buf = fuse_io::aligned_slice(&buf, request_size);
+			let dec = fuse_io::RequestDecoder::new(
+				request_buf,
+				self.version(),
+				fuse_io::Semantics::FUSE,
+			)?;
+			let header = dec.header();
+			if header.opcode == fuse_kernel::FUSE_DESTROY {
+				break Some(header.unique);
+			}
+			self.dispatch(dec)?;
+		};
+
+		if destroy_request_id.is_some() {
+			self.stop_all();
+			// Requests in flight may be answered by tasks running on this
+			// runtime, so wait for them without blocking it.
+			let inflight = self.inflight().clone();
+			tokio::task::spawn_blocking(move || inflight.wait_until_drained())
+				.await
+				.map_err(io::Error::other)?;
+		}
+		self.finish_drained(destroy_request_id)
+	}
+}
@@ -0,0 +1,202 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io;
+use std::net::Shutdown;
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+use std::os::unix::net::UnixDatagram;
+use std::{fs, path, thread};
+
+use crate::async_fuse_handlers::{
+	AsyncFuseHandlers,
+	AsyncFuseHandlersAdapter,
+	HandlerFuture,
+};
+use crate::channel::Channel;
+use crate::fuse_server::FuseServerBuilder;
+use crate::internal::testutil::{hello_id, hello_name, HelloFS};
+use crate::server::{NoopServerHooks, ServerChannel};
+use crate::testing::{FuseClient, MemoryChannel};
+use crate::{
+	ErrorCode,
+	FuseHandlers,
+	LookupRequest,
+	LookupResponse,
+	NodeName,
+	ServerContext,
+	ROOT_ID,
+};
+
+use super::{DevFuseChannel, TokioDevFuseChannel};
+
+// Forwards messages between a `FuseClient` and a socket standing in for
+// `/dev/fuse`. Datagrams keep message boundaries, like reads and writes of
+// `/dev/fuse` do.
+fn bridge(
+	channel: MemoryChannel,
+	socket: UnixDatagram,
+) -> [thread::JoinHandle<()>; 2] {
+	let requests = {
+		let channel = channel.try_clone().unwrap();
+		let socket = socket.try_clone().unwrap();
+		thread::spawn(move || {
+			let mut buf = vec![0u8; 1 << 20];
+			while let Ok(len) = channel.receive(&mut buf) {
+				socket.send(&buf[..len]).unwrap();
+			}
+		})
+	};
+	let responses = thread::spawn(move || {
+		let mut buf = vec![0u8; 1 << 20];
+		loop {
+			match socket.recv(&mut buf) {
+				Ok(0) | Err(_) => return,
+				Ok(len) => {
+					if channel.send(&buf[..len]).is_err() {
+						return;
+					}
+				},
+			}
+		}
+	});
+	[requests, responses]
+}
+
+// Serves `handlers` with `run_tokio()` on a single-threaded runtime, with
+// requests sent by `client` forwarded over a socket.
+struct TokioServer {
+	client: FuseClient,
+	client_socket: UnixDatagram,
+	bridge_threads: [thread::JoinHandle<()>; 2],
+	runtime: thread::JoinHandle<Result<(), io::Error>>,
+}
+
+impl TokioServer {
+	fn start<Handlers>(
+		handlers: Handlers,
+		runtime: tokio::runtime::Runtime,
+	) -> TokioServer
+	where
+		Handlers: FuseHandlers + Send + Sync + 'static,
+	{
+		let (mut client, channel) = FuseClient::new();
+		let (server_socket, client_socket) = UnixDatagram::pair().unwrap();
+		let bridge_threads =
+			bridge(channel, client_socket.try_clone().unwrap());
+
+		let dev_fuse =
+			unsafe { fs::File::from_raw_fd(server_socket.into_raw_fd()) };
+		// The channel is never shut down, so it doesn't matter which
+		// connection is found for the mount.
+		let mount = fs::File::open("/dev/null").unwrap();
+		let channel = DevFuseChannel::new_detached(
+			dev_fuse,
+			path::Path::new("/dev/fuse"),
+			&mount,
+		);
+		let builder = thread::spawn(move || {
+			FuseServerBuilder::<_, _, NoopServerHooks>::new(channel, handlers)
+				.build()
+		});
+		client.fuse_init().unwrap();
+		let server = builder.join().unwrap().unwrap();
+
+		let runtime = thread::spawn(move || {
+			runtime.block_on(async move {
+				let mut server =
+					server.map_channel(TokioDevFuseChannel::new)?;
+				server.executor_mut().run_tokio().await
+			})
+		});
+		TokioServer {
+			client,
+			client_socket,
+			bridge_threads,
+			runtime,
+		}
+	}
+
+	fn stop(self) {
+		self.client.destroy().unwrap();
+		self.runtime.join().unwrap().unwrap();
+
+		self.client_socket.shutdown(Shutdown::Both).unwrap();
+		for thread in self.bridge_threads {
+			thread.join().unwrap();
+		}
+	}
+}
+
+fn new_runtime() -> tokio::runtime::Runtime {
+	tokio::runtime::Builder::new_current_thread()
+		.enable_io()
+		.build()
+		.unwrap()
+}
+
+#[test]
+fn run_tokio() {
+	let mut server = TokioServer::start(HelloFS::new(), new_runtime());
+	let client = &mut server.client;
+
+	let node = client.lookup(ROOT_ID, hello_name()).unwrap();
+	assert_eq!(node.id(), Some(hello_id()));
+	let missing = NodeName::from_bytes(b"missing.txt").unwrap();
+	let err = client.lookup(ROOT_ID, missing).unwrap_err();
+	assert_eq!(err, ErrorCode::ENOENT);
+
+	server.stop();
+}
+
+struct AsyncHelloFS;
+
+impl AsyncFuseHandlers for AsyncHelloFS {
+	fn lookup(
+		&self,
+		_ctx: ServerContext,
+		request: &LookupRequest,
+	) -> HandlerFuture<LookupResponse<'static>> {
+		let found = request.name() == hello_name();
+		Box::pin(async move {
+			tokio::task::yield_now().await;
+			if !found {
+				return Err(ErrorCode::ENOENT);
+			}
+			let mut resp = LookupResponse::new();
+			resp.node_mut().set_id(hello_id());
+			Ok(resp)
+		})
+	}
+}
+
+#[test]
+fn run_tokio_async_handlers() {
+	// Handlers respond from tasks on the same thread that receives
+	// requests, so `run_tokio()` must not block it.
+	let runtime = new_runtime();
+	let handlers =
+		AsyncFuseHandlersAdapter::new(AsyncHelloFS, runtime.handle().clone());
+	let mut server = TokioServer::start(handlers, runtime);
+	let client = &mut server.client;
+
+	let node = client.lookup(ROOT_ID, hello_name()).unwrap();
+	assert_eq!(node.id(), Some(hello_id()));
+	let missing = NodeName::from_bytes(b"missing.txt").unwrap();
+	let err = client.lookup(ROOT_ID, missing).unwrap_err();
+	assert_eq!(err, ErrorCode::ENOENT);
+
+	server.stop();
+}
@@ -37,6 +37,16 @@ impl BmapRequest<'_> {
 	}
 }
 
+impl fmt::Debug for BmapRequest<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("BmapRequest")
+			.field("node_id", &self.node_id())
+			.field("block", &self.block())
+			.field("blocksize", &self.blocksize())
+			.finish()
+	}
+}
+
 impl<'a> fuse_io::DecodeRequest<'a> for BmapRequest<'a> {
 	fn decode_request(
 		mut dec: fuse_io::RequestDecoder<'a>,
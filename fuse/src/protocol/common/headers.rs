@@ -94,6 +94,14 @@ impl fmt::Debug for RequestHeader {
 pub struct ResponseHeader(fuse_kernel::fuse_out_header);
 
 impl ResponseHeader {
+	#[cfg(feature = "std")]
+	pub(crate) fn new_ref(raw: &fuse_kernel::fuse_out_header) -> &Self {
+		unsafe {
+			&*(raw as *const fuse_kernel::fuse_out_header
+				as *const ResponseHeader)
+		}
+	}
+
 	pub fn request_id(&self) -> u64 {
 		self.0.unique
 	}
@@ -65,6 +65,11 @@ impl Node {
 		NodeAttr::new_ref_mut(&mut self.0.attr)
 	}
 
+	#[cfg(feature = "std")]
+	pub(crate) fn new(raw: fuse_kernel::fuse_entry_out) -> Node {
+		Node(raw)
+	}
+
 	pub(crate) fn new_ref(raw: &fuse_kernel::fuse_entry_out) -> &Node {
 		unsafe { &*(raw as *const fuse_kernel::fuse_entry_out as *const Node) }
 	}
@@ -0,0 +1,162 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::protocol::prelude::*;
+
+#[cfg(rust_fuse_test = "copy_file_range_test")]
+mod copy_file_range_test;
+
+// CopyFileRangeRequest {{{
+
+/// Request type for [`FuseHandlers::copy_file_range`].
+///
+/// [`FuseHandlers::copy_file_range`]: ../../trait.FuseHandlers.html#method.copy_file_range
+pub struct CopyFileRangeRequest<'a> {
+	phantom: PhantomData<&'a ()>,
+	input_node_id: NodeId,
+	output_node_id: NodeId,
+	raw: &'a fuse_kernel::fuse_copy_file_range_in,
+}
+
+impl CopyFileRangeRequest<'_> {
+	pub fn input_node_id(&self) -> NodeId {
+		self.input_node_id
+	}
+
+	/// The value passed to [`OpenResponse::set_handle`] when the input file
+	/// was opened, or zero if not set.
+	///
+	/// [`OpenResponse::set_handle`]: struct.OpenResponse.html#method.set_handle
+	pub fn input_handle(&self) -> u64 {
+		self.raw.fh_in
+	}
+
+	pub fn input_offset(&self) -> u64 {
+		self.raw.off_in
+	}
+
+	pub fn output_node_id(&self) -> NodeId {
+		self.output_node_id
+	}
+
+	/// The value passed to [`OpenResponse::set_handle`] when the output file
+	/// was opened, or zero if not set.
+	///
+	/// [`OpenResponse::set_handle`]: struct.OpenResponse.html#method.set_handle
+	pub fn output_handle(&self) -> u64 {
+		self.raw.fh_out
+	}
+
+	pub fn output_offset(&self) -> u64 {
+		self.raw.off_out
+	}
+
+	pub fn len(&self) -> u64 {
+		self.raw.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.raw.len == 0
+	}
+
+	/// Flags passed to `copy_file_range(2)`. No flags are currently defined.
+	pub fn flags(&self) -> u64 {
+		self.raw.flags
+	}
+}
+
+impl fmt::Debug for CopyFileRangeRequest<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("CopyFileRangeRequest")
+			.field("input_node_id", &self.input_node_id)
+			.field("input_handle", &self.raw.fh_in)
+			.field("input_offset", &self.raw.off_in)
+			.field("output_node_id", &self.output_node_id)
+			.field("output_handle", &self.raw.fh_out)
+			.field("output_offset", &self.raw.off_out)
+			.field("len", &self.raw.len)
+			.field("flags", &self.raw.flags)
+			.finish()
+	}
+}
+
+impl<'a> fuse_io::DecodeRequest<'a> for CopyFileRangeRequest<'a> {
+	fn decode_request(
+		mut dec: fuse_io::RequestDecoder<'a>,
+	) -> Result<Self, Error> {
+		let header = dec.header();
+		debug_assert!(header.opcode == fuse_kernel::FUSE_COPY_FILE_RANGE);
+		let raw: &'a fuse_kernel::fuse_copy_file_range_in = dec.next_sized()?;
+		Ok(Self {
+			phantom: PhantomData,
+			input_node_id: try_node_id(header.nodeid)?,
+			output_node_id: try_node_id(raw.nodeid_out)?,
+			raw,
+		})
+	}
+}
+
+// }}}
+
+// CopyFileRangeResponse {{{
+
+/// Response type for [`FuseHandlers::copy_file_range`].
+///
+/// [`FuseHandlers::copy_file_range`]: ../../trait.FuseHandlers.html#method.copy_file_range
+pub struct CopyFileRangeResponse<'a> {
+	phantom: PhantomData<&'a ()>,
+	raw: fuse_kernel::fuse_write_out,
+}
+
+impl<'a> CopyFileRangeResponse<'a> {
+	pub fn new() -> CopyFileRangeResponse<'a> {
+		Self {
+			phantom: PhantomData,
+			raw: fuse_kernel::fuse_write_out {
+				size: 0,
+				padding: 0,
+			},
+		}
+	}
+
+	pub fn size(&self) -> u32 {
+		self.raw.size
+	}
+
+	/// Sets the number of bytes copied.
+	pub fn set_size(&mut self, size: u32) {
+		self.raw.size = size;
+	}
+}
+
+impl fmt::Debug for CopyFileRangeResponse<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("CopyFileRangeResponse")
+			.field("size", &self.raw.size)
+			.finish()
+	}
+}
+
+impl fuse_io::EncodeResponse for CopyFileRangeResponse<'_> {
+	fn encode_response<'a, Chan: fuse_io::Channel>(
+		&'a self,
+		enc: fuse_io::ResponseEncoder<Chan>,
+	) -> Result<(), Chan::Error> {
+		enc.encode_sized(&self.raw)
+	}
+}
+
+// }}}
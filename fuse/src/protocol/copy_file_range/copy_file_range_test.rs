@@ -0,0 +1,119 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::internal::testutil::MessageBuilder;
+use crate::protocol::prelude::*;
+
+use super::{CopyFileRangeRequest, CopyFileRangeResponse};
+
+#[test]
+fn request() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_COPY_FILE_RANGE;
+			h.nodeid = 123;
+		})
+		.push_sized(&fuse_kernel::fuse_copy_file_range_in {
+			fh_in: 1,
+			off_in: 2,
+			nodeid_out: 456,
+			fh_out: 3,
+			off_out: 4,
+			len: 5,
+			flags: 0,
+		})
+		.build_aligned();
+
+	let req: CopyFileRangeRequest = decode_request!(buf);
+
+	assert_eq!(req.input_node_id(), NodeId::new(123).unwrap());
+	assert_eq!(req.input_handle(), 1);
+	assert_eq!(req.input_offset(), 2);
+	assert_eq!(req.output_node_id(), NodeId::new(456).unwrap());
+	assert_eq!(req.output_handle(), 3);
+	assert_eq!(req.output_offset(), 4);
+	assert_eq!(req.len(), 5);
+	assert_eq!(req.flags(), 0);
+}
+
+#[test]
+fn request_impl_debug() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_COPY_FILE_RANGE;
+			h.nodeid = 123;
+		})
+		.push_sized(&fuse_kernel::fuse_copy_file_range_in {
+			fh_in: 1,
+			off_in: 2,
+			nodeid_out: 456,
+			fh_out: 3,
+			off_out: 4,
+			len: 5,
+			flags: 0,
+		})
+		.build_aligned();
+	let request: CopyFileRangeRequest = decode_request!(buf);
+
+	assert_eq!(
+		format!("{:#?}", request),
+		concat!(
+			"CopyFileRangeRequest {\n",
+			"    input_node_id: 123,\n",
+			"    input_handle: 1,\n",
+			"    input_offset: 2,\n",
+			"    output_node_id: 456,\n",
+			"    output_handle: 3,\n",
+			"    output_offset: 4,\n",
+			"    len: 5,\n",
+			"    flags: 0,\n",
+			"}",
+		),
+	);
+}
+
+#[test]
+fn response() {
+	let mut resp = CopyFileRangeResponse::new();
+	resp.set_size(4096);
+	let encoded = encode_response!(resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: (size_of::<fuse_kernel::fuse_out_header>()
+					+ size_of::<fuse_kernel::fuse_write_out>()) as u32,
+				error: 0,
+				unique: 0,
+			})
+			.push_sized(&fuse_kernel::fuse_write_out {
+				size: 4096,
+				padding: 0,
+			})
+			.build()
+	);
+}
+
+#[test]
+fn response_impl_debug() {
+	let mut response = CopyFileRangeResponse::new();
+	response.set_size(4096);
+	assert_eq!(
+		format!("{:#?}", response),
+		concat!("CopyFileRangeResponse {\n", "    size: 4096,\n", "}",),
+	);
+}
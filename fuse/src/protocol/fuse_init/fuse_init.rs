@@ -132,12 +132,22 @@ impl FuseInitResponse {
 				congestion_threshold: 0,
 				max_write: 0,
 				time_gran: 0,
-				unused: [0; 9],
+				max_pages: 0,
+				padding: 0,
+				unused: [0; 8],
 			},
 			flags: FuseInitFlags::new(),
 		}
 	}
 
+	#[cfg(feature = "std")]
+	pub(crate) fn from_raw(raw: fuse_kernel::fuse_init_out) -> Self {
+		Self {
+			raw,
+			flags: FuseInitFlags::from_bits(raw.flags),
+		}
+	}
+
 	pub(crate) fn version(&self) -> ProtocolVersion {
 		ProtocolVersion::new(self.raw.major, self.raw.minor)
 	}
@@ -194,8 +204,60 @@ impl FuseInitResponse {
 	pub fn set_time_granularity(&mut self, granularity: u32) {
 		self.raw.time_gran = granularity;
 	}
+
+	/// The maximum number of pages in a single read or write request.
+	///
+	/// Only used if [`FuseInitFlags::max_pages`] is set. Otherwise the
+	/// kernel uses its default limit of 32 pages.
+	///
+	/// [`FuseInitFlags::max_pages`]: struct.FuseInitFlags.html#structfield.max_pages
+	pub fn max_pages(&self) -> u16 {
+		self.raw.max_pages
+	}
+
+	/// Sets the maximum number of pages in a single read or write request.
+	///
+	/// If [`FuseInitFlags::max_pages`] is set and this value is zero, it
+	/// will be derived from [`max_write`](#method.max_write). Values larger
+	/// than the kernel's limit of 256 pages are reduced to that limit.
+	///
+	/// [`FuseInitFlags::max_pages`]: struct.FuseInitFlags.html#structfield.max_pages
+	pub fn set_max_pages(&mut self, max_pages: u16) {
+		self.raw.max_pages = max_pages;
+	}
+
+	// Reconciles `max_pages` with the kernel's capabilities and `max_write`.
+	//
+	// After this returns, `max_write` is no larger than the number of bytes
+	// permitted by `max_pages`, so read buffers sized from `max_write` are
+	// large enough for any request the kernel sends.
+	pub(crate) fn negotiate_max_pages(&mut self, request: &FuseInitRequest) {
+		if self.raw.minor < 28 || !request.flags().max_pages {
+			self.flags.max_pages = false;
+		}
+		if !self.flags.max_pages {
+			self.raw.max_pages = 0;
+			return;
+		}
+
+		let mut max_pages = u32::from(self.raw.max_pages);
+		if max_pages == 0 {
+			max_pages = self.raw.max_write.div_ceil(PAGE_SIZE);
+		}
+		let max_pages = max_pages.clamp(1, MAX_MAX_PAGES);
+		self.raw.max_pages = max_pages as u16;
+		self.raw.max_write = min(self.raw.max_write, max_pages * PAGE_SIZE);
+	}
 }
 
+// The kernel's upper bound on `max_pages` (`FUSE_MAX_MAX_PAGES`).
+const MAX_MAX_PAGES: u32 = 256;
+
+// Page size used to convert between `max_pages` and `max_write`. Kernels with
+// larger pages allow larger requests, but those are still bounded by
+// `max_write`, so the smallest common page size is a safe choice.
+const PAGE_SIZE: u32 = 4096;
+
 impl fmt::Debug for FuseInitResponse {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("FuseInitResponse")
@@ -205,6 +267,7 @@ impl fmt::Debug for FuseInitResponse {
 			.field("congestion_threshold", &self.congestion_threshold())
 			.field("max_write", &self.max_write())
 			.field("time_granularity", &self.time_granularity())
+			.field("max_pages", &self.max_pages())
 			.finish()
 	}
 }
@@ -285,6 +348,8 @@ bitflags_struct! {
 	fuse_kernel::FUSE_HANDLE_KILLPRIV: handle_killpriv,
 	fuse_kernel::FUSE_POSIX_ACL: posix_acl,
 	fuse_kernel::FUSE_ABORT_ERROR: abort_error,
+	fuse_kernel::FUSE_MAX_PAGES: max_pages,
+	fuse_kernel::FUSE_CACHE_SYMLINKS: cache_symlinks,
 }
 
 // }}}
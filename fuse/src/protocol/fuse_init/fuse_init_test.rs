@@ -149,12 +149,115 @@ fn response_v7p23() {
 				congestion_threshold: 0,
 				max_write: 0,
 				time_gran: 0,
-				unused: [0; 9],
+				max_pages: 0,
+				padding: 0,
+				unused: [0; 8],
 			})
 			.build()
 	);
 }
 
+#[test]
+fn response_v7p28() {
+	let mut resp = FuseInitResponse::new();
+	resp.set_version(ProtocolVersion::new(7, 28));
+	resp.set_max_write(1 << 20);
+	resp.set_max_pages(256);
+	resp.flags_mut().max_pages = true;
+	let encoded = encode_response!(resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: (size_of::<fuse_kernel::fuse_out_header>()
+					+ size_of::<fuse_kernel::fuse_init_out>()) as u32,
+				error: 0,
+				unique: 0,
+			})
+			.push_sized(&fuse_kernel::fuse_init_out {
+				major: 7,
+				minor: 28,
+				max_readahead: 0,
+				flags: fuse_kernel::FUSE_MAX_PAGES,
+				max_background: 0,
+				congestion_threshold: 0,
+				max_write: 1 << 20,
+				time_gran: 0,
+				max_pages: 256,
+				padding: 0,
+				unused: [0; 8],
+			})
+			.build()
+	);
+}
+
+fn max_pages_request(flags: u32) -> FuseInitRequest<'static> {
+	FuseInitRequest {
+		phantom: PhantomData,
+		version: ProtocolVersion::new(7, 28),
+		max_readahead: 0,
+		flags: FuseInitFlags::from_bits(flags),
+	}
+}
+
+#[test]
+fn negotiate_max_pages() {
+	let request = max_pages_request(fuse_kernel::FUSE_MAX_PAGES);
+
+	// Explicit `max_pages` limits `max_write`.
+	let mut resp = FuseInitResponse::new();
+	resp.set_version(ProtocolVersion::new(7, 28));
+	resp.set_max_write(1 << 20);
+	resp.set_max_pages(64);
+	resp.flags_mut().max_pages = true;
+	resp.negotiate_max_pages(&request);
+	assert_eq!(resp.max_pages(), 64);
+	assert_eq!(resp.max_write(), 64 * 4096);
+
+	// Zero `max_pages` is derived from `max_write`.
+	let mut resp = FuseInitResponse::new();
+	resp.set_version(ProtocolVersion::new(7, 28));
+	resp.set_max_write((1 << 20) - 1);
+	resp.flags_mut().max_pages = true;
+	resp.negotiate_max_pages(&request);
+	assert_eq!(resp.max_pages(), 256);
+	assert_eq!(resp.max_write(), (1 << 20) - 1);
+
+	// Oversized `max_pages` is capped to the kernel limit.
+	let mut resp = FuseInitResponse::new();
+	resp.set_version(ProtocolVersion::new(7, 28));
+	resp.set_max_write(4 << 20);
+	resp.set_max_pages(1000);
+	resp.flags_mut().max_pages = true;
+	resp.negotiate_max_pages(&request);
+	assert_eq!(resp.max_pages(), 256);
+	assert_eq!(resp.max_write(), 1 << 20);
+}
+
+#[test]
+fn negotiate_max_pages_unsupported() {
+	// Kernel did not offer FUSE_MAX_PAGES.
+	let mut resp = FuseInitResponse::new();
+	resp.set_version(ProtocolVersion::new(7, 28));
+	resp.set_max_write(1 << 20);
+	resp.set_max_pages(256);
+	resp.flags_mut().max_pages = true;
+	resp.negotiate_max_pages(&max_pages_request(0));
+	assert_eq!(resp.flags().max_pages, false);
+	assert_eq!(resp.max_pages(), 0);
+	assert_eq!(resp.max_write(), 1 << 20);
+
+	// Negotiated version predates FUSE_MAX_PAGES.
+	let mut resp = FuseInitResponse::new();
+	resp.set_version(ProtocolVersion::new(7, 27));
+	resp.set_max_pages(256);
+	resp.flags_mut().max_pages = true;
+	resp.negotiate_max_pages(&max_pages_request(fuse_kernel::FUSE_MAX_PAGES));
+	assert_eq!(resp.flags().max_pages, false);
+	assert_eq!(resp.max_pages(), 0);
+}
+
 #[test]
 fn init_flags() {
 	// Flag sets render as a struct, with unknown flags falling back
@@ -185,6 +288,8 @@ fn init_flags() {
 			"    handle_killpriv: false,\n",
 			"    posix_acl: false,\n",
 			"    abort_error: false,\n",
+			"    max_pages: false,\n",
+			"    cache_symlinks: false,\n",
 			"    0x80000000: true,\n",
 			"}",
 		),
@@ -233,6 +338,8 @@ fn request_impl_debug() {
 			"        handle_killpriv: false,\n",
 			"        posix_acl: false,\n",
 			"        abort_error: false,\n",
+			"        max_pages: false,\n",
+			"        cache_symlinks: false,\n",
 			"    },\n",
 			"}",
 		),
@@ -277,11 +384,14 @@ fn response_impl_debug() {
 			"        handle_killpriv: false,\n",
 			"        posix_acl: false,\n",
 			"        abort_error: false,\n",
+			"        max_pages: false,\n",
+			"        cache_symlinks: false,\n",
 			"    },\n",
 			"    max_background: 10,\n",
 			"    congestion_threshold: 11,\n",
 			"    max_write: 8192,\n",
 			"    time_granularity: 100,\n",
+			"    max_pages: 0,\n",
 			"}",
 		),
 	);
@@ -14,30 +14,83 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of_val;
+
 use crate::protocol::prelude::*;
 
+#[cfg(rust_fuse_test = "ioctl_test")]
+mod ioctl_test;
+
 // IoctlRequest {{{
 
+/// Request type for [`FuseHandlers::ioctl`] and [`CuseHandlers::ioctl`].
+///
+/// [`FuseHandlers::ioctl`]: ../../trait.FuseHandlers.html#method.ioctl
+/// [`CuseHandlers::ioctl`]: ../../trait.CuseHandlers.html#method.ioctl
 pub struct IoctlRequest<'a> {
-	header: &'a fuse_kernel::fuse_in_header,
-	raw: &'a fuse_kernel::fuse_ioctl_in,
-	buf: &'a [u8],
+	phantom: PhantomData<&'a ()>,
+	node_id: NodeId,
+	handle: u64,
+	flags: IoctlRequestFlags,
+	command: u32,
+	arg: u64,
+	input: &'a [u8],
+	output_size: u32,
 }
 
-impl IoctlRequest<'_> {
-	pub fn node_id(&self) -> u64 {
-		self.header.nodeid
+impl<'a> IoctlRequest<'a> {
+	pub fn node_id(&self) -> NodeId {
+		self.node_id
 	}
+
+	/// The value passed to [`OpenResponse::set_handle`], or zero if not set.
+	///
+	/// [`OpenResponse::set_handle`]: struct.OpenResponse.html#method.set_handle
 	pub fn handle(&self) -> u64 {
-		self.raw.fh
+		self.handle
+	}
+
+	pub fn flags(&self) -> &IoctlRequestFlags {
+		&self.flags
 	}
 
 	pub fn command(&self) -> u32 {
-		self.raw.cmd
+		self.command
+	}
+
+	/// The `ioctl(2)` argument, as passed by the caller.
+	///
+	/// For restricted ioctls this value is opaque; the data it points to is
+	/// available in [`input`].
+	///
+	/// [`input`]: #method.input
+	pub fn arg(&self) -> u64 {
+		self.arg
+	}
+
+	/// Input data copied from the caller, with a size determined by the
+	/// ioctl command.
+	pub fn input(&self) -> &'a [u8] {
+		self.input
+	}
+
+	/// The maximum size of output data the caller will accept.
+	pub fn output_size(&self) -> u32 {
+		self.output_size
 	}
+}
 
-	pub fn buf(&self) -> &[u8] {
-		self.buf
+impl fmt::Debug for IoctlRequest<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("IoctlRequest")
+			.field("node_id", &self.node_id)
+			.field("handle", &self.handle)
+			.field("flags", &self.flags)
+			.field("command", &DebugHexU32(self.command))
+			.field("arg", &self.arg)
+			.field("input", &DebugBytesAsString(self.input))
+			.field("output_size", &self.output_size)
+			.finish()
 	}
 }
 
@@ -47,97 +100,302 @@ impl<'a> fuse_io::DecodeRequest<'a> for IoctlRequest<'a> {
 	) -> Result<Self, Error> {
 		let header = dec.header();
 		debug_assert!(header.opcode == fuse_kernel::FUSE_IOCTL);
-		let raw: &'a fuse_kernel::fuse_ioctl_in = dec.next_sized()?;
 
-		/* TODO
-		if (raw.flags & fuse_kernel::FUSE_IOCTL_DIR) > 0 {
-			if !r.init_flags().get(InitFlag::IOCTL_DIR) {
-				return Err(ENOTTY);
-			}
-		}
-		*/
+		let node_id = if dec.is_cuse() {
+			crate::ROOT_ID
+		} else {
+			try_node_id(header.nodeid)?
+		};
+
+		let raw: &'a fuse_kernel::fuse_ioctl_in = dec.next_sized()?;
+		let input = dec.next_bytes(raw.in_size)?;
+		Ok(Self {
+			phantom: PhantomData,
+			node_id,
+			handle: raw.fh,
+			flags: IoctlRequestFlags::from_bits(raw.flags),
+			command: raw.cmd,
+			arg: raw.arg,
+			input,
+			output_size: raw.out_size,
+		})
+	}
+}
 
-		let buf = dec.next_bytes(raw.in_size)?;
-		Ok(Self { header, raw, buf })
+// Returns whether a `FUSE_IOCTL` request was made on a directory, without
+// fully decoding it.
+pub(crate) fn ioctl_is_dir(dec: &fuse_io::RequestDecoder) -> bool {
+	match dec.peek_sized::<fuse_kernel::fuse_ioctl_in>() {
+		Ok(raw) => raw.flags & fuse_kernel::FUSE_IOCTL_DIR != 0,
+		Err(_) => false,
 	}
 }
 
 // }}}
 
+// IoctlRequestFlags {{{
+
+bitflags_struct! {
+	/// Optional flags set on [`IoctlRequest`].
+	///
+	/// [`IoctlRequest`]: struct.IoctlRequest.html
+	pub struct IoctlRequestFlags(u32);
+
+	/// The ioctl was issued by a 32-bit process on a 64-bit kernel.
+	fuse_kernel::FUSE_IOCTL_COMPAT: compat,
+
+	/// The ioctl may be retried with [`IoctlResponse::set_retry`]. Only set
+	/// for CUSE servers that enabled unrestricted ioctls.
+	///
+	/// [`IoctlResponse::set_retry`]: struct.IoctlResponse.html#method.set_retry
+	fuse_kernel::FUSE_IOCTL_UNRESTRICTED: unrestricted,
+
+	/// The ioctl was issued by a 32-bit process.
+	fuse_kernel::FUSE_IOCTL_32BIT: ioctl_32bit,
+
+	/// The ioctl was issued on a directory.
+	fuse_kernel::FUSE_IOCTL_DIR: dir,
+}
+
+// }}}
+
 // IoctlResponse {{{
 
-const PAGE_SIZE: usize = 4096;
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct IoctlError {
+	kind: IoctlErrorKind,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum IoctlErrorKind {
+	ExceedsOutputSize(usize, u32),
+	ExceedsMaxIovecs(usize),
+}
+
+impl IoctlError {
+	fn exceeds_output_size(size: usize, output_size: u32) -> IoctlError {
+		IoctlError {
+			kind: IoctlErrorKind::ExceedsOutputSize(size, output_size),
+		}
+	}
 
-enum OutBuf {
-	OutArr([u8; 4096], usize),
-	OutVec(Vec<u8>),
+	fn exceeds_max_iovecs(count: usize) -> IoctlError {
+		IoctlError {
+			kind: IoctlErrorKind::ExceedsMaxIovecs(count),
+		}
+	}
 }
 
+impl fmt::Display for IoctlError {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		fmt::Debug::fmt(self, fmt)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IoctlError {}
+
+/// Response type for [`FuseHandlers::ioctl`] and [`CuseHandlers::ioctl`].
+///
+/// [`FuseHandlers::ioctl`]: ../../trait.FuseHandlers.html#method.ioctl
+/// [`CuseHandlers::ioctl`]: ../../trait.CuseHandlers.html#method.ioctl
 pub struct IoctlResponse<'a> {
-	phantom: PhantomData<&'a ()>,
+	output_size: u32,
 	raw: fuse_kernel::fuse_ioctl_out,
-	buf: OutBuf,
+	output: &'a [u8],
+	retry_input: &'a [IoctlIovec],
+	retry_output: &'a [IoctlIovec],
 }
 
 impl<'a> IoctlResponse<'a> {
-	// TODO: fix construction API
-	pub fn new(request: &IoctlRequest) -> IoctlResponse<'a> {
-		let out_size = request.raw.out_size as usize;
-		let buf: OutBuf;
-		if out_size > PAGE_SIZE {
-			buf = OutBuf::OutVec(Vec::with_capacity(out_size));
-		} else {
-			buf = OutBuf::OutArr([0; 4096], out_size)
-		}
+	/// Constructs a new, empty `IoctlResponse` that can hold up to
+	/// `output_size` bytes of output. This should be the value of
+	/// [`IoctlRequest::output_size`].
+	///
+	/// [`IoctlRequest::output_size`]: struct.IoctlRequest.html#method.output_size
+	pub fn new(output_size: u32) -> IoctlResponse<'a> {
 		Self {
-			phantom: PhantomData,
+			output_size,
 			raw: Default::default(),
-			buf: buf,
+			output: &[],
+			retry_input: &[],
+			retry_output: &[],
 		}
 	}
 
+	/// The value returned to the caller of `ioctl(2)`.
+	pub fn result(&self) -> i32 {
+		self.raw.result
+	}
+
 	pub fn set_result(&mut self, result: i32) {
 		self.raw.result = result;
 	}
 
-	pub fn buf(&self) -> &[u8] {
-		match self.buf {
-			OutBuf::OutVec(ref b) => b,
-			OutBuf::OutArr(ref arr, buf_size) => {
-				let (buf, _) = arr.split_at(buf_size);
-				buf
-			},
+	pub fn output(&self) -> &[u8] {
+		self.output
+	}
+
+	pub fn set_output(&mut self, output: &'a [u8]) {
+		self.try_set_output(output).unwrap()
+	}
+
+	pub fn try_set_output(
+		&mut self,
+		output: &'a [u8],
+	) -> Result<(), IoctlError> {
+		if output.len() > self.output_size as usize {
+			return Err(IoctlError::exceeds_output_size(
+				output.len(),
+				self.output_size,
+			));
 		}
+		self.output = output;
+		Ok(())
+	}
+
+	/// Asks the kernel to retry an unrestricted ioctl with the given memory
+	/// regions of the calling process.
+	///
+	/// The kernel will send a second request for the same ioctl, with
+	/// [`IoctlRequest::input`] containing the concatenated contents of
+	/// `input`, and with an [`IoctlRequest::output_size`] equal to the total
+	/// length of `output`. Output data sent in response to the second request
+	/// is scattered into the `output` regions.
+	///
+	/// This is only valid when [`IoctlRequestFlags::unrestricted`] is set,
+	/// which requires a CUSE server with [`CuseInitFlags::unrestricted_ioctl`]
+	/// and FUSE protocol v7.16 or later. Any previously set output is
+	/// discarded.
+	///
+	/// # Panics
+	///
+	/// Panics if the total number of iovecs exceeds the kernel's limit of
+	/// `FUSE_IOCTL_MAX_IOV` (256).
+	///
+	/// [`IoctlRequest::input`]: struct.IoctlRequest.html#method.input
+	/// [`IoctlRequest::output_size`]: struct.IoctlRequest.html#method.output_size
+	/// [`IoctlRequestFlags::unrestricted`]: struct.IoctlRequestFlags.html#structfield.unrestricted
+	/// [`CuseInitFlags::unrestricted_ioctl`]: struct.CuseInitFlags.html#structfield.unrestricted_ioctl
+	pub fn set_retry(
+		&mut self,
+		input: &'a [IoctlIovec],
+		output: &'a [IoctlIovec],
+	) {
+		self.try_set_retry(input, output).unwrap()
 	}
 
-	pub fn buf_mut(&mut self) -> &mut [u8] {
-		match self.buf {
-			OutBuf::OutVec(ref mut b) => b,
-			OutBuf::OutArr(ref mut arr, buf_size) => {
-				let (buf, _) = arr.split_at_mut(buf_size);
-				buf
-			},
+	pub fn try_set_retry(
+		&mut self,
+		input: &'a [IoctlIovec],
+		output: &'a [IoctlIovec],
+	) -> Result<(), IoctlError> {
+		let count = input.len().saturating_add(output.len());
+		if count > fuse_kernel::FUSE_IOCTL_MAX_IOV as usize {
+			return Err(IoctlError::exceeds_max_iovecs(count));
 		}
+		self.raw.flags = fuse_kernel::FUSE_IOCTL_RETRY;
+		self.raw.in_iovs = input.len() as u32;
+		self.raw.out_iovs = output.len() as u32;
+		self.output = &[];
+		self.retry_input = input;
+		self.retry_output = output;
+		Ok(())
+	}
+
+	pub fn is_retry(&self) -> bool {
+		self.raw.flags & fuse_kernel::FUSE_IOCTL_RETRY != 0
+	}
+
+	pub fn retry_input(&self) -> &[IoctlIovec] {
+		self.retry_input
+	}
+
+	pub fn retry_output(&self) -> &[IoctlIovec] {
+		self.retry_output
 	}
 }
 
 impl fmt::Debug for IoctlResponse<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-		fmt.debug_struct("IoctlResponse")
-			.field("result", &self.raw.result)
-			.field("buf", &self.buf())
-			.finish()
+		let mut out = fmt.debug_struct("IoctlResponse");
+		if self.is_retry() {
+			out.field("retry_input", &self.retry_input);
+			out.field("retry_output", &self.retry_output);
+		} else {
+			out.field("result", &self.raw.result);
+			out.field("output", &DebugBytesAsString(self.output));
+		}
+		out.finish()
 	}
 }
 
 impl fuse_io::EncodeResponse for IoctlResponse<'_> {
 	fn encode_response<'a, Chan: fuse_io::Channel>(
 		&'a self,
-		_enc: fuse_io::ResponseEncoder<Chan>,
+		enc: fuse_io::ResponseEncoder<Chan>,
 	) -> Result<(), Chan::Error> {
-		todo!()
-		//w.append_sized(&self.raw);
-		//w.append_bytes(self.buf())
+		let raw: &[u8] = unsafe {
+			slice::from_raw_parts(
+				(&self.raw as *const fuse_kernel::fuse_ioctl_out) as *const u8,
+				size_of::<fuse_kernel::fuse_ioctl_out>(),
+			)
+		};
+		if self.is_retry() {
+			return enc.encode_bytes_4(
+				raw,
+				iovecs_as_bytes(self.retry_input),
+				iovecs_as_bytes(self.retry_output),
+				&[],
+			);
+		}
+		enc.encode_bytes_2(raw, self.output)
+	}
+}
+
+// }}}
+
+// IoctlIovec {{{
+
+/// A region of memory in the process that called `ioctl(2)`.
+///
+/// See [`IoctlResponse::set_retry`].
+///
+/// [`IoctlResponse::set_retry`]: struct.IoctlResponse.html#method.set_retry
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct IoctlIovec(fuse_kernel::fuse_ioctl_iovec);
+
+impl IoctlIovec {
+	pub fn new(base: u64, len: u64) -> IoctlIovec {
+		Self(fuse_kernel::fuse_ioctl_iovec { base, len })
+	}
+
+	pub fn base(&self) -> u64 {
+		self.0.base
+	}
+
+	pub fn len(&self) -> u64 {
+		self.0.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.len == 0
+	}
+}
+
+impl fmt::Debug for IoctlIovec {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("IoctlIovec")
+			.field("base", &self.0.base)
+			.field("len", &self.0.len)
+			.finish()
+	}
+}
+
+fn iovecs_as_bytes(iovecs: &[IoctlIovec]) -> &[u8] {
+	unsafe {
+		slice::from_raw_parts(iovecs.as_ptr() as *const u8, size_of_val(iovecs))
 	}
 }
 
@@ -0,0 +1,209 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::internal::testutil::MessageBuilder;
+use crate::protocol::prelude::*;
+
+use super::{IoctlIovec, IoctlRequest, IoctlRequestFlags, IoctlResponse};
+
+#[test]
+fn request() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_IOCTL;
+			h.nodeid = 123;
+		})
+		.push_sized(&fuse_kernel::fuse_ioctl_in {
+			fh: 12,
+			flags: fuse_kernel::FUSE_IOCTL_DIR,
+			cmd: 0x5401,
+			arg: 0xABCD,
+			in_size: 5,
+			out_size: 16,
+		})
+		.push_bytes(b"hello")
+		.build_aligned();
+
+	let req: IoctlRequest = decode_request!(buf);
+
+	assert_eq!(req.node_id(), NodeId::new(123).unwrap());
+	assert_eq!(req.handle(), 12);
+	assert_eq!(req.flags().dir, true);
+	assert_eq!(req.flags().compat, false);
+	assert_eq!(req.command(), 0x5401);
+	assert_eq!(req.arg(), 0xABCD);
+	assert_eq!(req.input(), b"hello");
+	assert_eq!(req.output_size(), 16);
+}
+
+#[test]
+fn request_impl_debug() {
+	let request = &IoctlRequest {
+		phantom: PhantomData,
+		node_id: crate::ROOT_ID,
+		handle: 12,
+		flags: IoctlRequestFlags::from_bits(fuse_kernel::FUSE_IOCTL_32BIT),
+		command: 0x5401,
+		arg: 0xABCD,
+		input: b"hello",
+		output_size: 16,
+	};
+
+	assert_eq!(
+		format!("{:#?}", request),
+		concat!(
+			"IoctlRequest {\n",
+			"    node_id: 1,\n",
+			"    handle: 12,\n",
+			"    flags: IoctlRequestFlags {\n",
+			"        compat: false,\n",
+			"        unrestricted: false,\n",
+			"        ioctl_32bit: true,\n",
+			"        dir: false,\n",
+			"    },\n",
+			"    command: 0x00005401,\n",
+			"    arg: 43981,\n",
+			"    input: \"hello\",\n",
+			"    output_size: 16,\n",
+			"}",
+		),
+	);
+}
+
+#[test]
+fn response() {
+	let mut resp = IoctlResponse::new(16);
+	resp.set_result(7);
+	resp.set_output(b"world");
+	let encoded = encode_response!(resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: (size_of::<fuse_kernel::fuse_out_header>()
+					+ size_of::<fuse_kernel::fuse_ioctl_out>()
+					+ 5) as u32,
+				error: 0,
+				unique: 0,
+			})
+			.push_sized(&fuse_kernel::fuse_ioctl_out {
+				result: 7,
+				flags: 0,
+				in_iovs: 0,
+				out_iovs: 0,
+			})
+			.push_bytes(b"world")
+			.build()
+	);
+}
+
+#[test]
+fn response_exceeds_output_size() {
+	let mut resp = IoctlResponse::new(4);
+	assert!(resp.try_set_output(b"world").is_err());
+	assert_eq!(resp.output(), b"");
+}
+
+#[test]
+fn response_retry() {
+	let input = [IoctlIovec::new(0x1000, 8)];
+	let output = [IoctlIovec::new(0x2000, 4), IoctlIovec::new(0x3000, 2)];
+	let mut resp = IoctlResponse::new(0);
+	resp.set_retry(&input, &output);
+	assert!(resp.is_retry());
+	let encoded = encode_response!(resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: (size_of::<fuse_kernel::fuse_out_header>()
+					+ size_of::<fuse_kernel::fuse_ioctl_out>()
+					+ 3 * size_of::<fuse_kernel::fuse_ioctl_iovec>())
+					as u32,
+				error: 0,
+				unique: 0,
+			})
+			.push_sized(&fuse_kernel::fuse_ioctl_out {
+				result: 0,
+				flags: fuse_kernel::FUSE_IOCTL_RETRY,
+				in_iovs: 1,
+				out_iovs: 2,
+			})
+			.push_sized(&fuse_kernel::fuse_ioctl_iovec {
+				base: 0x1000,
+				len: 8,
+			})
+			.push_sized(&fuse_kernel::fuse_ioctl_iovec {
+				base: 0x2000,
+				len: 4,
+			})
+			.push_sized(&fuse_kernel::fuse_ioctl_iovec {
+				base: 0x3000,
+				len: 2,
+			})
+			.build()
+	);
+}
+
+#[test]
+fn response_retry_exceeds_max_iovecs() {
+	let iovecs = [IoctlIovec::new(0, 0); 257];
+	let mut resp = IoctlResponse::new(0);
+	assert!(resp.try_set_retry(&iovecs[..200], &iovecs[..57]).is_err());
+	assert!(!resp.is_retry());
+	assert!(resp.try_set_retry(&iovecs[..200], &iovecs[..56]).is_ok());
+}
+
+#[test]
+fn response_retry_impl_debug() {
+	let input = [IoctlIovec::new(4096, 8)];
+	let mut response = IoctlResponse::new(0);
+	response.set_retry(&input, &[]);
+
+	assert_eq!(
+		format!("{:#?}", response),
+		concat!(
+			"IoctlResponse {\n",
+			"    retry_input: [\n",
+			"        IoctlIovec {\n",
+			"            base: 4096,\n",
+			"            len: 8,\n",
+			"        },\n",
+			"    ],\n",
+			"    retry_output: [],\n",
+			"}",
+		),
+	);
+}
+
+#[test]
+fn response_impl_debug() {
+	let mut response = IoctlResponse::new(16);
+	response.set_result(7);
+	response.set_output(b"world");
+
+	assert_eq!(
+		format!("{:#?}", response),
+		concat!(
+			"IoctlResponse {\n",
+			"    result: 7,\n",
+			"    output: \"world\",\n",
+			"}",
+		),
+	);
+}
@@ -41,6 +41,10 @@ pub mod bmap;
 #[cfg(any(doc, feature = "unstable_bmap"))]
 pub use self::bmap::*;
 
+#[path = "copy_file_range/copy_file_range.rs"]
+pub mod copy_file_range;
+pub use self::copy_file_range::*;
+
 #[path = "create/create.rs"]
 pub mod create;
 pub use self::create::*;
@@ -85,11 +89,8 @@ pub use self::getlk::*;
 pub mod getxattr;
 pub use self::getxattr::*;
 
-#[cfg(any(doc, feature = "unstable_ioctl"))]
-#[cfg_attr(doc, doc(cfg(feature = "unstable_ioctl")))]
 #[path = "ioctl/ioctl.rs"]
 pub mod ioctl;
-#[cfg(any(doc, feature = "unstable_ioctl"))]
 pub use self::ioctl::*;
 
 #[path = "link/link.rs"]
@@ -116,6 +117,10 @@ pub use self::mkdir::*;
 pub mod mknod;
 pub use self::mknod::*;
 
+#[path = "notify_reply/notify_reply.rs"]
+pub mod notify_reply;
+pub use self::notify_reply::*;
+
 #[path = "open/open.rs"]
 pub mod open;
 pub use self::open::*;
@@ -124,6 +129,10 @@ pub use self::open::*;
 pub mod opendir;
 pub use self::opendir::*;
 
+#[path = "poll/poll.rs"]
+pub mod poll;
+pub use self::poll::*;
+
 #[path = "read/read.rs"]
 pub mod read;
 pub use self::read::*;
@@ -132,6 +141,10 @@ pub use self::read::*;
 pub mod readdir;
 pub use self::readdir::*;
 
+#[path = "readdirplus/readdirplus.rs"]
+pub mod readdirplus;
+pub use self::readdirplus::*;
+
 #[path = "readlink/readlink.rs"]
 pub mod readlink;
 pub use self::readlink::*;
@@ -156,11 +169,8 @@ pub use self::rename::*;
 pub mod rmdir;
 pub use self::rmdir::*;
 
-#[cfg(any(doc, feature = "unstable_setattr"))]
-#[cfg_attr(doc, doc(cfg(feature = "unstable_setattr")))]
 #[path = "setattr/setattr.rs"]
 pub mod setattr;
-#[cfg(any(doc, feature = "unstable_setattr"))]
 pub use self::setattr::*;
 
 #[path = "setlk/setlk.rs"]
@@ -0,0 +1,85 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::protocol::prelude::*;
+
+#[cfg(rust_fuse_test = "notify_reply_test")]
+mod notify_reply_test;
+
+// RetrieveReply {{{
+
+/// Cached data sent by the kernel in reply to [`Notifier::retrieve`].
+///
+/// The kernel sends this as a `FUSE_NOTIFY_REPLY` message, which has the
+/// same request ID as the `FUSE_NOTIFY_RETRIEVE` notification it replies to.
+///
+/// [`Notifier::retrieve`]: ../../struct.Notifier.html#method.retrieve
+pub struct RetrieveReply<'a> {
+	notify_unique: u64,
+	node_id: NodeId,
+	offset: u64,
+	data: &'a [u8],
+}
+
+impl RetrieveReply<'_> {
+	pub(crate) fn notify_unique(&self) -> u64 {
+		self.notify_unique
+	}
+
+	pub fn node_id(&self) -> NodeId {
+		self.node_id
+	}
+
+	pub fn offset(&self) -> u64 {
+		self.offset
+	}
+
+	/// The retrieved data, which may be shorter than requested if some of
+	/// the range was not in the kernel's page cache.
+	pub fn data(&self) -> &[u8] {
+		self.data
+	}
+}
+
+impl fmt::Debug for RetrieveReply<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("RetrieveReply")
+			.field("node_id", &self.node_id)
+			.field("offset", &self.offset)
+			.field("data", &DebugBytesAsString(self.data))
+			.finish()
+	}
+}
+
+impl<'a> fuse_io::DecodeRequest<'a> for RetrieveReply<'a> {
+	fn decode_request(
+		mut dec: fuse_io::RequestDecoder<'a>,
+	) -> Result<Self, Error> {
+		let header = dec.header();
+		debug_assert!(header.opcode == fuse_kernel::FUSE_NOTIFY_REPLY);
+
+		let raw: &fuse_kernel::fuse_notify_retrieve_in = dec.next_sized()?;
+		let data = dec.next_bytes(raw.size)?;
+		Ok(Self {
+			notify_unique: header.unique,
+			node_id: try_node_id(header.nodeid)?,
+			offset: raw.offset,
+			data,
+		})
+	}
+}
+
+// }}}
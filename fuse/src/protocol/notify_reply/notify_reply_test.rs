@@ -0,0 +1,68 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::internal::testutil::MessageBuilder;
+use crate::protocol::prelude::*;
+
+use super::RetrieveReply;
+
+#[test]
+fn request() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_NOTIFY_REPLY;
+			h.unique = 0xABCD;
+			h.nodeid = 123;
+		})
+		.push_sized(&fuse_kernel::fuse_notify_retrieve_in {
+			dummy1: 0,
+			offset: 45,
+			size: 5,
+			dummy2: 0,
+			dummy3: 0,
+			dummy4: 0,
+		})
+		.push_bytes(b"hello")
+		.build_aligned();
+
+	let reply: RetrieveReply = decode_request!(buf);
+
+	assert_eq!(reply.notify_unique(), 0xABCD);
+	assert_eq!(reply.node_id(), NodeId::new(123).unwrap());
+	assert_eq!(reply.offset(), 45);
+	assert_eq!(reply.data(), b"hello");
+}
+
+#[test]
+fn request_impl_debug() {
+	let reply = RetrieveReply {
+		notify_unique: 0xABCD,
+		node_id: crate::ROOT_ID,
+		offset: 45,
+		data: b"hello",
+	};
+
+	assert_eq!(
+		format!("{:#?}", reply),
+		concat!(
+			"RetrieveReply {\n",
+			"    node_id: 1,\n",
+			"    offset: 45,\n",
+			"    data: \"hello\",\n",
+			"}",
+		),
+	);
+}
@@ -0,0 +1,185 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::protocol::prelude::*;
+
+#[cfg(rust_fuse_test = "poll_test")]
+mod poll_test;
+
+// PollRequest {{{
+
+/// Request type for [`FuseHandlers::poll`] and [`CuseHandlers::poll`].
+///
+/// [`FuseHandlers::poll`]: ../../trait.FuseHandlers.html#method.poll
+/// [`CuseHandlers::poll`]: ../../trait.CuseHandlers.html#method.poll
+pub struct PollRequest<'a> {
+	phantom: PhantomData<&'a ()>,
+	node_id: NodeId,
+	handle: u64,
+	poll_handle: Option<PollHandle>,
+	events: u32,
+}
+
+impl PollRequest<'_> {
+	pub fn node_id(&self) -> NodeId {
+		self.node_id
+	}
+
+	/// The value passed to [`OpenResponse::set_handle`], or zero if not set.
+	///
+	/// [`OpenResponse::set_handle`]: struct.OpenResponse.html#method.set_handle
+	pub fn handle(&self) -> u64 {
+		self.handle
+	}
+
+	/// The kernel's handle for this poll operation, if the kernel has asked
+	/// to be notified of readiness changes.
+	///
+	/// Pass this handle to [`Notifier::poll_wakeup`] when the requested
+	/// events become ready.
+	///
+	/// [`Notifier::poll_wakeup`]: ../../struct.Notifier.html#method.poll_wakeup
+	pub fn poll_handle(&self) -> Option<PollHandle> {
+		self.poll_handle
+	}
+
+	/// The `poll(2)` events requested by the caller, such as `POLLIN` or
+	/// `POLLOUT`. Always zero for protocol versions before v7.21.
+	pub fn events(&self) -> u32 {
+		self.events
+	}
+}
+
+impl fmt::Debug for PollRequest<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("PollRequest")
+			.field("node_id", &self.node_id)
+			.field("handle", &self.handle)
+			.field("poll_handle", &self.poll_handle)
+			.field("events", &DebugHexU32(self.events))
+			.finish()
+	}
+}
+
+impl<'a> fuse_io::DecodeRequest<'a> for PollRequest<'a> {
+	fn decode_request(
+		mut dec: fuse_io::RequestDecoder<'a>,
+	) -> Result<Self, Error> {
+		let header = dec.header();
+		debug_assert!(header.opcode == fuse_kernel::FUSE_POLL);
+
+		let node_id = if dec.is_cuse() {
+			crate::ROOT_ID
+		} else {
+			try_node_id(header.nodeid)?
+		};
+
+		let raw: &'a fuse_kernel::fuse_poll_in = dec.next_sized()?;
+
+		let mut poll_handle = None;
+		if raw.flags & fuse_kernel::FUSE_POLL_SCHEDULE_NOTIFY != 0 {
+			poll_handle = Some(PollHandle(raw.kh));
+		}
+
+		// FUSE v7.21 added `events` to `fuse_poll_in`, which was previously
+		// a padding field.
+		let events = if dec.version().minor() < 21 {
+			0
+		} else {
+			raw.events
+		};
+
+		Ok(Self {
+			phantom: PhantomData,
+			node_id,
+			handle: raw.fh,
+			poll_handle,
+			events,
+		})
+	}
+}
+
+// }}}
+
+// PollHandle {{{
+
+/// A kernel handle identifying a pending poll operation.
+///
+/// See [`PollRequest::poll_handle`] and [`Notifier::poll_wakeup`].
+///
+/// [`PollRequest::poll_handle`]: struct.PollRequest.html#method.poll_handle
+/// [`Notifier::poll_wakeup`]: ../../struct.Notifier.html#method.poll_wakeup
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+pub struct PollHandle(pub(crate) u64);
+
+impl fmt::Debug for PollHandle {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_tuple("PollHandle").field(&self.0).finish()
+	}
+}
+
+// }}}
+
+// PollResponse {{{
+
+/// Response type for [`FuseHandlers::poll`] and [`CuseHandlers::poll`].
+///
+/// [`FuseHandlers::poll`]: ../../trait.FuseHandlers.html#method.poll
+/// [`CuseHandlers::poll`]: ../../trait.CuseHandlers.html#method.poll
+pub struct PollResponse<'a> {
+	phantom: PhantomData<&'a ()>,
+	raw: fuse_kernel::fuse_poll_out,
+}
+
+impl<'a> PollResponse<'a> {
+	pub fn new() -> PollResponse<'a> {
+		Self {
+			phantom: PhantomData,
+			raw: fuse_kernel::fuse_poll_out {
+				revents: 0,
+				padding: 0,
+			},
+		}
+	}
+
+	/// The `poll(2)` events that are currently ready.
+	pub fn revents(&self) -> u32 {
+		self.raw.revents
+	}
+
+	pub fn set_revents(&mut self, revents: u32) {
+		self.raw.revents = revents;
+	}
+}
+
+impl fmt::Debug for PollResponse<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("PollResponse")
+			.field("revents", &DebugHexU32(self.raw.revents))
+			.finish()
+	}
+}
+
+impl fuse_io::EncodeResponse for PollResponse<'_> {
+	fn encode_response<'a, Chan: fuse_io::Channel>(
+		&'a self,
+		enc: fuse_io::ResponseEncoder<Chan>,
+	) -> Result<(), Chan::Error> {
+		enc.encode_sized(&self.raw)
+	}
+}
+
+// }}}
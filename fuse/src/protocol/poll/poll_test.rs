@@ -0,0 +1,147 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::internal::testutil::MessageBuilder;
+use crate::protocol::prelude::*;
+
+use super::{PollHandle, PollRequest, PollResponse};
+
+#[test]
+fn request() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_POLL;
+			h.nodeid = 123;
+		})
+		.push_sized(&fuse_kernel::fuse_poll_in {
+			fh: 12,
+			kh: 34,
+			flags: 0,
+			events: 0x1,
+		})
+		.build_aligned();
+
+	let req: PollRequest = decode_request!(buf);
+
+	assert_eq!(req.node_id(), NodeId::new(123).unwrap());
+	assert_eq!(req.handle(), 12);
+	assert_eq!(req.poll_handle(), None);
+	assert_eq!(req.events(), 0x1);
+}
+
+#[test]
+fn request_schedule_notify() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_POLL;
+			h.nodeid = 123;
+		})
+		.push_sized(&fuse_kernel::fuse_poll_in {
+			fh: 12,
+			kh: 34,
+			flags: fuse_kernel::FUSE_POLL_SCHEDULE_NOTIFY,
+			events: 0x1,
+		})
+		.build_aligned();
+
+	let req: PollRequest = decode_request!(buf);
+
+	assert_eq!(req.poll_handle(), Some(PollHandle(34)));
+}
+
+#[test]
+fn request_v7p20() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_POLL;
+			h.nodeid = 123;
+		})
+		.push_sized(&fuse_kernel::fuse_poll_in {
+			fh: 12,
+			kh: 34,
+			flags: 0,
+			events: 0x1,
+		})
+		.build_aligned();
+
+	let req: PollRequest = decode_request!(buf, {
+		protocol_version: (7, 20),
+	});
+
+	assert_eq!(req.handle(), 12);
+	assert_eq!(req.events(), 0);
+}
+
+#[test]
+fn request_impl_debug() {
+	let request = PollRequest {
+		phantom: PhantomData,
+		node_id: crate::ROOT_ID,
+		handle: 12,
+		poll_handle: Some(PollHandle(34)),
+		events: 0x1,
+	};
+
+	assert_eq!(
+		format!("{:#?}", request),
+		concat!(
+			"PollRequest {\n",
+			"    node_id: 1,\n",
+			"    handle: 12,\n",
+			"    poll_handle: Some(\n",
+			"        PollHandle(\n",
+			"            34,\n",
+			"        ),\n",
+			"    ),\n",
+			"    events: 0x00000001,\n",
+			"}",
+		),
+	);
+}
+
+#[test]
+fn response() {
+	let mut resp = PollResponse::new();
+	resp.set_revents(0x5);
+	let encoded = encode_response!(resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: (size_of::<fuse_kernel::fuse_out_header>()
+					+ size_of::<fuse_kernel::fuse_poll_out>()) as u32,
+				error: 0,
+				unique: 0,
+			})
+			.push_sized(&fuse_kernel::fuse_poll_out {
+				revents: 0x5,
+				padding: 0,
+			})
+			.build()
+	);
+}
+
+#[test]
+fn response_impl_debug() {
+	let mut response = PollResponse::new();
+	response.set_revents(0x5);
+
+	assert_eq!(
+		format!("{:#?}", response),
+		concat!("PollResponse {\n", "    revents: 0x00000005,\n", "}",),
+	);
+}
@@ -143,25 +143,121 @@ impl<'a> fuse_io::DecodeRequest<'a> for ReadRequest<'a> {
 ///
 /// [`FuseHandlers::read`]: ../../trait.FuseHandlers.html#method.read
 pub struct ReadResponse<'a> {
-	bytes: &'a [u8],
+	data: ReadData<'a>,
+}
+
+enum ReadData<'a> {
+	Bytes(&'a [u8]),
+	#[cfg(feature = "std")]
+	Vec(Vec<u8>),
+	#[cfg(feature = "std")]
+	Slices(&'a [io::IoSlice<'a>]),
+	#[cfg(feature = "std")]
+	Fd {
+		phantom: PhantomData<&'a ()>,
+		fd: std::os::unix::io::RawFd,
+		offset: u64,
+		len: u32,
+	},
 }
 
 impl<'a> ReadResponse<'a> {
 	pub fn from_bytes(bytes: &'a [u8]) -> ReadResponse<'a> {
-		Self { bytes }
+		Self {
+			data: ReadData::Bytes(bytes),
+		}
+	}
+
+	/// Responds with the contents of `bytes`, which are owned by the
+	/// response.
+	///
+	/// This is useful when the response must outlive the data it was read
+	/// from, such as when returned from [`AsyncFuseHandlers::read`].
+	///
+	/// [`AsyncFuseHandlers::read`]: ../../trait.AsyncFuseHandlers.html#method.read
+	#[cfg(feature = "std")]
+	#[cfg_attr(doc, doc(cfg(feature = "std")))]
+	pub fn from_vec(bytes: Vec<u8>) -> ReadResponse<'a> {
+		Self {
+			data: ReadData::Vec(bytes),
+		}
 	}
 
-	// TODO; from &[std::io::IoSlice]
+	/// Responds with the contents of `slices`, in order.
+	///
+	/// The slices are sent to the kernel with a single vectored write, so
+	/// they don't need to be copied into a contiguous buffer first.
+	#[cfg(feature = "std")]
+	#[cfg_attr(doc, doc(cfg(feature = "std")))]
+	pub fn from_slices(slices: &'a [io::IoSlice<'a>]) -> ReadResponse<'a> {
+		Self {
+			data: ReadData::Slices(slices),
+		}
+	}
 
-	// TODO: from file descriptor (for splicing)
+	/// Responds with up to `len` bytes read from `fd`, starting at `offset`.
+	/// The response is shorter if the end of the file is reached first.
+	///
+	/// If the channel supports it, the data is spliced from `fd` without
+	/// being copied into userspace. Otherwise it's read with `pread()`. See
+	/// [`Channel::send_from_fd`] for details.
+	///
+	/// If the data can't be read, the kernel receives an error response
+	/// with the `errno` of the failed read.
+	///
+	/// [`Channel::send_from_fd`]: ../../io/trait.Channel.html#method.send_from_fd
+	#[cfg(feature = "std")]
+	#[cfg_attr(doc, doc(cfg(feature = "std")))]
+	pub fn from_fd<Fd: std::os::unix::io::AsRawFd>(
+		fd: &'a Fd,
+		offset: u64,
+		len: u32,
+	) -> ReadResponse<'a> {
+		Self {
+			data: ReadData::Fd {
+				phantom: PhantomData,
+				fd: fd.as_raw_fd(),
+				offset,
+				len,
+			},
+		}
+	}
 }
 
 impl fmt::Debug for ReadResponse<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-		let bytes = DebugBytesAsString(self.bytes);
-		fmt.debug_struct("ReadResponse")
-			.field("bytes", &bytes)
-			.finish()
+		let mut out = fmt.debug_struct("ReadResponse");
+		match &self.data {
+			ReadData::Bytes(bytes) => {
+				out.field("bytes", &DebugBytesAsString(bytes));
+			},
+			#[cfg(feature = "std")]
+			ReadData::Vec(bytes) => {
+				out.field("bytes", &DebugBytesAsString(bytes));
+			},
+			#[cfg(feature = "std")]
+			ReadData::Slices(slices) => {
+				out.field(
+					"slices",
+					&DebugClosure(|fmt| {
+						fmt.debug_list()
+							.entries(
+								slices.iter().map(|s| DebugBytesAsString(s)),
+							)
+							.finish()
+					}),
+				);
+			},
+			#[cfg(feature = "std")]
+			ReadData::Fd {
+				fd, offset, len, ..
+			} => {
+				out.field("fd", fd);
+				out.field("offset", offset);
+				out.field("len", len);
+			},
+		}
+		out.finish()
 	}
 }
 
@@ -170,7 +266,17 @@ impl fuse_io::EncodeResponse for ReadResponse<'_> {
 		&'a self,
 		enc: fuse_io::ResponseEncoder<Chan>,
 	) -> Result<(), Chan::Error> {
-		enc.encode_bytes(self.bytes)
+		match &self.data {
+			ReadData::Bytes(bytes) => enc.encode_bytes(bytes),
+			#[cfg(feature = "std")]
+			ReadData::Vec(bytes) => enc.encode_bytes(bytes),
+			#[cfg(feature = "std")]
+			ReadData::Slices(slices) => enc.encode_slices(slices),
+			#[cfg(feature = "std")]
+			ReadData::Fd {
+				fd, offset, len, ..
+			} => enc.encode_fd(*fd, *offset, *len),
+		}
 	}
 }
 
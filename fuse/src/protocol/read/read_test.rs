@@ -135,8 +135,7 @@ fn response() {
 		encoded,
 		MessageBuilder::new()
 			.push_sized(&fuse_kernel::fuse_out_header {
-				len: (size_of::<fuse_kernel::fuse_out_header>()
-					+ resp.bytes.len()) as u32,
+				len: (size_of::<fuse_kernel::fuse_out_header>() + 3) as u32,
 				error: 0,
 				unique: 0,
 			})
@@ -159,3 +158,137 @@ fn response_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn response_from_vec() {
+	let resp = ReadResponse::from_vec(vec![255, 0, 255]);
+	let encoded = encode_response!(resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: (size_of::<fuse_kernel::fuse_out_header>() + 3) as u32,
+				error: 0,
+				unique: 0,
+			})
+			.push_bytes(&[255, 0, 255])
+			.build()
+	);
+}
+
+#[test]
+fn response_from_slices() {
+	let slices = [
+		std::io::IoSlice::new(&[255, 0]),
+		std::io::IoSlice::new(&[]),
+		std::io::IoSlice::new(&[255]),
+	];
+	let resp = ReadResponse::from_slices(&slices);
+	let encoded = encode_response!(resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: (size_of::<fuse_kernel::fuse_out_header>() + 3) as u32,
+				error: 0,
+				unique: 0,
+			})
+			.push_bytes(&[255, 0, 255])
+			.build()
+	);
+}
+
+#[test]
+fn response_from_slices_impl_debug() {
+	let slices = [
+		std::io::IoSlice::new(&[255, 0]),
+		std::io::IoSlice::new(&[255]),
+	];
+	let response = ReadResponse::from_slices(&slices);
+
+	#[rustfmt::skip]
+	assert_eq!(
+		format!("{:#?}", response),
+		concat!(
+			"ReadResponse {\n",
+			"    slices: [\n",
+			r#"        "\xff\x00","#, "\n",
+			r#"        "\xff","#, "\n",
+			"    ],\n",
+			"}",
+		),
+	);
+}
+
+fn temp_file(name: &str, contents: &[u8]) -> std::fs::File {
+	let path = std::env::temp_dir().join(format!(
+		"rust-fuse-{}-{}",
+		name,
+		std::process::id()
+	));
+	std::fs::write(&path, contents).unwrap();
+	let file = std::fs::File::open(&path).unwrap();
+	std::fs::remove_file(&path).unwrap();
+	file
+}
+
+#[test]
+fn response_from_fd() {
+	let file = temp_file("response_from_fd", b"hello world");
+	let resp = ReadResponse::from_fd(&file, 6, 100);
+	let encoded = encode_response!(resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: (size_of::<fuse_kernel::fuse_out_header>() + 5) as u32,
+				error: 0,
+				unique: 0,
+			})
+			.push_bytes(b"world")
+			.build()
+	);
+}
+
+#[test]
+fn response_from_fd_error() {
+	// Reading from a directory fails with EISDIR.
+	let dir = std::fs::File::open(std::env::temp_dir()).unwrap();
+	let resp = ReadResponse::from_fd(&dir, 0, 100);
+	let encoded = encode_response!(resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: size_of::<fuse_kernel::fuse_out_header>() as u32,
+				error: -21, // EISDIR
+				unique: 0,
+			})
+			.build()
+	);
+}
+
+#[test]
+fn response_from_fd_impl_debug() {
+	use std::os::unix::io::AsRawFd;
+
+	let file = temp_file("response_from_fd_impl_debug", b"");
+	let response = ReadResponse::from_fd(&file, 1, 2);
+	assert_eq!(
+		format!("{:#?}", response),
+		format!(
+			concat!(
+				"ReadResponse {{\n",
+				"    fd: {},\n",
+				"    offset: 1,\n",
+				"    len: 2,\n",
+				"}}",
+			),
+			file.as_raw_fd(),
+		),
+	);
+}
@@ -185,12 +185,8 @@ impl<'a> ReaddirResponse<'a> {
 	#[cfg(feature = "std")]
 	#[cfg_attr(doc, doc(cfg(feature = "std")))]
 	pub fn with_max_size(max_size: u32) -> ReaddirResponse<'a> {
-		let max_size = max_size as usize;
 		Self {
-			buf: ReaddirBuf::Owned {
-				cap: Vec::new(),
-				max_size,
-			},
+			buf: ReaddirBuf::with_max_size(max_size),
 		}
 	}
 
@@ -218,18 +214,8 @@ impl<'a> ReaddirResponse<'a> {
 	/// }
 	/// ```
 	pub fn with_capacity(capacity: &'a mut [u8]) -> ReaddirResponse<'a> {
-		let offset = capacity.as_ptr().align_offset(mem::align_of::<u64>());
-		if offset != 0 {
-			panic!(
-				"ReaddirResponse::with_capacity() requires an 8-byte aligned buffer."
-			);
-		}
 		Self {
-			buf: ReaddirBuf::Borrowed {
-				cap: capacity,
-				size: 0,
-				phantom: PhantomData,
-			},
+			buf: ReaddirBuf::with_capacity("ReaddirResponse", capacity),
 		}
 	}
 
@@ -283,7 +269,7 @@ impl<'a> ReaddirResponse<'a> {
 	}
 }
 
-enum ReaddirBuf<'a, Dirent> {
+pub(crate) enum ReaddirBuf<'a, Dirent> {
 	None,
 	#[cfg(feature = "std")]
 	Owned {
@@ -297,7 +283,7 @@ enum ReaddirBuf<'a, Dirent> {
 	},
 }
 
-trait DirentT {
+pub(crate) trait DirentT {
 	fn namelen(&self) -> u32;
 }
 
@@ -307,8 +293,51 @@ impl DirentT for fuse_kernel::fuse_dirent {
 	}
 }
 
+impl<'a, Dirent> ReaddirBuf<'a, Dirent> {
+	#[cfg(feature = "std")]
+	pub(crate) fn with_max_size(max_size: u32) -> ReaddirBuf<'a, Dirent> {
+		ReaddirBuf::Owned {
+			cap: Vec::new(),
+			max_size: max_size as usize,
+		}
+	}
+
+	pub(crate) fn with_capacity(
+		response_type: &str,
+		capacity: &'a mut [u8],
+	) -> ReaddirBuf<'a, Dirent> {
+		let offset = capacity.as_ptr().align_offset(mem::align_of::<u64>());
+		if offset != 0 {
+			panic!(
+				"{}::with_capacity() requires an 8-byte aligned buffer.",
+				response_type,
+			);
+		}
+		ReaddirBuf::Borrowed {
+			cap: capacity,
+			size: 0,
+			phantom: PhantomData,
+		}
+	}
+
+	pub(crate) fn encode_response<Chan: fuse_io::Channel>(
+		&self,
+		enc: fuse_io::ResponseEncoder<Chan>,
+	) -> Result<(), Chan::Error> {
+		match self {
+			ReaddirBuf::None => enc.encode_header_only(),
+			#[cfg(feature = "std")]
+			ReaddirBuf::Owned { cap, .. } => enc.encode_bytes(cap),
+			ReaddirBuf::Borrowed { cap, size, .. } => {
+				let (bytes, _) = cap.split_at(*size);
+				enc.encode_bytes(bytes)
+			},
+		}
+	}
+}
+
 impl<Dirent: DirentT> ReaddirBuf<'_, Dirent> {
-	fn try_alloc_dirent(
+	pub(crate) fn try_alloc_dirent(
 		&mut self,
 		name: &[u8],
 	) -> Result<*mut u8, ReaddirError> {
@@ -371,7 +400,10 @@ impl<Dirent: DirentT> ReaddirBuf<'_, Dirent> {
 		Ok(entry_buf.as_mut_ptr())
 	}
 
-	fn next_dirent(&self, offset: usize) -> Option<(&Dirent, usize)> {
+	pub(crate) fn next_dirent(
+		&self,
+		offset: usize,
+	) -> Option<(&Dirent, usize)> {
 		let mut buf = match &self {
 			Self::None => &[],
 			#[cfg(feature = "std")]
@@ -439,7 +471,7 @@ impl ReaddirEntry {
 	}
 }
 
-fn dirent_type(dirent: &fuse_kernel::fuse_dirent) -> FileType {
+pub(crate) fn dirent_type(dirent: &fuse_kernel::fuse_dirent) -> FileType {
 	match FileType::from_bits(dirent.r#type) {
 		Some(t) => t,
 		None => unsafe {
@@ -448,7 +480,7 @@ fn dirent_type(dirent: &fuse_kernel::fuse_dirent) -> FileType {
 	}
 }
 
-fn dirent_name(dirent: &fuse_kernel::fuse_dirent) -> &[u8] {
+pub(crate) fn dirent_name(dirent: &fuse_kernel::fuse_dirent) -> &[u8] {
 	unsafe {
 		core::slice::from_raw_parts(
 			&dirent.name as *const u8,
@@ -518,15 +550,7 @@ impl fuse_io::EncodeResponse for ReaddirResponse<'_> {
 		&'a self,
 		enc: fuse_io::ResponseEncoder<Chan>,
 	) -> Result<(), Chan::Error> {
-		match &self.buf {
-			ReaddirBuf::None => enc.encode_header_only(),
-			#[cfg(feature = "std")]
-			ReaddirBuf::Owned { cap, .. } => enc.encode_bytes(&cap),
-			ReaddirBuf::Borrowed { cap, size, .. } => {
-				let (bytes, _) = cap.split_at(*size);
-				enc.encode_bytes(bytes)
-			},
-		}
+		self.buf.encode_response(enc)
 	}
 }
 
@@ -0,0 +1,355 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::num;
+
+use crate::protocol::prelude::*;
+use crate::protocol::readdir::{
+	dirent_name,
+	dirent_type,
+	DirentT,
+	ReaddirBuf,
+	ReaddirError,
+};
+
+#[cfg(rust_fuse_test = "readdirplus_test")]
+mod readdirplus_test;
+
+// ReaddirplusRequest {{{
+
+/// Request type for [`FuseHandlers::readdirplus`].
+///
+/// [`FuseHandlers::readdirplus`]: ../../trait.FuseHandlers.html#method.readdirplus
+pub struct ReaddirplusRequest<'a> {
+	phantom: PhantomData<&'a ()>,
+	node_id: NodeId,
+	size: u32,
+	cursor: Option<num::NonZeroU64>,
+	handle: u64,
+	opendir_flags: u32,
+}
+
+impl ReaddirplusRequest<'_> {
+	pub fn node_id(&self) -> NodeId {
+		self.node_id
+	}
+
+	pub fn size(&self) -> u32 {
+		self.size
+	}
+
+	pub fn cursor(&self) -> Option<num::NonZeroU64> {
+		self.cursor
+	}
+
+	/// The value passed to [`OpendirResponse::set_handle`], or zero if not set.
+	///
+	/// [`OpendirResponse::set_handle`]: struct.OpendirResponse.html#method.set_handle
+	pub fn handle(&self) -> u64 {
+		self.handle
+	}
+
+	/// Platform-specific flags passed to [`FuseHandlers::opendir`]. See
+	/// [`OpendirRequest::flags`] for details.
+	///
+	/// [`FuseHandlers::opendir`]: ../../trait.FuseHandlers.html#method.opendir
+	/// [`OpendirRequest::flags`]: struct.OpendirRequest.html#method.flags
+	pub fn opendir_flags(&self) -> u32 {
+		self.opendir_flags
+	}
+}
+
+impl fmt::Debug for ReaddirplusRequest<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("ReaddirplusRequest")
+			.field("node_id", &self.node_id)
+			.field("size", &self.size)
+			.field("cursor", &format_args!("{:?}", self.cursor))
+			.field("handle", &self.handle)
+			.field("opendir_flags", &DebugHexU32(self.opendir_flags))
+			.finish()
+	}
+}
+
+impl<'a> fuse_io::DecodeRequest<'a> for ReaddirplusRequest<'a> {
+	fn decode_request(
+		mut dec: fuse_io::RequestDecoder<'a>,
+	) -> Result<Self, Error> {
+		let header = dec.header();
+		debug_assert!(header.opcode == fuse_kernel::FUSE_READDIRPLUS);
+
+		let raw: &'a fuse_kernel::fuse_read_in = dec.next_sized()?;
+		Ok(Self {
+			phantom: PhantomData,
+			node_id: try_node_id(header.nodeid)?,
+			size: raw.size,
+			cursor: num::NonZeroU64::new(raw.offset),
+			handle: raw.fh,
+			opendir_flags: raw.flags,
+		})
+	}
+}
+
+// }}}
+
+// ReaddirplusResponse {{{
+
+/// Response type for [`FuseHandlers::readdirplus`].
+///
+/// [`FuseHandlers::readdirplus`]: ../../trait.FuseHandlers.html#method.readdirplus
+pub struct ReaddirplusResponse<'a> {
+	buf: ReaddirBuf<'a, fuse_kernel::fuse_direntplus>,
+}
+
+impl ReaddirplusResponse<'_> {
+	/// An empty `ReaddirplusResponse` that cannot have entries added to it.
+	///
+	/// This is useful for returning end-of-stream responses.
+	pub const EMPTY: &'static ReaddirplusResponse<'static> =
+		&ReaddirplusResponse {
+			buf: ReaddirBuf::None,
+		};
+}
+
+impl<'a> ReaddirplusResponse<'a> {
+	/// Constructs a new, empty `ReaddirplusResponse` that will grow up to the
+	/// given maximum size.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// fn readdirplus(
+	///     request: &fuse::ReaddirplusRequest,
+	///     respond: impl for<'a> fuse::Respond<fuse::ReaddirplusResponse<'a>>,
+	/// ) {
+	///     let mut response =
+	///         fuse::ReaddirplusResponse::with_max_size(request.size());
+	///     /* fill in response */
+	///     respond.ok(&response);
+	/// }
+	/// ```
+	#[cfg(feature = "std")]
+	#[cfg_attr(doc, doc(cfg(feature = "std")))]
+	pub fn with_max_size(max_size: u32) -> ReaddirplusResponse<'a> {
+		Self {
+			buf: ReaddirBuf::with_max_size(max_size),
+		}
+	}
+
+	/// Constructs a new, empty `ReaddirplusResponse` that will use the given
+	/// buffer as capacity. The caller is responsible for allocating a buffer
+	/// of the appropriate size and alignment.
+	///
+	/// # Panics
+	///
+	/// Panics if `buf` is not sufficiently aligned. The minimum alignment is
+	/// `align_of::<u64>()`.
+	pub fn with_capacity(capacity: &'a mut [u8]) -> ReaddirplusResponse<'a> {
+		Self {
+			buf: ReaddirBuf::with_capacity("ReaddirplusResponse", capacity),
+		}
+	}
+
+	pub fn entries(&self) -> impl Iterator<Item = &ReaddirplusEntry> {
+		ReaddirplusEntriesIter::new(&self.buf)
+	}
+
+	/// Adds an entry for the given node. The entry's [`Node`] has its ID set
+	/// to `node_id`, and should have its attributes and cache timeouts filled
+	/// in as for a [`LookupResponse`].
+	///
+	/// [`Node`]: ../../struct.Node.html
+	/// [`LookupResponse`]: struct.LookupResponse.html
+	pub fn add_entry(
+		&mut self,
+		node_id: NodeId,
+		name: &NodeName,
+		cursor: num::NonZeroU64,
+	) -> &mut ReaddirplusEntry {
+		self.try_add_entry(node_id, name, cursor).unwrap()
+	}
+
+	pub fn try_add_entry(
+		&mut self,
+		node_id: NodeId,
+		name: &NodeName,
+		cursor: num::NonZeroU64,
+	) -> Result<&mut ReaddirplusEntry, ReaddirError> {
+		let name = name.as_bytes();
+		let dirent_buf = self.buf.try_alloc_dirent(name)?;
+
+		// From here on `try_add_entry()` must not fail, or the response buffer
+		// would contain uninitialized bytes.
+
+		unsafe {
+			let dirent_ptr = dirent_buf as *mut fuse_kernel::fuse_direntplus;
+			let name_ptr =
+				dirent_buf.add(size_of::<fuse_kernel::fuse_direntplus>());
+			let padding_ptr = name_ptr.add(name.len());
+
+			dirent_ptr.write(fuse_kernel::fuse_direntplus {
+				entry_out: fuse_kernel::fuse_entry_out {
+					nodeid: node_id.get(),
+					..Default::default()
+				},
+				dirent: fuse_kernel::fuse_dirent {
+					ino: node_id.get(),
+					off: cursor.get(),
+					namelen: name.len() as u32,
+					r#type: FileType::Unknown.as_bits(),
+					name: [],
+				},
+			});
+
+			ptr::copy_nonoverlapping(name.as_ptr(), name_ptr, name.len());
+			let padding_len = (8 - (name.len() % 8)) % 8;
+			if padding_len > 0 {
+				ptr::write_bytes(padding_ptr, 0, padding_len);
+			}
+
+			Ok(ReaddirplusEntry::new_ref_mut(&mut *dirent_ptr))
+		}
+	}
+}
+
+impl DirentT for fuse_kernel::fuse_direntplus {
+	fn namelen(&self) -> u32 {
+		self.dirent.namelen
+	}
+}
+
+#[repr(transparent)]
+pub struct ReaddirplusEntry(fuse_kernel::fuse_direntplus);
+
+impl ReaddirplusEntry {
+	pub(crate) fn new_ref(
+		raw: &fuse_kernel::fuse_direntplus,
+	) -> &ReaddirplusEntry {
+		unsafe {
+			&*(raw as *const fuse_kernel::fuse_direntplus
+				as *const ReaddirplusEntry)
+		}
+	}
+
+	pub(crate) fn new_ref_mut(
+		raw: &mut fuse_kernel::fuse_direntplus,
+	) -> &mut ReaddirplusEntry {
+		unsafe {
+			&mut *(raw as *mut fuse_kernel::fuse_direntplus
+				as *mut ReaddirplusEntry)
+		}
+	}
+
+	/// The inode number of the directory entry, as passed to
+	/// [`add_entry`](struct.ReaddirplusResponse.html#method.add_entry).
+	///
+	/// This is not changed by setting the ID of the entry's [`Node`], so the
+	/// two may differ. The kernel reports this value as the entry's `d_ino`,
+	/// and uses the node's ID to look up the entry.
+	///
+	/// [`Node`]: ../../struct.Node.html
+	pub fn node_id(&self) -> NodeId {
+		unsafe { NodeId::new_unchecked(self.0.dirent.ino) }
+	}
+
+	pub fn name(&self) -> &[u8] {
+		dirent_name(&self.0.dirent)
+	}
+
+	pub fn cursor(&self) -> num::NonZeroU64 {
+		unsafe { num::NonZeroU64::new_unchecked(self.0.dirent.off) }
+	}
+
+	pub fn file_type(&self) -> FileType {
+		dirent_type(&self.0.dirent)
+	}
+
+	pub fn set_file_type(&mut self, file_type: FileType) {
+		self.0.dirent.r#type = file_type.as_bits();
+	}
+
+	pub fn node(&self) -> &Node {
+		Node::new_ref(&self.0.entry_out)
+	}
+
+	/// The entry's node. Its ID is initially the entry's
+	/// [`node_id`](#method.node_id), and changing it doesn't update the
+	/// entry's inode number.
+	pub fn node_mut(&mut self) -> &mut Node {
+		Node::new_ref_mut(&mut self.0.entry_out)
+	}
+}
+
+impl fmt::Debug for ReaddirplusEntry {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("ReaddirplusEntry")
+			.field("node_id", &self.0.dirent.ino)
+			.field("cursor", &self.0.dirent.off)
+			.field("file_type", &dirent_type(&self.0.dirent))
+			.field("name", &DebugBytesAsString(dirent_name(&self.0.dirent)))
+			.field("node", self.node())
+			.finish()
+	}
+}
+
+struct ReaddirplusEntriesIter<'a> {
+	buf: &'a ReaddirBuf<'a, fuse_kernel::fuse_direntplus>,
+	offset: usize,
+}
+
+impl<'a> ReaddirplusEntriesIter<'a> {
+	fn new(buf: &'a ReaddirBuf<'a, fuse_kernel::fuse_direntplus>) -> Self {
+		Self { buf, offset: 0 }
+	}
+}
+
+impl<'a> core::iter::Iterator for ReaddirplusEntriesIter<'a> {
+	type Item = &'a ReaddirplusEntry;
+
+	fn next(&mut self) -> Option<&'a ReaddirplusEntry> {
+		match self.buf.next_dirent(self.offset) {
+			None => None,
+			Some((dirent, new_offset)) => {
+				self.offset = new_offset;
+				Some(ReaddirplusEntry::new_ref(dirent))
+			},
+		}
+	}
+}
+
+impl fmt::Debug for ReaddirplusResponse<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		let entries = DebugClosure(|fmt| {
+			fmt.debug_list()
+				.entries(ReaddirplusEntriesIter::new(&self.buf))
+				.finish()
+		});
+		fmt.debug_struct("ReaddirplusResponse")
+			.field("entries", &entries)
+			.finish()
+	}
+}
+
+impl fuse_io::EncodeResponse for ReaddirplusResponse<'_> {
+	fn encode_response<'a, Chan: fuse_io::Channel>(
+		&'a self,
+		enc: fuse_io::ResponseEncoder<Chan>,
+	) -> Result<(), Chan::Error> {
+		self.buf.encode_response(enc)
+	}
+}
+
+// }}}
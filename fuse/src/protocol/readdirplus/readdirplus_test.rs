@@ -0,0 +1,206 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::num;
+
+use crate::internal::testutil::MessageBuilder;
+use crate::protocol::prelude::*;
+
+use super::{ReaddirplusRequest, ReaddirplusResponse};
+
+#[test]
+fn request() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_READDIRPLUS;
+			h.nodeid = 123;
+		})
+		.push_sized(&fuse_kernel::fuse_read_in {
+			fh: 123,
+			offset: 45,
+			size: 4096,
+			read_flags: 0,
+			lock_owner: 0,
+			flags: 67,
+			padding: 0,
+		})
+		.build_aligned();
+
+	let req: ReaddirplusRequest = decode_request!(buf);
+
+	assert_eq!(req.node_id(), NodeId::new(123).unwrap());
+	assert_eq!(req.handle(), 123);
+	assert_eq!(req.cursor(), num::NonZeroU64::new(45));
+	assert_eq!(req.opendir_flags(), 67);
+	assert_eq!(req.size(), 4096);
+}
+
+#[test]
+fn request_impl_debug() {
+	let request = &ReaddirplusRequest {
+		phantom: PhantomData,
+		node_id: crate::ROOT_ID,
+		size: 1,
+		cursor: num::NonZeroU64::new(2),
+		handle: 3,
+		opendir_flags: 0x4,
+	};
+
+	assert_eq!(
+		format!("{:#?}", request),
+		concat!(
+			"ReaddirplusRequest {\n",
+			"    node_id: 1,\n",
+			"    size: 1,\n",
+			"    cursor: Some(2),\n",
+			"    handle: 3,\n",
+			"    opendir_flags: 0x00000004,\n",
+			"}",
+		),
+	);
+}
+
+#[test]
+fn readdirplus_response_heap() {
+	let max_size = size_of::<fuse_kernel::fuse_direntplus>() + 12;
+	let mut resp = ReaddirplusResponse::with_max_size(max_size as u32);
+	readdirplus_response_test_impl(&mut resp);
+}
+
+#[test]
+fn readdirplus_response_stack() {
+	let mut buf = [0u8; 1024];
+	let aligned = match buf.as_ptr().align_offset(mem::align_of::<u64>()) {
+		0 => &mut buf,
+		offset => {
+			let (_, aligned) = buf.split_at_mut(offset);
+			aligned
+		},
+	};
+
+	let max_size = size_of::<fuse_kernel::fuse_direntplus>() + 12;
+	let (sized_buf, _) = aligned.split_at_mut(max_size);
+	let mut resp = ReaddirplusResponse::with_capacity(sized_buf);
+	readdirplus_response_test_impl(&mut resp);
+}
+
+fn readdirplus_response_test_impl(resp: &mut ReaddirplusResponse) {
+	// Adding a dirent fails if there's not enough capacity.
+	{
+		let node_id = NodeId::new(100).unwrap();
+		let name = NodeName::from_bytes(b"123456789ABCDEF").unwrap();
+		let cursor = num::NonZeroU64::new(1).unwrap();
+		let opt_dirent = resp.try_add_entry(node_id, name, cursor);
+		assert!(opt_dirent.is_err());
+	}
+
+	// Dirent capacity takes 8-byte name padding into account.
+	{
+		let node_id = NodeId::new(100).unwrap();
+		let name = NodeName::from_bytes(b"123456789").unwrap();
+		let cursor = num::NonZeroU64::new(1).unwrap();
+		let opt_dirent = resp.try_add_entry(node_id, name, cursor);
+		assert!(opt_dirent.is_err());
+	}
+
+	// Adding a dirent works if there's enough capacity.
+	{
+		let node_id = NodeId::new(100).unwrap();
+		let name = NodeName::from_bytes(b"foobar").unwrap();
+		let cursor = num::NonZeroU64::new(1).unwrap();
+		let dirent = resp.try_add_entry(node_id, name, cursor).unwrap();
+
+		assert_eq!(dirent.cursor(), cursor);
+		assert_eq!(dirent.file_type(), FileType::Unknown);
+		assert_eq!(dirent.node().id(), Some(node_id));
+
+		dirent.set_file_type(FileType::Regular);
+		let node = dirent.node_mut();
+		node.set_generation(2);
+		node.set_cache_timeout(Duration::new(3, 4));
+		node.set_attr_cache_timeout(Duration::new(5, 6));
+		node.attr_mut().set_size(999);
+	}
+
+	assert_eq!(resp.entries().count(), 1);
+
+	let encoded = encode_response!(resp);
+
+	let mut entry_out = fuse_kernel::fuse_entry_out {
+		nodeid: 100,
+		generation: 2,
+		entry_valid: 3,
+		entry_valid_nsec: 4,
+		attr_valid: 5,
+		attr_valid_nsec: 6,
+		..Default::default()
+	};
+	entry_out.attr.size = 999;
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: (size_of::<fuse_kernel::fuse_out_header>()
+					+ size_of::<fuse_kernel::fuse_direntplus>()
+					+ 8) as u32,
+				error: 0,
+				unique: 0,
+			})
+			.push_sized(&fuse_kernel::fuse_direntplus {
+				entry_out,
+				dirent: fuse_kernel::fuse_dirent {
+					ino: 100,
+					off: 1,
+					namelen: 6,
+					r#type: 8,
+					name: [0u8; 0],
+				},
+			})
+			.push_bytes(b"foobar\0\0")
+			.build()
+	);
+}
+
+#[test]
+fn response_node_id() {
+	// Setting the node's ID doesn't change the entry's inode number.
+	let mut resp = ReaddirplusResponse::with_max_size(1024);
+	let name = NodeName::from_bytes(b"foobar").unwrap();
+	let cursor = num::NonZeroU64::new(1).unwrap();
+	let dirent = resp.add_entry(NodeId::new(100).unwrap(), name, cursor);
+	dirent.node_mut().set_id(NodeId::new(200).unwrap());
+
+	assert_eq!(dirent.node_id(), NodeId::new(100).unwrap());
+	assert_eq!(dirent.node().id(), Some(NodeId::new(200).unwrap()));
+}
+
+#[test]
+fn response_empty() {
+	let resp = ReaddirplusResponse::EMPTY;
+	let encoded = encode_response!(resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: size_of::<fuse_kernel::fuse_out_header>() as u32,
+				error: 0,
+				unique: 0,
+			})
+			.build()
+	);
+}
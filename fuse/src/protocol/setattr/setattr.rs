@@ -21,18 +21,23 @@ mod setattr_test;
 
 // SetattrRequest {{{
 
+/// Request type for [`FuseHandlers::setattr`].
+///
+/// [`FuseHandlers::setattr`]: ../../trait.FuseHandlers.html#method.setattr
 pub struct SetattrRequest<'a> {
-	header: &'a fuse_kernel::fuse_in_header,
+	phantom: PhantomData<&'a ()>,
+	node_id: NodeId,
+	valid: u32,
 	raw: &'a fuse_kernel::fuse_setattr_in,
 }
 
 impl SetattrRequest<'_> {
-	pub fn node_id(&self) -> u64 {
-		self.header.nodeid
+	pub fn node_id(&self) -> NodeId {
+		self.node_id
 	}
 
 	fn get<T>(&self, bitmask: u32, value: T) -> Option<T> {
-		if self.raw.valid & bitmask == 0 {
+		if self.valid & bitmask == 0 {
 			return None;
 		}
 		Some(value)
@@ -43,13 +48,17 @@ impl SetattrRequest<'_> {
 		bitmask: u32,
 		seconds: u64,
 		nanos: u32,
-	) -> Option<time::SystemTime> {
-		if self.raw.valid & bitmask == 0 {
+	) -> Option<Duration> {
+		if self.valid & bitmask == 0 {
 			return None;
 		}
-		Some(systime(seconds, nanos))
+		Some(Duration::new(seconds, nanos))
 	}
 
+	/// The value passed to [`OpenResponse::set_handle`], if the attributes
+	/// are being set through an open file.
+	///
+	/// [`OpenResponse::set_handle`]: struct.OpenResponse.html#method.set_handle
 	pub fn handle(&self) -> Option<u64> {
 		self.get(fuse_kernel::FATTR_FH, self.raw.fh)
 	}
@@ -62,7 +71,8 @@ impl SetattrRequest<'_> {
 		self.get(fuse_kernel::FATTR_LOCKOWNER, self.raw.lock_owner)
 	}
 
-	pub fn atime(&self) -> Option<time::SystemTime> {
+	/// The new access time, as a duration since the Unix epoch.
+	pub fn atime(&self) -> Option<Duration> {
 		self.get_timestamp(
 			fuse_kernel::FATTR_ATIME,
 			self.raw.atime,
@@ -70,11 +80,13 @@ impl SetattrRequest<'_> {
 		)
 	}
 
+	/// Whether the access time should be set to the current time. If set,
+	/// [`atime`](#method.atime) should be ignored.
 	pub fn atime_now(&self) -> bool {
-		self.raw.valid & fuse_kernel::FATTR_ATIME_NOW > 0
+		self.valid & fuse_kernel::FATTR_ATIME_NOW > 0
 	}
 
-	pub fn mtime(&self) -> Option<time::SystemTime> {
+	pub fn mtime(&self) -> Option<Duration> {
 		self.get_timestamp(
 			fuse_kernel::FATTR_MTIME,
 			self.raw.mtime,
@@ -82,11 +94,13 @@ impl SetattrRequest<'_> {
 		)
 	}
 
+	/// Whether the modification time should be set to the current time. If
+	/// set, [`mtime`](#method.mtime) should be ignored.
 	pub fn mtime_now(&self) -> bool {
-		self.raw.valid & fuse_kernel::FATTR_MTIME_NOW > 0
+		self.valid & fuse_kernel::FATTR_MTIME_NOW > 0
 	}
 
-	pub fn ctime(&self) -> Option<time::SystemTime> {
+	pub fn ctime(&self) -> Option<Duration> {
 		self.get_timestamp(
 			fuse_kernel::FATTR_CTIME,
 			self.raw.ctime,
@@ -107,70 +121,103 @@ impl SetattrRequest<'_> {
 	}
 }
 
+impl fmt::Debug for SetattrRequest<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("SetattrRequest")
+			.field("node_id", &self.node_id)
+			.field("handle", &format_args!("{:?}", self.handle()))
+			.field("size", &format_args!("{:?}", self.size()))
+			.field("lock_owner", &format_args!("{:?}", self.lock_owner()))
+			.field("atime", &format_args!("{:?}", self.atime()))
+			.field("atime_now", &self.atime_now())
+			.field("mtime", &format_args!("{:?}", self.mtime()))
+			.field("mtime_now", &self.mtime_now())
+			.field("ctime", &format_args!("{:?}", self.ctime()))
+			.field("mode", &format_args!("{:?}", self.mode()))
+			.field("user_id", &format_args!("{:?}", self.user_id()))
+			.field("group_id", &format_args!("{:?}", self.group_id()))
+			.finish()
+	}
+}
+
 impl<'a> fuse_io::DecodeRequest<'a> for SetattrRequest<'a> {
 	fn decode_request(
 		mut dec: fuse_io::RequestDecoder<'a>,
 	) -> Result<Self, Error> {
 		let header = dec.header();
 		debug_assert!(header.opcode == fuse_kernel::FUSE_SETATTR);
-		let raw = dec.next_sized()?;
-		Ok(Self { header, raw })
-	}
-}
 
-fn systime(seconds: u64, nanos: u32) -> time::SystemTime {
-	time::UNIX_EPOCH + time::Duration::new(seconds, nanos)
+		let node_id = try_node_id(header.nodeid)?;
+
+		// The layout of `fuse_setattr_in` has not changed since v7.1, but
+		// fields that were added later occupy previously unused space.
+		// Mask off any validity bits that the negotiated version does not
+		// define, so that stale data isn't exposed as a value.
+		let raw: &'a fuse_kernel::fuse_setattr_in = dec.next_sized()?;
+		let mut valid = raw.valid;
+		let minor = dec.version().minor();
+		if minor < 9 {
+			valid &= !(fuse_kernel::FATTR_FH
+				| fuse_kernel::FATTR_ATIME_NOW
+				| fuse_kernel::FATTR_MTIME_NOW
+				| fuse_kernel::FATTR_LOCKOWNER);
+		}
+		if minor < 23 {
+			valid &= !fuse_kernel::FATTR_CTIME;
+		}
+
+		Ok(Self {
+			phantom: PhantomData,
+			node_id,
+			valid,
+			raw,
+		})
+	}
 }
 
 // }}}
 
 // SetattrResponse {{{
 
+/// Response type for [`FuseHandlers::setattr`].
+///
+/// [`FuseHandlers::setattr`]: ../../trait.FuseHandlers.html#method.setattr
 pub struct SetattrResponse<'a> {
 	phantom: PhantomData<&'a ()>,
 	raw: fuse_kernel::fuse_attr_out,
 }
 
 impl<'a> SetattrResponse<'a> {
-	// TODO: fix API
-	pub fn new(request: &SetattrRequest) -> SetattrResponse<'a> {
+	pub fn new() -> SetattrResponse<'a> {
 		Self {
 			phantom: PhantomData,
-			raw: fuse_kernel::fuse_attr_out {
-				attr_valid: 0,
-				attr_valid_nsec: 0,
-				dummy: 0,
-				attr: fuse_kernel::fuse_attr {
-					ino: request.header.nodeid,
-					..Default::default()
-				},
-			},
+			raw: Default::default(),
 		}
 	}
 
-	pub fn attr(&self) -> &NodeAttr {
-		NodeAttr::new_ref(&self.raw.attr)
+	pub fn attr_timeout(&self) -> Duration {
+		Duration::new(self.raw.attr_valid, self.raw.attr_valid_nsec)
 	}
 
-	pub fn attr_mut(&mut self) -> &mut NodeAttr {
-		NodeAttr::new_ref_mut(&mut self.raw.attr)
+	pub fn set_attr_timeout(&mut self, attr_timeout: Duration) {
+		self.raw.attr_valid = attr_timeout.as_secs();
+		self.raw.attr_valid_nsec = attr_timeout.subsec_nanos();
 	}
 
-	pub fn cache_duration(&self) -> time::Duration {
-		time::Duration::new(self.raw.attr_valid, self.raw.attr_valid_nsec)
+	pub fn attr(&self) -> &NodeAttr {
+		NodeAttr::new_ref(&self.raw.attr)
 	}
 
-	pub fn set_cache_duration(&mut self, cache_duration: time::Duration) {
-		self.raw.attr_valid = cache_duration.as_secs();
-		self.raw.attr_valid_nsec = cache_duration.subsec_nanos();
+	pub fn attr_mut(&mut self) -> &mut NodeAttr {
+		NodeAttr::new_ref_mut(&mut self.raw.attr)
 	}
 }
 
 impl fmt::Debug for SetattrResponse<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("SetattrResponse")
+			.field("attr_timeout", &self.attr_timeout())
 			.field("attr", self.attr())
-			.field("cache_duration", &self.cache_duration())
 			.finish()
 	}
 }
@@ -30,6 +30,9 @@ pub struct WriteRequest<'a> {
 	offset: u64,
 	handle: u64,
 	value: &'a [u8],
+	size: u32,
+	#[cfg(feature = "std")]
+	value_fd: Option<std::os::unix::io::RawFd>,
 	flags: WriteRequestFlags,
 	lock_owner: Option<u64>,
 	open_flags: u32,
@@ -51,10 +54,35 @@ impl WriteRequest<'_> {
 		self.handle
 	}
 
+	/// The data to write. Empty if the data was spliced into a pipe, in
+	/// which case it must be read from [`value_fd`](#method.value_fd).
 	pub fn value(&self) -> &[u8] {
 		self.value
 	}
 
+	/// The size of the data to write, in bytes.
+	pub fn size(&self) -> u32 {
+		self.size
+	}
+
+	/// A pipe holding the data to write, if the request was received with
+	/// [`FuseServerBuilder::set_splice_writes`] enabled.
+	///
+	/// The pipe contains exactly [`size`](#method.size) bytes, which can be
+	/// spliced into a file or socket without being copied into userspace.
+	/// The data must be consumed before the handler returns. Any data left
+	/// in the pipe is discarded before the next request is received.
+	///
+	/// Small writes are not spliced, and are available from
+	/// [`value`](#method.value).
+	///
+	/// [`FuseServerBuilder::set_splice_writes`]: ../../struct.FuseServerBuilder.html#method.set_splice_writes
+	#[cfg(feature = "std")]
+	#[cfg_attr(doc, doc(cfg(feature = "std")))]
+	pub fn value_fd(&self) -> Option<std::os::unix::io::RawFd> {
+		self.value_fd
+	}
+
 	pub fn flags(&self) -> &WriteRequestFlags {
 		&self.flags
 	}
@@ -89,6 +117,7 @@ impl fmt::Debug for WriteRequest<'_> {
 			.field("offset", &self.offset)
 			.field("handle", &self.handle)
 			.field("value", &self.value)
+			.field("size", &self.size)
 			.field("flags", &self.flags)
 			.field("lock_owner", &self.lock_owner())
 			.field("open_flags", &DebugHexU32(self.open_flags))
@@ -126,6 +155,9 @@ impl<'a> fuse_io::DecodeRequest<'a> for WriteRequest<'a> {
 				offset: raw.offset,
 				handle: raw.fh,
 				value,
+				size: raw.size,
+				#[cfg(feature = "std")]
+				value_fd: None,
 				flags: WriteRequestFlags::from_bits(raw.write_flags),
 				lock_owner: None,
 				open_flags: 0,
@@ -133,6 +165,20 @@ impl<'a> fuse_io::DecodeRequest<'a> for WriteRequest<'a> {
 		}
 
 		let raw: &'a fuse_kernel::fuse_write_in = dec.next_sized()?;
+
+		#[cfg(feature = "std")]
+		let value_fd = dec.spliced_payload();
+		#[cfg(feature = "std")]
+		let value = match value_fd {
+			Some(_) => {
+				if dec.header().len - dec.consumed() != raw.size {
+					return Err(Error::unexpected_eof());
+				}
+				&[]
+			},
+			None => dec.next_bytes(raw.size)?,
+		};
+		#[cfg(not(feature = "std"))]
 		let value = dec.next_bytes(raw.size)?;
 
 		let mut lock_owner = None;
@@ -146,6 +192,9 @@ impl<'a> fuse_io::DecodeRequest<'a> for WriteRequest<'a> {
 			offset: raw.offset,
 			handle: raw.fh,
 			value,
+			size: raw.size,
+			#[cfg(feature = "std")]
+			value_fd,
 			flags: WriteRequestFlags::from_bits(raw.write_flags),
 			lock_owner,
 			open_flags: raw.flags,
@@ -83,6 +83,47 @@ fn request_v7p9() {
 	assert_eq!(req.flags().write_cache, false);
 	assert_eq!(req.open_flags(), 67);
 	assert_eq!(req.value(), b"hello.world!");
+	assert_eq!(req.size(), 12);
+	assert_eq!(req.value_fd(), None);
+}
+
+#[test]
+fn request_spliced() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_WRITE;
+			h.nodeid = 123;
+		})
+		.push_sized(&fuse_kernel::fuse_write_in {
+			fh: 123,
+			offset: 45,
+			size: 12,
+			write_flags: 0,
+			lock_owner: 0,
+			flags: 67,
+			padding: 0,
+		})
+		.push_bytes(b"hello.world!")
+		.build_aligned();
+
+	// Only the headers are in the buffer. The payload is in a pipe.
+	let headers_len = size_of::<fuse_kernel::fuse_in_header>()
+		+ size_of::<fuse_kernel::fuse_write_in>();
+	let decoder = fuse_io::RequestDecoder::new_spliced(
+		fuse_io::aligned_slice(&buf, headers_len),
+		crate::io::ProtocolVersion::LATEST,
+		fuse_io::Semantics::FUSE,
+		10,
+	)
+	.unwrap();
+	let req: WriteRequest = DecodeRequest::decode_request(decoder).unwrap();
+
+	assert_eq!(req.handle(), 123);
+	assert_eq!(req.offset(), 45);
+	assert_eq!(req.open_flags(), 67);
+	assert_eq!(req.value(), b"");
+	assert_eq!(req.size(), 12);
+	assert_eq!(req.value_fd(), Some(10));
 }
 
 #[test]
@@ -0,0 +1,764 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Recording and replaying FUSE traffic.
+//!
+//! A [`RecordingChannel`] wraps another channel and writes every message that
+//! passes through it to a recording. The recording can later be loaded with
+//! [`Recording::read_from`] and fed back into a server by a
+//! [`ReplayChannel`], which compares the server's new responses against the
+//! recorded ones.
+//!
+//! # Format
+//!
+//! A recording starts with a 24-byte header:
+//!
+//! * The magic bytes `FUSEREC\0`.
+//! * The format version (currently 1) as a little-endian `u32`, followed by
+//!   four reserved bytes.
+//! * The time the recording started, in nanoseconds since the Unix epoch, as
+//!   a little-endian `u64`.
+//!
+//! Each message is stored as a 16-byte entry header followed by the message
+//! itself, exactly as it was received or sent:
+//!
+//! * The entry kind as a little-endian `u32`: 1 for a request, 2 for a
+//!   response, or 3 for a notification.
+//! * The length of the message as a little-endian `u32`.
+//! * The time the message was received or sent, in nanoseconds since the
+//!   recording started, as a little-endian `u64`.
+//!
+//! [`RecordingChannel`]: struct.RecordingChannel.html
+//! [`Recording::read_from`]: struct.Recording.html#method.read_from
+//! [`ReplayChannel`]: struct.ReplayChannel.html
+
+use core::convert::TryInto;
+use core::mem::size_of;
+use core::{cmp, fmt, ptr, time};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::channel::Channel;
+use crate::cuse_server::CuseServerChannel;
+use crate::error::ErrorCode;
+use crate::fuse_server::FuseServerChannel;
+use crate::internal::fuse_kernel;
+use crate::protocol::common::{RequestHeader, ResponseHeader};
+use crate::server::ServerChannel;
+
+#[cfg(rust_fuse_test = "record_test")]
+#[path = "record_test.rs"]
+mod record_test;
+
+const MAGIC: [u8; 8] = *b"FUSEREC\0";
+const FORMAT_VERSION: u32 = 1;
+
+// No FUSE message is larger than `max_pages` (at most 256 pages, of up to
+// 64 KiB each) plus headers. Larger entries are rejected rather than
+// allocating a buffer for them.
+const MAX_ENTRY_LEN: u32 = 256 * 65536 + 4096;
+
+// RecordKind {{{
+
+/// The kind of message stored in a [`RecordEntry`].
+///
+/// [`RecordEntry`]: struct.RecordEntry.html
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RecordKind {
+	/// A request received from the kernel.
+	Request,
+	/// A response sent to the kernel.
+	Response,
+	/// A notification sent to the kernel.
+	Notification,
+}
+
+impl RecordKind {
+	fn to_u32(self) -> u32 {
+		match self {
+			RecordKind::Request => 1,
+			RecordKind::Response => 2,
+			RecordKind::Notification => 3,
+		}
+	}
+
+	fn from_u32(kind: u32) -> Option<RecordKind> {
+		match kind {
+			1 => Some(RecordKind::Request),
+			2 => Some(RecordKind::Response),
+			3 => Some(RecordKind::Notification),
+			_ => None,
+		}
+	}
+
+	// Messages sent to the kernel with a request ID of zero are
+	// notifications.
+	fn of_sent(buf: &[u8]) -> RecordKind {
+		match out_header(buf) {
+			Some(header) if header.unique == 0 => RecordKind::Notification,
+			_ => RecordKind::Response,
+		}
+	}
+}
+
+// }}}
+
+// RecordingChannel {{{
+
+/// A channel that records the messages passing through another channel.
+///
+/// Requests are recorded as they're received, and responses and
+/// notifications as they're sent. Messages are recorded even if the wrapped
+/// channel fails to send them.
+///
+/// Failing to write the recording doesn't affect the wrapped channel. The
+/// first write error stops the recording, and is returned by
+/// [`flush`](#method.flush).
+///
+/// The wrapped channel's [`receive_spliced`] and [`send_from_fd`] are not
+/// used, so that every payload is available to be recorded.
+///
+/// [`receive_spliced`]: ../io/trait.Channel.html#method.receive_spliced
+/// [`send_from_fd`]: ../io/trait.Channel.html#method.send_from_fd
+pub struct RecordingChannel<C, W> {
+	channel: C,
+	recorder: Arc<Recorder<W>>,
+}
+
+impl<C, W: Write> RecordingChannel<C, W> {
+	/// Wraps `channel`, writing the recording to `writer`.
+	///
+	/// Each message is written with a single call to `writer`, so a buffered
+	/// writer is recommended. The recording's header is written immediately.
+	pub fn new(
+		channel: C,
+		writer: W,
+	) -> Result<RecordingChannel<C, W>, io::Error> {
+		Ok(Self {
+			channel,
+			recorder: Arc::new(Recorder::new(writer)?),
+		})
+	}
+
+	/// The wrapped channel.
+	pub fn get_ref(&self) -> &C {
+		&self.channel
+	}
+
+	/// Flushes the recording's writer.
+	///
+	/// Returns an error if any message could not be recorded.
+	pub fn flush(&self) -> Result<(), io::Error> {
+		self.recorder.flush()
+	}
+}
+
+impl<C, W> fmt::Debug for RecordingChannel<C, W>
+where
+	C: fmt::Debug,
+{
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("RecordingChannel")
+			.field("channel", &self.channel)
+			.finish()
+	}
+}
+
+impl<C, W> Channel for RecordingChannel<C, W>
+where
+	C: Channel,
+	W: Write,
+{
+	type Error = C::Error;
+
+	fn send(&self, buf: &[u8]) -> Result<(), C::Error> {
+		let result = self.channel.send(buf);
+		self.recorder.record(RecordKind::of_sent(buf), &[buf]);
+		result
+	}
+
+	fn send_vectored<const N: usize>(
+		&self,
+		bufs: &[&[u8]; N],
+	) -> Result<(), C::Error> {
+		let kind = match bufs.first() {
+			Some(buf) => RecordKind::of_sent(buf),
+			None => RecordKind::Response,
+		};
+		let result = self.channel.send_vectored(bufs);
+		self.recorder.record(kind, bufs);
+		result
+	}
+
+	fn receive(&self, buf: &mut [u8]) -> Result<usize, C::Error> {
+		let size = self.channel.receive(buf)?;
+		self.recorder.record(RecordKind::Request, &[&buf[..size]]);
+		Ok(size)
+	}
+}
+
+impl<C, W> ServerChannel for RecordingChannel<C, W>
+where
+	C: ServerChannel,
+	W: Write,
+{
+	fn try_clone(&self) -> Result<Self, C::Error> {
+		Ok(Self {
+			channel: self.channel.try_clone()?,
+			recorder: self.recorder.clone(),
+		})
+	}
+}
+
+impl<C, W> FuseServerChannel for RecordingChannel<C, W>
+where
+	C: FuseServerChannel,
+	W: Write,
+{
+	fn shutdown(&self) -> Result<(), C::Error> {
+		self.channel.shutdown()
+	}
+
+	fn abort(&self) -> Result<(), C::Error> {
+		self.channel.abort()
+	}
+}
+
+impl<C, W> CuseServerChannel for RecordingChannel<C, W>
+where
+	C: CuseServerChannel,
+	W: Write,
+{
+}
+
+struct Recorder<W> {
+	state: Mutex<RecorderState<W>>,
+	start: Instant,
+}
+
+struct RecorderState<W> {
+	writer: W,
+	// The first write error. Nothing more is recorded after an error,
+	// because the recording may end partway through an entry.
+	error: Option<io::Error>,
+}
+
+impl<W: Write> Recorder<W> {
+	fn new(mut writer: W) -> Result<Recorder<W>, io::Error> {
+		let start_time = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default();
+		let mut header = [0u8; 24];
+		header[0..8].copy_from_slice(&MAGIC);
+		header[8..12].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+		header[16..24]
+			.copy_from_slice(&duration_nanos(start_time).to_le_bytes());
+		writer.write_all(&header)?;
+		Ok(Self {
+			state: Mutex::new(RecorderState {
+				writer,
+				error: None,
+			}),
+			start: Instant::now(),
+		})
+	}
+
+	fn lock(&self) -> MutexGuard<'_, RecorderState<W>> {
+		match self.state.lock() {
+			Ok(guard) => guard,
+			Err(poisoned) => poisoned.into_inner(),
+		}
+	}
+
+	fn record(&self, kind: RecordKind, bufs: &[&[u8]]) {
+		let len: usize = bufs.iter().map(|b| b.len()).sum();
+		let timestamp = duration_nanos(self.start.elapsed());
+
+		let mut entry = Vec::with_capacity(16 + len);
+		entry.extend_from_slice(&kind.to_u32().to_le_bytes());
+		entry.extend_from_slice(&(len as u32).to_le_bytes());
+		entry.extend_from_slice(&timestamp.to_le_bytes());
+		for buf in bufs {
+			entry.extend_from_slice(buf);
+		}
+
+		let mut state = self.lock();
+		if state.error.is_some() {
+			return;
+		}
+		if let Err(err) = state.writer.write_all(&entry) {
+			state.error = Some(err);
+		}
+	}
+
+	fn flush(&self) -> Result<(), io::Error> {
+		let mut state = self.lock();
+		if let Some(err) = &state.error {
+			return Err(io::Error::new(
+				err.kind(),
+				format!("failed to record FUSE message: {}", err),
+			));
+		}
+		state.writer.flush()
+	}
+}
+
+// }}}
+
+// Recording {{{
+
+/// A recording written by a [`RecordingChannel`].
+///
+/// [`RecordingChannel`]: struct.RecordingChannel.html
+pub struct Recording {
+	start_time: SystemTime,
+	entries: Vec<RecordEntry>,
+}
+
+impl Recording {
+	/// Reads a complete recording.
+	///
+	/// A recording that ends partway through an entry, for example because
+	/// the recording process was killed, is read up to the last complete
+	/// entry.
+	pub fn read_from(mut reader: impl Read) -> Result<Recording, io::Error> {
+		let mut header = [0u8; 24];
+		reader.read_exact(&mut header)?;
+		if header[0..8] != MAGIC {
+			return Err(invalid_data("not a FUSE recording"));
+		}
+		let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+		if version != FORMAT_VERSION {
+			return Err(invalid_data("unsupported FUSE recording version"));
+		}
+		let start_nanos =
+			u64::from_le_bytes(header[16..24].try_into().unwrap());
+		let start_time = UNIX_EPOCH + time::Duration::from_nanos(start_nanos);
+
+		let mut entries = Vec::new();
+		loop {
+			let mut entry_header = [0u8; 16];
+			match read_full(&mut reader, &mut entry_header)? {
+				0 => break,
+				16 => {},
+				_ => break,
+			}
+			let kind =
+				u32::from_le_bytes(entry_header[0..4].try_into().unwrap());
+			let kind = RecordKind::from_u32(kind)
+				.ok_or_else(|| invalid_data("invalid FUSE recording entry"))?;
+			let len =
+				u32::from_le_bytes(entry_header[4..8].try_into().unwrap());
+			let nanos =
+				u64::from_le_bytes(entry_header[8..16].try_into().unwrap());
+			if len > MAX_ENTRY_LEN {
+				return Err(invalid_data("FUSE recording entry is too large"));
+			}
+			let mut data = vec![0u8; len as usize];
+			if read_full(&mut reader, &mut data)? != data.len() {
+				break;
+			}
+			entries.push(RecordEntry {
+				kind,
+				timestamp: time::Duration::from_nanos(nanos),
+				data,
+			});
+		}
+		Ok(Self {
+			start_time,
+			entries,
+		})
+	}
+
+	/// The time the recording started.
+	pub fn start_time(&self) -> SystemTime {
+		self.start_time
+	}
+
+	/// The recorded messages, in the order they were received or sent.
+	pub fn entries(&self) -> &[RecordEntry] {
+		&self.entries
+	}
+}
+
+impl fmt::Debug for Recording {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("Recording")
+			.field("start_time", &self.start_time)
+			.field("entries", &self.entries)
+			.finish()
+	}
+}
+
+/// A message stored in a [`Recording`].
+///
+/// [`Recording`]: struct.Recording.html
+pub struct RecordEntry {
+	kind: RecordKind,
+	timestamp: time::Duration,
+	data: Vec<u8>,
+}
+
+impl RecordEntry {
+	pub fn kind(&self) -> RecordKind {
+		self.kind
+	}
+
+	/// The time the message was received or sent, relative to the start of
+	/// the recording.
+	pub fn timestamp(&self) -> time::Duration {
+		self.timestamp
+	}
+
+	/// The message, including its `fuse_in_header` or `fuse_out_header`.
+	pub fn data(&self) -> &[u8] {
+		&self.data
+	}
+
+	/// The header of a recorded request.
+	///
+	/// Returns `None` if this entry isn't a request, or is too short to
+	/// contain a header.
+	pub fn request_header(&self) -> Option<RequestHeader> {
+		if self.kind != RecordKind::Request {
+			return None;
+		}
+		let raw = in_header(&self.data)?;
+		Some(*RequestHeader::new_ref(&raw))
+	}
+
+	/// The header of a recorded response or notification.
+	///
+	/// Returns `None` if this entry is a request, or is too short to contain
+	/// a header.
+	pub fn response_header(&self) -> Option<ResponseHeader> {
+		if self.kind == RecordKind::Request {
+			return None;
+		}
+		let raw = out_header(&self.data)?;
+		Some(*ResponseHeader::new_ref(&raw))
+	}
+}
+
+impl fmt::Debug for RecordEntry {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		let mut dbg = fmt.debug_struct("RecordEntry");
+		dbg.field("kind", &self.kind);
+		dbg.field("timestamp", &self.timestamp);
+		if let Some(header) = self.request_header() {
+			dbg.field("header", &header);
+		}
+		if let Some(header) = self.response_header() {
+			dbg.field("header", &header);
+		}
+		dbg.field("len", &self.data.len());
+		dbg.finish()
+	}
+}
+
+// }}}
+
+// ReplayChannel {{{
+
+/// A channel that replays the requests in a [`Recording`], and compares the
+/// server's responses against the recorded responses.
+///
+/// Requests are received in the order they were recorded, after which
+/// `receive()` fails with `ENODEV` as if the filesystem had been unmounted.
+/// Once the server has stopped, [`mismatches`](#method.mismatches) reports
+/// every response that differs from the recording. Clones of a
+/// `ReplayChannel` share its state, so a clone can be kept to check the
+/// mismatches after the original has been passed to a server.
+///
+/// Responses are matched to recorded responses by request ID, and
+/// notifications are matched in the order they were recorded.
+///
+/// [`Recording`]: struct.Recording.html
+pub struct ReplayChannel {
+	state: Arc<Mutex<ReplayState>>,
+}
+
+struct ReplayState {
+	requests: VecDeque<Vec<u8>>,
+	request_headers: HashMap<u64, RequestHeader>,
+	responses: HashMap<u64, Vec<u8>>,
+	notifications: VecDeque<Vec<u8>>,
+	mismatches: Vec<ReplayMismatch>,
+}
+
+impl ReplayChannel {
+	pub fn new(recording: &Recording) -> ReplayChannel {
+		let mut state = ReplayState {
+			requests: VecDeque::new(),
+			request_headers: HashMap::new(),
+			responses: HashMap::new(),
+			notifications: VecDeque::new(),
+			mismatches: Vec::new(),
+		};
+		for entry in &recording.entries {
+			match entry.kind {
+				RecordKind::Request => {
+					if let Some(header) = entry.request_header() {
+						let request_id = header.request_id();
+						state.request_headers.insert(request_id, header);
+					}
+					state.requests.push_back(entry.data.clone());
+				},
+				RecordKind::Response => {
+					if let Some(header) = entry.response_header() {
+						let request_id = header.request_id();
+						state.responses.insert(request_id, entry.data.clone());
+					}
+				},
+				RecordKind::Notification => {
+					state.notifications.push_back(entry.data.clone());
+				},
+			}
+		}
+		Self {
+			state: Arc::new(Mutex::new(state)),
+		}
+	}
+
+	/// Returns the responses and notifications that differ from the
+	/// recording, including recorded messages that were never sent.
+	///
+	/// This should be called after the server has stopped.
+	pub fn mismatches(&self) -> Vec<ReplayMismatch> {
+		let state = self.lock();
+		let mut mismatches = state.mismatches.clone();
+		let mut missing: Vec<_> = state.responses.iter().collect();
+		missing.sort_by_key(|(request_id, _)| **request_id);
+		for (request_id, expected) in missing {
+			mismatches.push(ReplayMismatch {
+				kind: RecordKind::Response,
+				request: state.request_headers.get(request_id).copied(),
+				expected: Some(expected.clone()),
+				actual: None,
+			});
+		}
+		for expected in &state.notifications {
+			mismatches.push(ReplayMismatch {
+				kind: RecordKind::Notification,
+				request: None,
+				expected: Some(expected.clone()),
+				actual: None,
+			});
+		}
+		mismatches
+	}
+
+	fn lock(&self) -> MutexGuard<'_, ReplayState> {
+		match self.state.lock() {
+			Ok(guard) => guard,
+			Err(poisoned) => poisoned.into_inner(),
+		}
+	}
+}
+
+impl Clone for ReplayChannel {
+	fn clone(&self) -> Self {
+		Self {
+			state: self.state.clone(),
+		}
+	}
+}
+
+impl fmt::Debug for ReplayChannel {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		let state = self.lock();
+		fmt.debug_struct("ReplayChannel")
+			.field("pending_requests", &state.requests.len())
+			.finish()
+	}
+}
+
+impl Channel for ReplayChannel {
+	type Error = io::Error;
+
+	fn send(&self, buf: &[u8]) -> Result<(), io::Error> {
+		let mut state = self.lock();
+		let state = &mut *state;
+		let actual = buf.to_vec();
+		let (kind, request, expected) = match RecordKind::of_sent(buf) {
+			RecordKind::Notification => (
+				RecordKind::Notification,
+				None,
+				state.notifications.pop_front(),
+			),
+			_ => {
+				let request_id = out_header(buf).map_or(0, |h| h.unique);
+				(
+					RecordKind::Response,
+					state.request_headers.get(&request_id).copied(),
+					state.responses.remove(&request_id),
+				)
+			},
+		};
+		if expected.as_ref() != Some(&actual) {
+			state.mismatches.push(ReplayMismatch {
+				kind,
+				request,
+				expected,
+				actual: Some(actual),
+			});
+		}
+		Ok(())
+	}
+
+	fn send_vectored<const N: usize>(
+		&self,
+		bufs: &[&[u8]; N],
+	) -> Result<(), io::Error> {
+		self.send(&bufs.concat())
+	}
+
+	fn receive(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
+		let request = match self.lock().requests.pop_front() {
+			Some(request) => request,
+			None => {
+				return Err(io::Error::from_raw_os_error(
+					ErrorCode::ENODEV.into(),
+				));
+			},
+		};
+		if request.len() > buf.len() {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"receive buffer is smaller than the request",
+			));
+		}
+		buf[..request.len()].copy_from_slice(&request);
+		Ok(request.len())
+	}
+}
+
+impl ServerChannel for ReplayChannel {
+	fn try_clone(&self) -> Result<Self, io::Error> {
+		Ok(self.clone())
+	}
+}
+
+impl FuseServerChannel for ReplayChannel {}
+
+impl CuseServerChannel for ReplayChannel {}
+
+/// A difference between a replayed response or notification and the
+/// recording.
+#[derive(Clone)]
+pub struct ReplayMismatch {
+	kind: RecordKind,
+	request: Option<RequestHeader>,
+	expected: Option<Vec<u8>>,
+	actual: Option<Vec<u8>>,
+}
+
+impl ReplayMismatch {
+	/// Whether the mismatched message is a response or a notification.
+	pub fn kind(&self) -> RecordKind {
+		self.kind
+	}
+
+	/// The header of the request being responded to, if known.
+	pub fn request_header(&self) -> Option<&RequestHeader> {
+		self.request.as_ref()
+	}
+
+	/// The recorded message, or `None` if nothing was recorded.
+	pub fn expected(&self) -> Option<&[u8]> {
+		self.expected.as_deref()
+	}
+
+	/// The replayed message, or `None` if it was never sent.
+	pub fn actual(&self) -> Option<&[u8]> {
+		self.actual.as_deref()
+	}
+
+	/// The offset of the first byte that differs between the recorded and
+	/// replayed messages, if both exist.
+	pub fn diff_offset(&self) -> Option<usize> {
+		let expected = self.expected.as_ref()?;
+		let actual = self.actual.as_ref()?;
+		let offset = expected
+			.iter()
+			.zip(actual.iter())
+			.position(|(a, b)| a != b)
+			.unwrap_or(cmp::min(expected.len(), actual.len()));
+		Some(offset)
+	}
+}
+
+impl fmt::Debug for ReplayMismatch {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fn header(buf: &Option<Vec<u8>>) -> Option<ResponseHeader> {
+			let raw = out_header(buf.as_ref()?)?;
+			Some(*ResponseHeader::new_ref(&raw))
+		}
+		fmt.debug_struct("ReplayMismatch")
+			.field("kind", &self.kind)
+			.field("request", &self.request)
+			.field("expected", &header(&self.expected))
+			.field("actual", &header(&self.actual))
+			.field("diff_offset", &self.diff_offset())
+			.finish()
+	}
+}
+
+// }}}
+
+fn in_header(buf: &[u8]) -> Option<fuse_kernel::fuse_in_header> {
+	if buf.len() < size_of::<fuse_kernel::fuse_in_header>() {
+		return None;
+	}
+	let p = buf.as_ptr() as *const fuse_kernel::fuse_in_header;
+	Some(unsafe { ptr::read_unaligned(p) })
+}
+
+fn out_header(buf: &[u8]) -> Option<fuse_kernel::fuse_out_header> {
+	if buf.len() < size_of::<fuse_kernel::fuse_out_header>() {
+		return None;
+	}
+	let p = buf.as_ptr() as *const fuse_kernel::fuse_out_header;
+	Some(unsafe { ptr::read_unaligned(p) })
+}
+
+fn duration_nanos(d: time::Duration) -> u64 {
+	cmp::min(d.as_nanos(), u64::MAX as u128) as u64
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// Reads until `buf` is full or EOF is reached, returning the number of bytes
+// read.
+fn read_full(
+	reader: &mut impl Read,
+	buf: &mut [u8],
+) -> Result<usize, io::Error> {
+	let mut total = 0;
+	while total < buf.len() {
+		match reader.read(&mut buf[total..]) {
+			Ok(0) => break,
+			Ok(n) => total += n,
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+			Err(err) => return Err(err),
+		}
+	}
+	Ok(total)
+}
@@ -0,0 +1,190 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::fuse_server::{FuseServerBuilder, FuseServerChannel};
+use crate::internal::testutil::{hello_name, HelloFS};
+use crate::server::{NoopServerHooks, ServerChannel};
+use crate::testing::FuseClient;
+use crate::{ErrorCode, NodeName, ROOT_ID};
+
+use super::{RecordKind, Recording, RecordingChannel, ReplayChannel};
+
+#[derive(Clone)]
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuf {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.lock().unwrap().extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+// Accepts the recording's header, then fails every write.
+struct FailingWriter;
+
+impl Write for FailingWriter {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if buf.len() == 24 {
+			return Ok(buf.len());
+		}
+		Err(io::Error::other("disk full"))
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+fn serve<C>(channel: C, handlers: HelloFS) -> io::Result<()>
+where
+	C: FuseServerChannel<Error = io::Error> + Send + Sync + 'static,
+{
+	let builder: FuseServerBuilder<_, _, NoopServerHooks> =
+		FuseServerBuilder::new(channel, handlers);
+	builder.build()?.executor_mut().run()
+}
+
+fn record_session() -> Recording {
+	let buf = SharedBuf(Arc::new(Mutex::new(Vec::new())));
+	let (mut client, channel) = FuseClient::new();
+	let channel = RecordingChannel::new(channel, buf.clone()).unwrap();
+	let server = thread::spawn(move || serve(channel, HelloFS::new()));
+
+	client.fuse_init().unwrap();
+	client.lookup(ROOT_ID, hello_name()).unwrap();
+	let missing = NodeName::from_bytes(b"missing.txt").unwrap();
+	assert_eq!(
+		client.lookup(ROOT_ID, missing).unwrap_err(),
+		ErrorCode::ENOENT
+	);
+	client.destroy().unwrap();
+	server.join().unwrap().unwrap();
+
+	let buf = buf.0.lock().unwrap();
+	Recording::read_from(&buf[..]).unwrap()
+}
+
+#[test]
+fn record() {
+	let recording = record_session();
+	let kinds: Vec<_> = recording.entries().iter().map(|e| e.kind()).collect();
+	assert_eq!(
+		kinds,
+		&[
+			RecordKind::Request,
+			RecordKind::Response,
+			RecordKind::Request,
+			RecordKind::Response,
+			RecordKind::Request,
+			RecordKind::Response,
+			RecordKind::Request,
+			RecordKind::Response,
+		]
+	);
+
+	let entries = recording.entries();
+	let request = entries[4].request_header().unwrap();
+	let response = entries[5].response_header().unwrap();
+	assert_eq!(request.request_id(), response.request_id());
+	assert_eq!(response.error(), Some(ErrorCode::ENOENT));
+	assert!(entries[5].timestamp() >= entries[4].timestamp());
+}
+
+#[test]
+fn record_truncated() {
+	let buf = SharedBuf(Arc::new(Mutex::new(Vec::new())));
+	let (mut client, channel) = FuseClient::new();
+	let channel = RecordingChannel::new(channel, buf.clone()).unwrap();
+	let server = thread::spawn(move || serve(channel, HelloFS::new()));
+	client.fuse_init().unwrap();
+	client.destroy().unwrap();
+	server.join().unwrap().unwrap();
+
+	let mut buf = buf.0.lock().unwrap().clone();
+	buf.truncate(buf.len() - 1);
+	let recording = Recording::read_from(&buf[..]).unwrap();
+	assert_eq!(recording.entries().len(), 3);
+}
+
+#[test]
+fn replay_matching() {
+	let recording = record_session();
+	let channel = ReplayChannel::new(&recording);
+	let replay = channel.clone();
+	serve(channel, HelloFS::new()).unwrap();
+	assert!(replay.mismatches().is_empty());
+}
+
+#[test]
+fn replay_mismatched() {
+	let recording = record_session();
+	let channel = ReplayChannel::new(&recording);
+	let replay = channel.clone();
+	let handlers = HelloFS {
+		contents: b"Hello, world?!\n",
+		..HelloFS::new()
+	};
+	serve(channel, handlers).unwrap();
+
+	let mismatches = replay.mismatches();
+	assert_eq!(mismatches.len(), 1);
+	let mismatch = &mismatches[0];
+	assert_eq!(mismatch.kind(), RecordKind::Response);
+	let request = mismatch.request_header().unwrap();
+	assert_eq!(request.opcode_enum(), Some(crate::Opcode::FUSE_LOOKUP));
+	assert!(mismatch.diff_offset().is_some());
+}
+
+#[test]
+fn record_write_error() {
+	let (mut client, channel) = FuseClient::new();
+	let channel = RecordingChannel::new(channel, FailingWriter).unwrap();
+	let recording = channel.try_clone().unwrap();
+	let server = thread::spawn(move || serve(channel, HelloFS::new()));
+
+	client.fuse_init().unwrap();
+	client.lookup(ROOT_ID, hello_name()).unwrap();
+	client.destroy().unwrap();
+	server.join().unwrap().unwrap();
+
+	let err = recording.flush().unwrap_err();
+	assert_eq!(err.to_string(), "failed to record FUSE message: disk full");
+}
+
+#[test]
+fn read_oversized_entry() {
+	let buf = SharedBuf(Arc::new(Mutex::new(Vec::new())));
+	let (mut client, channel) = FuseClient::new();
+	let channel = RecordingChannel::new(channel, buf.clone()).unwrap();
+	let server = thread::spawn(move || serve(channel, HelloFS::new()));
+	client.fuse_init().unwrap();
+	client.destroy().unwrap();
+	server.join().unwrap().unwrap();
+
+	// Overwrite the length of the first entry.
+	let mut buf = buf.0.lock().unwrap().clone();
+	buf[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+	let err = Recording::read_from(&buf[..]).unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,372 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::{align_of, size_of};
+
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "std")]
+use crate::channel::Channel;
+use crate::error::Error;
+#[cfg(feature = "std")]
+use crate::error::ErrorCode;
+use crate::internal::fuse_io::{self, DecodeRequest};
+use crate::internal::fuse_kernel;
+use crate::internal::types::ProtocolVersion;
+use crate::protocol::common::{RequestHeader, UnknownRequest};
+use crate::protocol::*;
+
+#[cfg(rust_fuse_test = "sans_io_test")]
+mod sans_io_test;
+
+// FuseRequest {{{
+
+/// A FUSE request decoded by [`decode_request`].
+///
+/// Requests without a typed representation are decoded as
+/// `FuseRequest::Unknown`.
+///
+/// [`decode_request`]: fn.decode_request.html
+#[derive(Debug)]
+pub enum FuseRequest<'a> {
+	Access(AccessRequest<'a>),
+	#[cfg(any(doc, feature = "unstable_bmap"))]
+	#[cfg_attr(doc, doc(cfg(feature = "unstable_bmap")))]
+	Bmap(BmapRequest<'a>),
+	CopyFileRange(CopyFileRangeRequest<'a>),
+	Create(CreateRequest<'a>),
+	Destroy,
+	Fallocate(FallocateRequest<'a>),
+	Flush(FlushRequest<'a>),
+	Forget(ForgetRequest<'a>),
+	Fsync(FsyncRequest<'a>),
+	Fsyncdir(FsyncdirRequest<'a>),
+	FuseInit(FuseInitRequest<'a>),
+	Getattr(GetattrRequest<'a>),
+	Getlk(GetlkRequest<'a>),
+	Getxattr(GetxattrRequest<'a>),
+	Ioctl(IoctlRequest<'a>),
+	Link(LinkRequest<'a>),
+	Listxattr(ListxattrRequest<'a>),
+	Lookup(LookupRequest<'a>),
+	Lseek(LseekRequest<'a>),
+	Mkdir(MkdirRequest<'a>),
+	Mknod(MknodRequest<'a>),
+	NotifyReply(RetrieveReply<'a>),
+	Open(OpenRequest<'a>),
+	Opendir(OpendirRequest<'a>),
+	Poll(PollRequest<'a>),
+	Read(ReadRequest<'a>),
+	Readdir(ReaddirRequest<'a>),
+	Readdirplus(ReaddirplusRequest<'a>),
+	Readlink(ReadlinkRequest<'a>),
+	Release(ReleaseRequest<'a>),
+	Releasedir(ReleasedirRequest<'a>),
+	Removexattr(RemovexattrRequest<'a>),
+	Rename(RenameRequest<'a>),
+	Rmdir(RmdirRequest<'a>),
+	Setattr(SetattrRequest<'a>),
+	Setlk(SetlkRequest<'a>),
+	Setxattr(SetxattrRequest<'a>),
+	Statfs(StatfsRequest<'a>),
+	Symlink(SymlinkRequest<'a>),
+	Unlink(UnlinkRequest<'a>),
+	Write(WriteRequest<'a>),
+	Unknown(UnknownRequest<'a>),
+}
+
+// }}}
+
+// CuseRequest {{{
+
+/// A CUSE request decoded by [`decode_cuse_request`].
+///
+/// Requests without a typed representation, or that CUSE servers don't
+/// handle, are decoded as `CuseRequest::Unknown`.
+///
+/// [`decode_cuse_request`]: fn.decode_cuse_request.html
+#[derive(Debug)]
+pub enum CuseRequest<'a> {
+	CuseInit(CuseInitRequest<'a>),
+	Flush(FlushRequest<'a>),
+	Fsync(FsyncRequest<'a>),
+	Ioctl(IoctlRequest<'a>),
+	Open(OpenRequest<'a>),
+	Poll(PollRequest<'a>),
+	Read(ReadRequest<'a>),
+	Release(ReleaseRequest<'a>),
+	Write(WriteRequest<'a>),
+	Unknown(UnknownRequest<'a>),
+}
+
+// }}}
+
+/// Returns the header of the request at the start of `buf`.
+///
+/// This succeeds for any request with a complete header, including requests
+/// that [`decode_request`] can't decode. The size of the request, including
+/// the header, is [`RequestHeader::size`].
+///
+/// `buf` must be aligned to 8 bytes.
+///
+/// [`decode_request`]: fn.decode_request.html
+/// [`RequestHeader::size`]: ../struct.RequestHeader.html#method.size
+pub fn request_header(buf: &[u8]) -> Result<&RequestHeader, Error> {
+	check_aligned(buf)?;
+	if buf.len() < size_of::<fuse_kernel::fuse_in_header>() {
+		return Err(Error::unexpected_eof());
+	}
+	let header = buf.as_ptr() as *const fuse_kernel::fuse_in_header;
+	Ok(RequestHeader::new_ref(unsafe { &*header }))
+}
+
+/// Decodes the FUSE request at the start of `buf`, for a session that has
+/// negotiated `version`.
+///
+/// `buf` must be aligned to 8 bytes, and must contain the entire request.
+/// Any bytes after the end of the request are ignored.
+///
+/// Requests sent to a CUSE device should be decoded with
+/// [`decode_cuse_request`].
+///
+/// [`decode_cuse_request`]: fn.decode_cuse_request.html
+pub fn decode_request<'a>(
+	buf: &'a [u8],
+	version: ProtocolVersion,
+) -> Result<FuseRequest<'a>, Error> {
+	let dec = request_decoder(buf, version, fuse_io::Semantics::FUSE)?;
+
+	macro_rules! decode {
+		($variant:ident) => {
+			FuseRequest::$variant(DecodeRequest::decode_request(dec)?)
+		};
+	}
+
+	Ok(match dec.header().opcode {
+		fuse_kernel::FUSE_ACCESS => decode!(Access),
+		#[cfg(feature = "unstable_bmap")]
+		fuse_kernel::FUSE_BMAP => decode!(Bmap),
+		fuse_kernel::FUSE_COPY_FILE_RANGE => decode!(CopyFileRange),
+		fuse_kernel::FUSE_CREATE => decode!(Create),
+		fuse_kernel::FUSE_DESTROY => FuseRequest::Destroy,
+		fuse_kernel::FUSE_FALLOCATE => decode!(Fallocate),
+		fuse_kernel::FUSE_FLUSH => decode!(Flush),
+		fuse_kernel::FUSE_FORGET | fuse_kernel::FUSE_BATCH_FORGET => {
+			decode!(Forget)
+		},
+		fuse_kernel::FUSE_FSYNC => decode!(Fsync),
+		fuse_kernel::FUSE_FSYNCDIR => decode!(Fsyncdir),
+		fuse_kernel::FUSE_GETATTR => decode!(Getattr),
+		fuse_kernel::FUSE_GETLK => decode!(Getlk),
+		fuse_kernel::FUSE_GETXATTR => decode!(Getxattr),
+		fuse_kernel::FUSE_INIT => decode!(FuseInit),
+		fuse_kernel::FUSE_IOCTL => decode!(Ioctl),
+		fuse_kernel::FUSE_LINK => decode!(Link),
+		fuse_kernel::FUSE_LISTXATTR => decode!(Listxattr),
+		fuse_kernel::FUSE_LOOKUP => decode!(Lookup),
+		fuse_kernel::FUSE_LSEEK => decode!(Lseek),
+		fuse_kernel::FUSE_MKDIR => decode!(Mkdir),
+		fuse_kernel::FUSE_MKNOD => decode!(Mknod),
+		fuse_kernel::FUSE_NOTIFY_REPLY => decode!(NotifyReply),
+		fuse_kernel::FUSE_OPEN => decode!(Open),
+		fuse_kernel::FUSE_OPENDIR => decode!(Opendir),
+		fuse_kernel::FUSE_POLL => decode!(Poll),
+		fuse_kernel::FUSE_READ => decode!(Read),
+		fuse_kernel::FUSE_READDIR => decode!(Readdir),
+		fuse_kernel::FUSE_READDIRPLUS => decode!(Readdirplus),
+		fuse_kernel::FUSE_READLINK => decode!(Readlink),
+		fuse_kernel::FUSE_RELEASE => decode!(Release),
+		fuse_kernel::FUSE_RELEASEDIR => decode!(Releasedir),
+		fuse_kernel::FUSE_REMOVEXATTR => decode!(Removexattr),
+		fuse_kernel::FUSE_RENAME | fuse_kernel::FUSE_RENAME2 => {
+			decode!(Rename)
+		},
+		fuse_kernel::FUSE_RMDIR => decode!(Rmdir),
+		fuse_kernel::FUSE_SETATTR => decode!(Setattr),
+		fuse_kernel::FUSE_SETLK | fuse_kernel::FUSE_SETLKW => decode!(Setlk),
+		fuse_kernel::FUSE_SETXATTR => decode!(Setxattr),
+		fuse_kernel::FUSE_STATFS => decode!(Statfs),
+		fuse_kernel::FUSE_SYMLINK => decode!(Symlink),
+		fuse_kernel::FUSE_UNLINK => decode!(Unlink),
+		fuse_kernel::FUSE_WRITE => decode!(Write),
+		_ => decode!(Unknown),
+	})
+}
+
+/// Decodes the CUSE request at the start of `buf`, for a session that has
+/// negotiated `version`.
+///
+/// CUSE requests don't refer to a node, so node IDs in the decoded requests
+/// are always [`ROOT_ID`]. The buffer requirements are the same as for
+/// [`decode_request`].
+///
+/// [`ROOT_ID`]: ../constant.ROOT_ID.html
+/// [`decode_request`]: fn.decode_request.html
+pub fn decode_cuse_request<'a>(
+	buf: &'a [u8],
+	version: ProtocolVersion,
+) -> Result<CuseRequest<'a>, Error> {
+	let dec = request_decoder(buf, version, fuse_io::Semantics::CUSE)?;
+
+	macro_rules! decode {
+		($variant:ident) => {
+			CuseRequest::$variant(DecodeRequest::decode_request(dec)?)
+		};
+	}
+
+	Ok(match dec.header().opcode {
+		fuse_kernel::CUSE_INIT => decode!(CuseInit),
+		fuse_kernel::FUSE_FLUSH => decode!(Flush),
+		fuse_kernel::FUSE_FSYNC => decode!(Fsync),
+		fuse_kernel::FUSE_IOCTL => decode!(Ioctl),
+		fuse_kernel::FUSE_OPEN => decode!(Open),
+		fuse_kernel::FUSE_POLL => decode!(Poll),
+		fuse_kernel::FUSE_READ => decode!(Read),
+		fuse_kernel::FUSE_RELEASE => decode!(Release),
+		fuse_kernel::FUSE_WRITE => decode!(Write),
+		_ => decode!(Unknown),
+	})
+}
+
+fn request_decoder<'a>(
+	buf: &'a [u8],
+	version: ProtocolVersion,
+	semantics: fuse_io::Semantics,
+) -> Result<fuse_io::RequestDecoder<'a>, Error> {
+	check_aligned(buf)?;
+	fuse_io::RequestDecoder::new(
+		fuse_io::AlignedSlice::new(buf),
+		version,
+		semantics,
+	)
+}
+
+fn check_aligned(buf: &[u8]) -> Result<(), Error> {
+	if buf.as_ptr() as usize % align_of::<u64>() != 0 {
+		return Err(Error::unaligned_buffer());
+	}
+	Ok(())
+}
+
+// EncodeResponse {{{
+
+/// **\[SEALED\]** A response that can be encoded with [`encode_response`].
+///
+/// This is implemented for all response types in the [`protocol`] module.
+///
+/// [`encode_response`]: fn.encode_response.html
+/// [`protocol`]: ../protocol/index.html
+pub trait EncodeResponse: private::EncodeResponse {}
+
+impl<R: fuse_io::EncodeResponse> EncodeResponse for R {}
+
+mod private {
+	#[cfg(feature = "std")]
+	use crate::internal::types::ProtocolVersion;
+
+	pub trait EncodeResponse {
+		#[cfg(feature = "std")]
+		fn encode_into(
+			&self,
+			request_id: u64,
+			version: ProtocolVersion,
+			buf: &mut Vec<u8>,
+		);
+	}
+}
+
+impl<R: fuse_io::EncodeResponse> private::EncodeResponse for R {
+	#[cfg(feature = "std")]
+	fn encode_into(
+		&self,
+		request_id: u64,
+		version: ProtocolVersion,
+		buf: &mut Vec<u8>,
+	) {
+		let channel = VecChannel(RefCell::new(buf));
+		let encoder =
+			fuse_io::ResponseEncoder::new(&channel, request_id, version);
+		// Sending to a `VecChannel` can't fail.
+		let _ = self.encode_response(encoder);
+	}
+}
+
+/// Encodes `response` as the reply to request `request_id`, for a session
+/// that has negotiated `version`.
+///
+/// The returned buffer holds a complete message, including the
+/// `fuse_out_header`.
+#[cfg(feature = "std")]
+#[cfg_attr(doc, doc(cfg(feature = "std")))]
+pub fn encode_response<R: EncodeResponse>(
+	request_id: u64,
+	version: ProtocolVersion,
+	response: &R,
+) -> Vec<u8> {
+	let mut buf = Vec::new();
+	response.encode_into(request_id, version, &mut buf);
+	buf
+}
+
+/// Encodes an error reply to request `request_id`.
+#[cfg(feature = "std")]
+#[cfg_attr(doc, doc(cfg(feature = "std")))]
+pub fn encode_error(request_id: u64, error: ErrorCode) -> Vec<u8> {
+	let mut buf = Vec::new();
+	let channel = VecChannel(RefCell::new(&mut buf));
+	let version = ProtocolVersion::LATEST;
+	let encoder = fuse_io::ResponseEncoder::new(&channel, request_id, version);
+	let _ = encoder.encode_error(error);
+	buf
+}
+
+// }}}
+
+// VecChannel {{{
+
+// Appends each message sent to it to a buffer.
+#[cfg(feature = "std")]
+struct VecChannel<'a>(RefCell<&'a mut Vec<u8>>);
+
+#[cfg(feature = "std")]
+impl Channel for VecChannel<'_> {
+	type Error = io::Error;
+
+	fn send(&self, buf: &[u8]) -> Result<(), io::Error> {
+		self.0.borrow_mut().extend_from_slice(buf);
+		Ok(())
+	}
+
+	fn send_vectored<const N: usize>(
+		&self,
+		bufs: &[&[u8]; N],
+	) -> Result<(), io::Error> {
+		let mut out = self.0.borrow_mut();
+		for buf in bufs {
+			out.extend_from_slice(buf);
+		}
+		Ok(())
+	}
+
+	fn receive(&self, _buf: &mut [u8]) -> Result<usize, io::Error> {
+		Err(io::ErrorKind::Unsupported.into())
+	}
+}
+
+// }}}
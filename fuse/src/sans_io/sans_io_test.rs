@@ -0,0 +1,154 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::{Error, ErrorCode};
+use crate::internal::fuse_io::AlignedBuffer;
+use crate::internal::fuse_kernel;
+use crate::internal::testutil::MessageBuilder;
+use crate::internal::types::ProtocolVersion;
+use crate::protocol::FlushResponse;
+
+use super::{
+	decode_cuse_request,
+	decode_request,
+	encode_error,
+	encode_response,
+	request_header,
+	CuseRequest,
+	FuseRequest,
+};
+
+#[test]
+fn decode_lookup() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_LOOKUP;
+			h.unique = 0xAABBCCDD;
+			h.nodeid = 123;
+		})
+		.push_bytes(b"hello.world!\x00")
+		.build_aligned();
+
+	let header = request_header(buf.get()).unwrap();
+	assert_eq!(header.request_id(), 0xAABBCCDD);
+
+	let request = decode_request(buf.get(), ProtocolVersion::LATEST).unwrap();
+	let request = match request {
+		FuseRequest::Lookup(request) => request,
+		_ => panic!("expected FuseRequest::Lookup, got {:?}", request),
+	};
+	let expect: &[u8] = b"hello.world!";
+	assert_eq!(request.name(), expect);
+}
+
+#[test]
+fn decode_unknown_opcode() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::Opcode(0xFFFF);
+		})
+		.push_bytes(&[1, 2, 3, 4])
+		.build_aligned();
+
+	let request = decode_request(buf.get(), ProtocolVersion::LATEST).unwrap();
+	assert!(matches!(request, FuseRequest::Unknown(_)));
+}
+
+#[test]
+fn decode_cuse_open() {
+	// CUSE requests have no node ID, which FUSE requests would reject.
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_OPEN;
+			h.nodeid = 0;
+		})
+		.push_sized(&fuse_kernel::fuse_open_in {
+			flags: 0o2,
+			unused: 0,
+		})
+		.build_aligned();
+
+	let err = decode_request(buf.get(), ProtocolVersion::LATEST).unwrap_err();
+	assert_eq!(err, Error::missing_node_id());
+
+	let request =
+		decode_cuse_request(buf.get(), ProtocolVersion::LATEST).unwrap();
+	let request = match request {
+		CuseRequest::Open(request) => request,
+		_ => panic!("expected CuseRequest::Open, got {:?}", request),
+	};
+	assert_eq!(request.node_id(), crate::ROOT_ID);
+	assert_eq!(request.flags(), 0o2);
+}
+
+#[test]
+fn decode_cuse_unknown_opcode() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_LOOKUP;
+		})
+		.push_bytes(b"hello.world!\x00")
+		.build_aligned();
+
+	let request =
+		decode_cuse_request(buf.get(), ProtocolVersion::LATEST).unwrap();
+	assert!(matches!(request, CuseRequest::Unknown(_)));
+}
+
+#[test]
+fn decode_unaligned() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_kernel::FUSE_LOOKUP;
+		})
+		.push_bytes(b"hello.world!\x00")
+		.build_aligned();
+
+	let err =
+		decode_request(&buf.get()[1..], ProtocolVersion::LATEST).unwrap_err();
+	assert_eq!(err, Error::unaligned_buffer());
+}
+
+#[test]
+fn encode_response_bytes() {
+	let response = FlushResponse::new();
+	let buf = encode_response(0xAABBCCDD, ProtocolVersion::LATEST, &response);
+	assert_eq!(
+		buf,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: 16,
+				error: 0,
+				unique: 0xAABBCCDD,
+			})
+			.build()
+	);
+}
+
+#[test]
+fn encode_error_bytes() {
+	let buf = encode_error(0xAABBCCDD, ErrorCode::ENOENT);
+	assert_eq!(
+		buf,
+		MessageBuilder::new()
+			.push_sized(&fuse_kernel::fuse_out_header {
+				len: 16,
+				error: -i32::from(ErrorCode::ENOENT),
+				unique: 0xAABBCCDD,
+			})
+			.build()
+	);
+}
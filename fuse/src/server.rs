@@ -15,9 +15,14 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use core::cmp::{max, min};
+use core::sync::atomic::{AtomicBool, Ordering};
 
 #[cfg(feature = "respond_async")]
-use std::sync::Arc;
+use std::collections::HashMap;
+#[cfg(feature = "respond_async")]
+use std::sync::{Arc, Condvar, Mutex};
+#[cfg(feature = "respond_async")]
+use std::time::Duration;
 
 use crate::channel::{self, ChannelError};
 use crate::error::{Error, ErrorCode};
@@ -26,6 +31,10 @@ use crate::internal::fuse_kernel;
 use crate::internal::types::ProtocolVersion;
 use crate::protocol::common::{RequestHeader, UnknownRequest};
 
+#[cfg(rust_fuse_test = "server_test")]
+#[path = "server_test.rs"]
+mod server_test;
+
 pub trait ServerChannel: channel::Channel {
 	fn try_clone(&self) -> Result<Self, Self::Error>
 	where
@@ -34,18 +43,271 @@ pub trait ServerChannel: channel::Channel {
 
 pub struct ServerContext {
 	header: fuse_kernel::fuse_in_header,
+
+	#[cfg(feature = "respond_async")]
+	cancellation: CancellationHandle,
 }
 
 impl<'a> ServerContext {
-	pub(crate) fn new(header: fuse_kernel::fuse_in_header) -> Self {
-		Self { header }
+	pub(crate) fn new(
+		header: fuse_kernel::fuse_in_header,
+		#[cfg(feature = "respond_async")] cancellation: CancellationHandle,
+	) -> Self {
+		Self {
+			header,
+			#[cfg(feature = "respond_async")]
+			cancellation,
+		}
 	}
 
 	pub fn request_header(&self) -> &RequestHeader {
 		RequestHeader::new_ref(&self.header)
 	}
+
+	/// Returns a handle that will be cancelled if the kernel sends a
+	/// `FUSE_INTERRUPT` for this request.
+	#[cfg(feature = "respond_async")]
+	#[cfg_attr(doc, doc(cfg(feature = "respond_async")))]
+	pub fn cancellation(&self) -> &CancellationHandle {
+		&self.cancellation
+	}
+}
+
+// CancellationHandle {{{
+
+/// Signals that the kernel has interrupted an in-flight request.
+///
+/// When the process that triggered a request receives a signal, the kernel
+/// sends a `FUSE_INTERRUPT` naming that request. Handlers that may block for
+/// a long time can poll or wait on the request's `CancellationHandle`, and
+/// respond with [`ErrorCode::EINTR`] once it has been cancelled.
+///
+/// [`ErrorCode::EINTR`]: struct.ErrorCode.html#associatedconstant.EINTR
+#[cfg(feature = "respond_async")]
+#[cfg_attr(doc, doc(cfg(feature = "respond_async")))]
+#[derive(Clone)]
+pub struct CancellationHandle(Arc<CancellationState>);
+
+#[cfg(feature = "respond_async")]
+struct CancellationState {
+	cancelled: Mutex<bool>,
+	cond: Condvar,
+}
+
+#[cfg(feature = "respond_async")]
+impl CancellationHandle {
+	fn new() -> CancellationHandle {
+		Self(Arc::new(CancellationState {
+			cancelled: Mutex::new(false),
+			cond: Condvar::new(),
+		}))
+	}
+
+	fn cancel(&self) {
+		let mut cancelled = self.0.cancelled.lock().unwrap();
+		*cancelled = true;
+		self.0.cond.notify_all();
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		*self.0.cancelled.lock().unwrap()
+	}
+
+	/// Blocks the current thread until the request is cancelled.
+	pub fn wait(&self) {
+		let mut cancelled = self.0.cancelled.lock().unwrap();
+		while !*cancelled {
+			cancelled = self.0.cond.wait(cancelled).unwrap();
+		}
+	}
+
+	/// Blocks the current thread until the request is cancelled, or until
+	/// `timeout` has elapsed. Returns whether the request was cancelled.
+	pub fn wait_timeout(&self, timeout: Duration) -> bool {
+		let cancelled = self.0.cancelled.lock().unwrap();
+		let (cancelled, _) = self
+			.0
+			.cond
+			.wait_timeout_while(cancelled, timeout, |c| !*c)
+			.unwrap();
+		*cancelled
+	}
+}
+
+#[cfg(feature = "respond_async")]
+impl core::fmt::Debug for CancellationHandle {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		fmt.debug_struct("CancellationHandle")
+			.field("cancelled", &self.is_cancelled())
+			.finish()
+	}
+}
+
+// }}}
+
+// InflightRequests {{{
+
+// Tracks requests that have been received from the kernel but not yet
+// responded to, so that `FUSE_INTERRUPT` can find its target.
+//
+// The kernel may send an interrupt before the request it targets has been
+// read by any executor. Such interrupts are queued until either the target
+// arrives or another request is received, in which case the oldest queued
+// interrupt is answered with `EAGAIN` and the kernel will resend it later.
+// This follows the behavior of libfuse.
+#[cfg(feature = "respond_async")]
+pub(crate) struct InflightRequests {
+	state: Mutex<InflightState>,
+	drained: Condvar,
+}
+
+#[cfg(feature = "respond_async")]
+struct InflightState {
+	requests: HashMap<u64, CancellationHandle>,
+	pending_interrupts: Vec<PendingInterrupt>,
+}
+
+#[cfg(feature = "respond_async")]
+struct PendingInterrupt {
+	interrupt_id: u64,
+	target_id: u64,
+}
+
+#[cfg(feature = "respond_async")]
+impl InflightRequests {
+	pub(crate) fn new() -> InflightRequests {
+		Self {
+			state: Mutex::new(InflightState {
+				requests: HashMap::new(),
+				pending_interrupts: Vec::new(),
+			}),
+			drained: Condvar::new(),
+		}
+	}
+
+	// Waits until no requests are in flight, returning `false` if `timeout`
+	// elapsed first.
+	pub(crate) fn wait_drained(&self, timeout: Duration) -> bool {
+		let state = self.state.lock().unwrap();
+		let (_state, result) = self
+			.drained
+			.wait_timeout_while(state, timeout, |s| !s.requests.is_empty())
+			.unwrap();
+		!result.timed_out()
+	}
+
+	// Waits until no requests are in flight.
+	pub(crate) fn wait_until_drained(&self) {
+		let state = self.state.lock().unwrap();
+		let _state = self
+			.drained
+			.wait_while(state, |s| !s.requests.is_empty())
+			.unwrap();
+	}
+
+	// Handles interrupt bookkeeping for a newly received request.
+	//
+	// Returns `None` if the request was a `FUSE_INTERRUPT`, which has been
+	// fully handled and should not be dispatched.
+	pub(crate) fn begin_request<C: channel::Channel>(
+		self: &Arc<Self>,
+		channel: &C,
+		dec: &fuse_io::RequestDecoder,
+	) -> Result<Option<InflightGuard>, C::Error> {
+		let header = dec.header();
+		if header.opcode == fuse_kernel::FUSE_INTERRUPT {
+			let raw: &fuse_kernel::fuse_interrupt_in = dec.peek_sized()?;
+			self.interrupt(header.unique, raw.unique);
+			return Ok(None);
+		}
+
+		let request_id = header.unique;
+		let cancellation = CancellationHandle::new();
+		let mut expired = None;
+		{
+			let mut state = self.state.lock().unwrap();
+			let pending = &mut state.pending_interrupts;
+			match pending.iter().position(|p| p.target_id == request_id) {
+				Some(idx) => {
+					pending.remove(idx);
+					cancellation.cancel();
+				},
+				None => {
+					if !pending.is_empty() {
+						expired = Some(pending.remove(0).interrupt_id);
+					}
+				},
+			}
+			state.requests.insert(request_id, cancellation.clone());
+		}
+
+		let guard = InflightGuard {
+			inflight: self.clone(),
+			request_id,
+			cancellation,
+		};
+		if let Some(interrupt_id) = expired {
+			let result = fuse_io::ResponseEncoder::new(
+				channel,
+				interrupt_id,
+				// Error responses are the same in all protocol versions.
+				ProtocolVersion::LATEST,
+			)
+			.encode_error(ErrorCode::EAGAIN);
+			// The kernel rejects the response with `ENOENT` if the target
+			// request was answered in the meantime, in which case there is
+			// nothing left to interrupt.
+			if let Err(err) = result {
+				if err.error_code() != Some(ErrorCode::ENOENT) {
+					return Err(err);
+				}
+			}
+		}
+		Ok(Some(guard))
+	}
+
+	fn interrupt(&self, interrupt_id: u64, target_id: u64) {
+		let mut state = self.state.lock().unwrap();
+		if let Some(cancellation) = state.requests.get(&target_id) {
+			cancellation.cancel();
+			return;
+		}
+		state.pending_interrupts.push(PendingInterrupt {
+			interrupt_id,
+			target_id,
+		});
+	}
+}
+
+// Removes a request from the in-flight table when dropped. Ownership moves
+// from `RespondRef` to `RespondAsync` if the handler responds asynchronously.
+#[cfg(feature = "respond_async")]
+pub(crate) struct InflightGuard {
+	inflight: Arc<InflightRequests>,
+	request_id: u64,
+	cancellation: CancellationHandle,
 }
 
+#[cfg(feature = "respond_async")]
+impl InflightGuard {
+	pub(crate) fn cancellation(&self) -> &CancellationHandle {
+		&self.cancellation
+	}
+}
+
+#[cfg(feature = "respond_async")]
+impl Drop for InflightGuard {
+	fn drop(&mut self) {
+		let mut state = self.inflight.state.lock().unwrap();
+		state.requests.remove(&self.request_id);
+		if state.requests.is_empty() {
+			self.inflight.drained.notify_all();
+		}
+	}
+}
+
+// }}}
+
 #[allow(unused_variables)]
 pub trait ServerHooks {
 	fn request(&self, request_header: &RequestHeader) {}
@@ -112,37 +374,121 @@ pub(crate) fn negotiate_version(
 	))
 }
 
+// Runs until the session ends, either because the kernel sent `FUSE_DESTROY`
+// or because the connection was closed.
+//
+// The `FUSE_DESTROY` request is not passed to `cb`. Its request ID is
+// returned so that the caller can respond after cleaning up.
 pub(crate) fn main_loop<Buf, C, Cb>(
 	channel: &C,
 	read_buf: &mut Buf,
 	fuse_version: ProtocolVersion,
 	semantics: fuse_io::Semantics,
 	cb: Cb,
-) -> Result<(), C::Error>
+) -> Result<Option<u64>, C::Error>
+where
+	Buf: fuse_io::AlignedBuffer,
+	C: channel::Channel,
+	Cb: Fn(fuse_io::RequestDecoder) -> Result<(), C::Error>,
+{
+	let splice_writes = false;
+	main_loop_until(
+		channel,
+		read_buf,
+		fuse_version,
+		semantics,
+		None,
+		splice_writes,
+		cb,
+	)
+}
+
+// Like `main_loop`, but also returns `Ok(None)` once `stop` has been set.
+//
+// The flag is checked before each receive, so an executor blocked waiting for
+// a request will notice it only after that request has been handled.
+//
+// If `splice_writes` is set, requests are received with
+// `Channel::receive_spliced()` so that large `FUSE_WRITE` payloads may be
+// left in a pipe.
+pub(crate) fn main_loop_until<Buf, C, Cb>(
+	channel: &C,
+	read_buf: &mut Buf,
+	fuse_version: ProtocolVersion,
+	semantics: fuse_io::Semantics,
+	stop: Option<&AtomicBool>,
+	splice_writes: bool,
+	cb: Cb,
+) -> Result<Option<u64>, C::Error>
 where
 	Buf: fuse_io::AlignedBuffer,
 	C: channel::Channel,
 	Cb: Fn(fuse_io::RequestDecoder) -> Result<(), C::Error>,
 {
 	loop {
-		let request_size = match channel.receive(read_buf.get_mut()) {
+		if let Some(stop) = stop {
+			if stop.load(Ordering::Acquire) {
+				return Ok(None);
+			}
+		}
+		let received = receive(channel, read_buf.get_mut(), splice_writes);
+		let (request_size, spliced_payload) = match received {
 			Err(err) => {
 				if semantics == fuse_io::Semantics::FUSE {
 					if err.error_code() == Some(ErrorCode::ENODEV) {
-						return Ok(());
+						return Ok(None);
 					}
 				}
 				return Err(err);
 			},
-			Ok(request_size) => request_size,
+			Ok(received) => received,
 		};
 		let request_buf = fuse_io::aligned_slice(read_buf, request_size);
-		cb(fuse_io::RequestDecoder::new(
-			request_buf,
-			fuse_version,
-			semantics,
-		)?)?;
+		let dec = match spliced_payload {
+			None => fuse_io::RequestDecoder::new(
+				request_buf,
+				fuse_version,
+				semantics,
+			)?,
+			#[cfg(feature = "std")]
+			Some(payload_fd) => fuse_io::RequestDecoder::new_spliced(
+				request_buf,
+				fuse_version,
+				semantics,
+				payload_fd,
+			)?,
+			#[cfg(not(feature = "std"))]
+			Some(never) => match never {},
+		};
+		if semantics == fuse_io::Semantics::FUSE {
+			let header = dec.header();
+			if header.opcode == fuse_kernel::FUSE_DESTROY {
+				return Ok(Some(header.unique));
+			}
+		}
+		cb(dec)?;
+	}
+}
+
+#[cfg(feature = "std")]
+fn receive<C: channel::Channel>(
+	channel: &C,
+	buf: &mut [u8],
+	splice_writes: bool,
+) -> Result<(usize, Option<std::os::unix::io::RawFd>), C::Error> {
+	if splice_writes {
+		return channel.receive_spliced(buf);
 	}
+	Ok((channel.receive(buf)?, None))
+}
+
+#[cfg(not(feature = "std"))]
+fn receive<C: channel::Channel>(
+	channel: &C,
+	buf: &mut [u8],
+	_splice_writes: bool,
+) -> Result<(usize, Option<core::convert::Infallible>), C::Error> {
+	Ok((channel.receive(buf)?, None))
 }
 
 pub(crate) trait MaybeSendChannel {
@@ -233,6 +579,9 @@ where
 
 	#[cfg(feature = "respond_async")]
 	hooks_arc: Option<&'a Arc<Hooks>>,
+
+	#[cfg(feature = "respond_async")]
+	inflight: InflightGuard,
 }
 
 impl<'a, C, Hooks> RespondRef<'a, C, Hooks>
@@ -248,6 +597,7 @@ where
 		fuse_version: ProtocolVersion,
 		#[cfg(feature = "respond_async")] channel_arc: &'a Arc<C>,
 		#[cfg(feature = "respond_async")] hooks_arc: Option<&'a Arc<Hooks>>,
+		#[cfg(feature = "respond_async")] inflight: InflightGuard,
 	) -> Self {
 		Self {
 			channel,
@@ -259,9 +609,16 @@ where
 			channel_arc,
 			#[cfg(feature = "respond_async")]
 			hooks_arc,
+			#[cfg(feature = "respond_async")]
+			inflight,
 		}
 	}
 
+	#[cfg(feature = "respond_async")]
+	pub(crate) fn cancellation(&self) -> &CancellationHandle {
+		self.inflight.cancellation()
+	}
+
 	pub(crate) fn encoder(&self) -> fuse_io::ResponseEncoder<C> {
 		fuse_io::ResponseEncoder::new(
 			self.channel,
@@ -344,6 +701,7 @@ where
 			hooks: r.hooks_arc.map(|h| h.clone()),
 			header: r.header.clone(),
 			fuse_version: r.fuse_version,
+			inflight: r.inflight,
 		}))
 	}
 }
@@ -398,12 +756,19 @@ impl<R> RespondAsync<R> {
 	pub fn err(self, err: ErrorCode) {
 		self.0.err(err)
 	}
+
+	/// Returns a handle that will be cancelled if the kernel sends a
+	/// `FUSE_INTERRUPT` for this request.
+	pub fn cancellation(&self) -> &CancellationHandle {
+		self.0.cancellation()
+	}
 }
 
 #[cfg(feature = "respond_async")]
 trait RespondAsyncInner<R>: Send + Sync {
 	fn ok(&self, response: &R);
 	fn err(&self, err: ErrorCode);
+	fn cancellation(&self) -> &CancellationHandle;
 }
 
 #[cfg(feature = "respond_async")]
@@ -412,6 +777,7 @@ struct RespondAsyncInnerImpl<C, Hooks> {
 	hooks: Option<Arc<Hooks>>,
 	header: RequestHeader,
 	fuse_version: ProtocolVersion,
+	inflight: InflightGuard,
 }
 
 #[cfg(feature = "respond_async")]
@@ -456,4 +822,8 @@ where
 	fn err(&self, err: ErrorCode) {
 		self.err_impl(err)
 	}
+
+	fn cancellation(&self) -> &CancellationHandle {
+		self.inflight.cancellation()
+	}
 }
@@ -0,0 +1,132 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::thread;
+
+use crate::protocol::{
+	Opcode,
+	OpenRequest,
+	OpenResponse,
+	OpendirRequest,
+	OpendirResponse,
+};
+use crate::server::{Respond, RespondAsync, ServerContext};
+use crate::testing::FuseClient;
+use crate::{ErrorCode, FuseHandlers, ROOT_ID};
+
+// The body of a `FUSE_OPEN` or `FUSE_OPENDIR` request, a `fuse_open_in`.
+const OPEN_IN: [u8; 8] = [0; 8];
+
+struct InterruptFS;
+
+impl FuseHandlers for InterruptFS {
+	// Responds immediately, failing with `EINTR` if the request was
+	// interrupted before it was dispatched.
+	fn open(
+		&self,
+		ctx: ServerContext,
+		_request: &OpenRequest,
+		respond: impl for<'a> Respond<OpenResponse<'a>>,
+	) {
+		if ctx.cancellation().is_cancelled() {
+			return respond.err(ErrorCode::EINTR);
+		}
+		let mut resp = OpenResponse::new();
+		resp.set_handle(1001);
+		respond.ok(&resp);
+	}
+
+	// Responds from another thread once the request has been interrupted.
+	fn opendir(
+		&self,
+		_ctx: ServerContext,
+		_request: &OpendirRequest,
+		respond: impl for<'a> Respond<OpendirResponse<'a>>,
+	) {
+		let respond: RespondAsync<OpendirResponse<'static>> =
+			RespondAsync::new(respond);
+		thread::spawn(move || {
+			respond.cancellation().wait();
+			respond.err(ErrorCode::EINTR);
+		});
+	}
+}
+
+#[test]
+fn interrupt_before_request() {
+	let mut client = FuseClient::serve(InterruptFS);
+	client.fuse_init().unwrap();
+
+	// The kernel may send an interrupt before the server has read the
+	// request it targets. Request IDs are sequential, so the interrupt is
+	// request 2 and its target will be request 3.
+	assert_eq!(client.interrupt(3), 2);
+	let request_id = client.send_request(Opcode::FUSE_OPEN, 2, &OPEN_IN);
+	assert_eq!(request_id, 3);
+
+	let err = client.receive_response(request_id).unwrap_err();
+	assert_eq!(err, ErrorCode::EINTR);
+	client.destroy().unwrap();
+}
+
+#[test]
+fn interrupt_target_not_received() {
+	let mut client = FuseClient::serve(InterruptFS);
+	client.fuse_init().unwrap();
+
+	// The interrupt is still queued when an unrelated request arrives, so
+	// the server asks the kernel to send it again later.
+	let interrupt_id = client.interrupt(4);
+	let first_id = client.send_request(Opcode::FUSE_OPEN, 2, &OPEN_IN);
+	let target_id = client.send_request(Opcode::FUSE_OPEN, 2, &OPEN_IN);
+	assert_eq!(target_id, 4);
+
+	let err = client.receive_response(interrupt_id).unwrap_err();
+	assert_eq!(err, ErrorCode::EAGAIN);
+	assert!(client.receive_response(first_id).is_ok());
+	assert!(client.receive_response(target_id).is_ok());
+	client.destroy().unwrap();
+}
+
+#[test]
+fn interrupt_after_response() {
+	let mut client = FuseClient::serve(InterruptFS);
+	client.fuse_init().unwrap();
+
+	let target_id = client.send_request(Opcode::FUSE_OPEN, 2, &OPEN_IN);
+	assert!(client.receive_response(target_id).is_ok());
+
+	// The next request expires the queued interrupt, and the channel
+	// rejects its `EAGAIN` response because the target has already been
+	// answered. The server must keep running.
+	client.interrupt(target_id);
+	assert_eq!(client.open(ROOT_ID, 0), Ok(1001));
+	client.destroy().unwrap();
+}
+
+#[test]
+fn interrupt_async_response() {
+	let mut client = FuseClient::serve(InterruptFS);
+	client.fuse_init().unwrap();
+
+	let request_id =
+		client.send_request(Opcode::FUSE_OPENDIR, ROOT_ID.get(), &OPEN_IN);
+	client.interrupt(request_id);
+
+	let err = client.receive_response(request_id).unwrap_err();
+	assert_eq!(err, ErrorCode::EINTR);
+	client.destroy().unwrap();
+}
@@ -0,0 +1,729 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Utilities for testing FUSE handlers without mounting a filesystem.
+//!
+//! A [`FuseClient`] speaks the kernel's side of the FUSE protocol over an
+//! in-memory [`MemoryChannel`]. Handlers can be served on a background thread
+//! with [`FuseClient::serve`], after which the client sends typed requests and
+//! decodes the responses:
+//!
+//! ```no_run
+//! # struct MyHandlers;
+//! # impl fuse::FuseHandlers for MyHandlers {}
+//! use fuse::testing::FuseClient;
+//!
+//! let mut client = FuseClient::serve(MyHandlers);
+//! client.fuse_init().unwrap();
+//!
+//! let name = fuse::NodeName::from_bytes(b"hello.txt").unwrap();
+//! let node = client.lookup(fuse::ROOT_ID, name).unwrap();
+//! let handle = client.open(node.id().unwrap(), 0).unwrap();
+//! let data = client.read(node.id().unwrap(), handle, 0, 4096).unwrap();
+//!
+//! client.destroy().unwrap();
+//! ```
+//!
+//! [`FuseClient`]: struct.FuseClient.html
+//! [`FuseClient::serve`]: struct.FuseClient.html#method.serve
+//! [`MemoryChannel`]: struct.MemoryChannel.html
+
+use core::mem::size_of;
+use core::{fmt, num, ptr, slice, time};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+
+use crate::channel::Channel;
+use crate::error::ErrorCode;
+use crate::fuse_handlers::FuseHandlers;
+use crate::fuse_server::{FuseServerBuilder, FuseServerChannel};
+use crate::internal::fuse_kernel;
+use crate::internal::types::ProtocolVersion;
+use crate::protocol::common::{FileType, Node, NodeId, NodeName};
+use crate::protocol::{FuseInitResponse, Opcode};
+
+use crate::server::{NoopServerHooks, ServerChannel};
+
+#[cfg(rust_fuse_test = "testing_test")]
+#[path = "testing_test.rs"]
+mod testing_test;
+
+const DEFAULT_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+// MemoryChannel {{{
+
+/// The server side of an in-memory FUSE connection.
+///
+/// Requests sent by the paired [`FuseClient`] are received by the server,
+/// and the server's responses and notifications are delivered back to the
+/// client. Once the client is dropped, `receive()` fails with `ENODEV` as if
+/// the filesystem had been unmounted.
+///
+/// [`FuseClient`]: struct.FuseClient.html
+pub struct MemoryChannel {
+	conn: Arc<Connection>,
+}
+
+impl MemoryChannel {
+	fn new(conn: Arc<Connection>) -> MemoryChannel {
+		conn.lock().servers += 1;
+		Self { conn }
+	}
+}
+
+impl Drop for MemoryChannel {
+	fn drop(&mut self) {
+		self.conn.lock().servers -= 1;
+		self.conn.cond.notify_all();
+	}
+}
+
+impl fmt::Debug for MemoryChannel {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("MemoryChannel").finish()
+	}
+}
+
+impl Channel for MemoryChannel {
+	type Error = io::Error;
+
+	fn send(&self, buf: &[u8]) -> Result<(), io::Error> {
+		let mut state = self.conn.lock();
+		if state.closed {
+			return Err(enodev());
+		}
+		if buf.len() >= size_of::<fuse_kernel::fuse_out_header>() {
+			let header: fuse_kernel::fuse_out_header = read_sized(buf);
+			if !state.accept_response(header.unique) {
+				return Err(io::Error::from_raw_os_error(
+					ErrorCode::ENOENT.into(),
+				));
+			}
+		}
+		state.responses.push_back(buf.to_vec());
+		drop(state);
+		self.conn.cond.notify_all();
+		Ok(())
+	}
+
+	fn send_vectored<const N: usize>(
+		&self,
+		bufs: &[&[u8]; N],
+	) -> Result<(), io::Error> {
+		self.send(&bufs.concat())
+	}
+
+	fn receive(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
+		let mut state = self.conn.lock();
+		loop {
+			if let Some(request) = state.requests.pop_front() {
+				if request.len() > buf.len() {
+					return Err(io::Error::new(
+						io::ErrorKind::InvalidInput,
+						"receive buffer is smaller than the request",
+					));
+				}
+				buf[..request.len()].copy_from_slice(&request);
+				return Ok(request.len());
+			}
+			if state.closed || state.unmounted {
+				return Err(enodev());
+			}
+			state = self.conn.wait(state);
+		}
+	}
+}
+
+impl ServerChannel for MemoryChannel {
+	fn try_clone(&self) -> Result<Self, io::Error> {
+		Ok(MemoryChannel::new(self.conn.clone()))
+	}
+}
+
+impl FuseServerChannel for MemoryChannel {
+	/// Wakes servers blocked in `receive()`, which then fails with
+	/// `ENODEV`. Responses can still be sent until the client is dropped
+	/// or the channel is aborted.
+	fn shutdown(&self) -> Result<(), io::Error> {
+		self.conn.lock().unmounted = true;
+		self.conn.cond.notify_all();
+		Ok(())
+	}
+
+	fn abort(&self) -> Result<(), io::Error> {
+		self.conn.close();
+		Ok(())
+	}
+}
+
+fn enodev() -> io::Error {
+	io::Error::from_raw_os_error(ErrorCode::ENODEV.into())
+}
+
+// }}}
+
+// Connection {{{
+
+struct Connection {
+	state: Mutex<ConnectionState>,
+	cond: Condvar,
+}
+
+struct ConnectionState {
+	requests: VecDeque<Vec<u8>>,
+	responses: VecDeque<Vec<u8>>,
+	closed: bool,
+	unmounted: bool,
+	servers: usize,
+
+	// Requests sent by the client that haven't been answered yet, and the
+	// targets of interrupts sent by the client.
+	outstanding: HashSet<u64>,
+	interrupts: HashMap<u64, u64>,
+}
+
+impl ConnectionState {
+	// Like the kernel, rejects responses to requests that were never sent
+	// or have already been answered. A response to an interrupt is only
+	// accepted while its target is still waiting for a response.
+	fn accept_response(&mut self, request_id: u64) -> bool {
+		if request_id == 0 || self.outstanding.remove(&request_id) {
+			return true;
+		}
+		match self.interrupts.remove(&request_id) {
+			Some(target_id) => self.outstanding.contains(&target_id),
+			None => false,
+		}
+	}
+}
+
+impl Connection {
+	fn new() -> Connection {
+		Self {
+			state: Mutex::new(ConnectionState {
+				requests: VecDeque::new(),
+				responses: VecDeque::new(),
+				closed: false,
+				unmounted: false,
+				servers: 0,
+				outstanding: HashSet::new(),
+				interrupts: HashMap::new(),
+			}),
+			cond: Condvar::new(),
+		}
+	}
+
+	fn lock(&self) -> MutexGuard<'_, ConnectionState> {
+		match self.state.lock() {
+			Ok(guard) => guard,
+			Err(poisoned) => poisoned.into_inner(),
+		}
+	}
+
+	fn wait<'a>(
+		&self,
+		guard: MutexGuard<'a, ConnectionState>,
+	) -> MutexGuard<'a, ConnectionState> {
+		match self.cond.wait(guard) {
+			Ok(guard) => guard,
+			Err(poisoned) => poisoned.into_inner(),
+		}
+	}
+
+	fn close(&self) {
+		self.lock().closed = true;
+		self.cond.notify_all();
+	}
+}
+
+// }}}
+
+// FuseClient {{{
+
+/// The kernel side of an in-memory FUSE connection.
+///
+/// Requests are sent one at a time, and each method waits for the server's
+/// response. An error response from the handlers is returned as `Err`.
+///
+/// # Panics
+///
+/// The client's methods panic if the server stops serving requests, sends a
+/// malformed response, or doesn't respond within the timeout set by
+/// [`set_timeout`](#method.set_timeout).
+pub struct FuseClient {
+	conn: Arc<Connection>,
+	version: ProtocolVersion,
+	next_request_id: u64,
+	timeout: time::Duration,
+	notifications: Vec<Vec<u8>>,
+	responses: HashMap<u64, Vec<u8>>,
+	server: Option<thread::JoinHandle<Result<(), io::Error>>>,
+}
+
+impl FuseClient {
+	/// Creates a client and the [`MemoryChannel`] it's connected to.
+	///
+	/// The channel can be passed to [`FuseServerBuilder::new`]. Building the
+	/// server blocks until the client has sent `FUSE_INIT`, so the server
+	/// should be built and run on another thread.
+	///
+	/// [`MemoryChannel`]: struct.MemoryChannel.html
+	/// [`FuseServerBuilder::new`]: ../struct.FuseServerBuilder.html#method.new
+	pub fn new() -> (FuseClient, MemoryChannel) {
+		let conn = Arc::new(Connection::new());
+		let channel = MemoryChannel::new(conn.clone());
+		let client = FuseClient {
+			conn,
+			version: ProtocolVersion::LATEST,
+			next_request_id: 1,
+			timeout: DEFAULT_TIMEOUT,
+			notifications: Vec::new(),
+			responses: HashMap::new(),
+			server: None,
+		};
+		(client, channel)
+	}
+
+	/// Serves `handlers` on a new thread, and returns a client connected to
+	/// that server.
+	///
+	/// The server is built once the client sends `FUSE_INIT`, so
+	/// [`fuse_init`](#method.fuse_init) should be the first request.
+	pub fn serve<Handlers>(handlers: Handlers) -> FuseClient
+	where
+		Handlers: FuseHandlers + Send + 'static,
+	{
+		let (mut client, channel) = FuseClient::new();
+		client.server = Some(thread::spawn(move || {
+			let builder: FuseServerBuilder<_, _, NoopServerHooks> =
+				FuseServerBuilder::new(channel, handlers);
+			let mut server = builder.build()?;
+			server.executor_mut().run()
+		}));
+		client
+	}
+
+	/// Sets how long to wait for each response. The default is 10 seconds.
+	pub fn set_timeout(&mut self, timeout: time::Duration) {
+		self.timeout = timeout;
+	}
+
+	/// The protocol version negotiated by [`fuse_init`](#method.fuse_init).
+	pub fn version(&self) -> ProtocolVersion {
+		self.version
+	}
+
+	/// Returns the notifications sent by the server since the last call,
+	/// each including its `fuse_out_header`.
+	pub fn take_notifications(&mut self) -> Vec<Vec<u8>> {
+		let mut state = self.conn.lock();
+		sort_responses(
+			&mut state.responses,
+			&mut self.notifications,
+			&mut self.responses,
+		);
+		drop(state);
+		core::mem::take(&mut self.notifications)
+	}
+
+	/// Sends `FUSE_INIT` at the latest protocol version supported by this
+	/// library, and returns the server's response.
+	pub fn fuse_init(&mut self) -> Result<FuseInitResponse, ErrorCode> {
+		let raw = fuse_kernel::fuse_init_in {
+			major: fuse_kernel::FUSE_KERNEL_VERSION,
+			minor: fuse_kernel::FUSE_KERNEL_MINOR_VERSION,
+			max_readahead: 0,
+			flags: 0,
+		};
+		let body = self.request(Opcode::FUSE_INIT, 0, sized_bytes(&raw))?;
+		let raw: fuse_kernel::fuse_init_out = read_sized_prefix(&body);
+		let response = FuseInitResponse::from_raw(raw);
+		self.version = response.version();
+		Ok(response)
+	}
+
+	/// Sends `FUSE_DESTROY`, waits for the server to stop, and returns the
+	/// server's result if it was started by [`serve`](#method.serve).
+	pub fn destroy(mut self) -> Result<(), io::Error> {
+		let request_id = self.send(Opcode::FUSE_DESTROY, 0, &[]);
+		// A server that failed to start can't respond.
+		let _ = self.try_receive(request_id);
+		self.conn.close();
+		self.join()
+	}
+
+	/// Looks up the entry named `name` in the directory `parent_id`.
+	pub fn lookup(
+		&mut self,
+		parent_id: NodeId,
+		name: &NodeName,
+	) -> Result<Node, ErrorCode> {
+		let mut body = name.as_bytes().to_vec();
+		body.push(0);
+		let body = self.request(Opcode::FUSE_LOOKUP, parent_id.get(), &body)?;
+		Ok(Node::new(read_sized(&body)))
+	}
+
+	/// Opens the file `node_id`, and returns its handle.
+	pub fn open(
+		&mut self,
+		node_id: NodeId,
+		flags: u32,
+	) -> Result<u64, ErrorCode> {
+		self.open_impl(Opcode::FUSE_OPEN, node_id, flags)
+	}
+
+	/// Opens the directory `node_id`, and returns its handle.
+	pub fn opendir(
+		&mut self,
+		node_id: NodeId,
+		flags: u32,
+	) -> Result<u64, ErrorCode> {
+		self.open_impl(Opcode::FUSE_OPENDIR, node_id, flags)
+	}
+
+	fn open_impl(
+		&mut self,
+		opcode: Opcode,
+		node_id: NodeId,
+		flags: u32,
+	) -> Result<u64, ErrorCode> {
+		let raw = fuse_kernel::fuse_open_in { flags, unused: 0 };
+		let body = self.request(opcode, node_id.get(), sized_bytes(&raw))?;
+		let raw: fuse_kernel::fuse_open_out = read_sized(&body);
+		Ok(raw.fh)
+	}
+
+	/// Reads up to `size` bytes at `offset` from an open file.
+	pub fn read(
+		&mut self,
+		node_id: NodeId,
+		handle: u64,
+		offset: u64,
+		size: u32,
+	) -> Result<Vec<u8>, ErrorCode> {
+		let raw = read_in(handle, offset, size);
+		self.request(Opcode::FUSE_READ, node_id.get(), sized_bytes(&raw))
+	}
+
+	/// Reads entries from an open directory, starting after `cursor`.
+	pub fn readdir(
+		&mut self,
+		node_id: NodeId,
+		handle: u64,
+		cursor: Option<num::NonZeroU64>,
+		size: u32,
+	) -> Result<Vec<DirEntry>, ErrorCode> {
+		let offset = cursor.map_or(0, |c| c.get());
+		let raw = read_in(handle, offset, size);
+		let body = self.request(
+			Opcode::FUSE_READDIR,
+			node_id.get(),
+			sized_bytes(&raw),
+		)?;
+		Ok(parse_dirents(&body))
+	}
+
+	/// Sends a request with an arbitrary opcode, and returns the body of the
+	/// response without its `fuse_out_header`.
+	///
+	/// `body` is everything following the `fuse_in_header`.
+	pub fn request(
+		&mut self,
+		opcode: Opcode,
+		node_id: u64,
+		body: &[u8],
+	) -> Result<Vec<u8>, ErrorCode> {
+		let request_id = self.send_request(opcode, node_id, body);
+		self.receive_response(request_id)
+	}
+
+	/// Sends a request with an arbitrary opcode without waiting for the
+	/// response, and returns the request's ID.
+	///
+	/// Request IDs are assigned sequentially, starting from 1. The response
+	/// can be collected with [`receive_response`](#method.receive_response).
+	pub fn send_request(
+		&mut self,
+		opcode: Opcode,
+		node_id: u64,
+		body: &[u8],
+	) -> u64 {
+		self.send(opcode, node_id, body)
+	}
+
+	/// Sends `FUSE_INTERRUPT` for the request `request_id`, and returns the
+	/// interrupt's own request ID.
+	///
+	/// The server only responds to an interrupt if it can't find the target,
+	/// in which case the response is `EAGAIN`. Like the kernel, the channel
+	/// rejects that response with `ENOENT` if the target has already been
+	/// answered.
+	pub fn interrupt(&mut self, request_id: u64) -> u64 {
+		let raw = fuse_kernel::fuse_interrupt_in { unique: request_id };
+		self.send(Opcode::FUSE_INTERRUPT, 0, sized_bytes(&raw))
+	}
+
+	/// Waits for the response to a request sent by
+	/// [`send_request`](#method.send_request) or
+	/// [`interrupt`](#method.interrupt), and returns its body without the
+	/// `fuse_out_header`.
+	pub fn receive_response(
+		&mut self,
+		request_id: u64,
+	) -> Result<Vec<u8>, ErrorCode> {
+		let response = self.receive(request_id);
+		let header: fuse_kernel::fuse_out_header = read_sized(&response);
+		if header.error != 0 {
+			let code = num::NonZeroU16::new((-header.error) as u16)
+				.unwrap_or_else(|| {
+					panic!("invalid error in response: {}", header.error)
+				});
+			return Err(ErrorCode::new(code));
+		}
+		Ok(response[size_of::<fuse_kernel::fuse_out_header>()..].to_vec())
+	}
+
+	fn send(&mut self, opcode: Opcode, node_id: u64, body: &[u8]) -> u64 {
+		let request_id = self.next_request_id;
+		self.next_request_id += 1;
+
+		let header_len = size_of::<fuse_kernel::fuse_in_header>();
+		let header = fuse_kernel::fuse_in_header {
+			len: (header_len + body.len()) as u32,
+			opcode: fuse_kernel::Opcode(opcode as u32),
+			unique: request_id,
+			nodeid: node_id,
+			uid: 0,
+			gid: 0,
+			pid: 0,
+			padding: 0,
+		};
+		let mut request = Vec::with_capacity(header_len + body.len());
+		request.extend_from_slice(sized_bytes(&header));
+		request.extend_from_slice(body);
+
+		let mut state = self.conn.lock();
+		if opcode == Opcode::FUSE_INTERRUPT {
+			let raw: fuse_kernel::fuse_interrupt_in = read_sized(body);
+			state.interrupts.insert(request_id, raw.unique);
+		} else {
+			state.outstanding.insert(request_id);
+		}
+		state.requests.push_back(request);
+		drop(state);
+		self.conn.cond.notify_all();
+		request_id
+	}
+
+	fn receive(&mut self, request_id: u64) -> Vec<u8> {
+		match self.try_receive(request_id) {
+			Some(response) => response,
+			None => panic!("server stopped: {:?}", self.join()),
+		}
+	}
+
+	// Waits for the response to `request_id`, or returns `None` if the
+	// server stops first.
+	fn try_receive(&mut self, request_id: u64) -> Option<Vec<u8>> {
+		let deadline = std::time::Instant::now() + self.timeout;
+		let mut state = self.conn.lock();
+		loop {
+			sort_responses(
+				&mut state.responses,
+				&mut self.notifications,
+				&mut self.responses,
+			);
+			if let Some(response) = self.responses.remove(&request_id) {
+				return Some(response);
+			}
+			if state.servers == 0 {
+				return None;
+			}
+			let now = std::time::Instant::now();
+			if now >= deadline {
+				panic!(
+					"timed out waiting for response to request {}",
+					request_id
+				);
+			}
+			state = match self.conn.cond.wait_timeout(state, deadline - now) {
+				Ok((guard, _)) => guard,
+				Err(poisoned) => poisoned.into_inner().0,
+			};
+		}
+	}
+
+	fn join(&mut self) -> Result<(), io::Error> {
+		match self.server.take() {
+			None => Ok(()),
+			Some(server) => match server.join() {
+				Ok(result) => result,
+				Err(panic) => std::panic::resume_unwind(panic),
+			},
+		}
+	}
+}
+
+impl Drop for FuseClient {
+	fn drop(&mut self) {
+		self.conn.close();
+		if let Some(server) = self.server.take() {
+			let _ = server.join();
+		}
+	}
+}
+
+impl fmt::Debug for FuseClient {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("FuseClient")
+			.field("version", &self.version)
+			.field("timeout", &self.timeout)
+			.finish()
+	}
+}
+
+// Moves messages sent by the server into `notifications` or `responses`,
+// depending on whether they answer a request.
+fn sort_responses(
+	sent: &mut VecDeque<Vec<u8>>,
+	notifications: &mut Vec<Vec<u8>>,
+	responses: &mut HashMap<u64, Vec<u8>>,
+) {
+	while let Some(response) = sent.pop_front() {
+		let header: fuse_kernel::fuse_out_header = read_sized(&response);
+		if header.len as usize != response.len() {
+			panic!(
+				"response length {} doesn't match header {:?}",
+				response.len(),
+				header,
+			);
+		}
+		if header.unique == 0 {
+			notifications.push(response);
+			continue;
+		}
+		responses.insert(header.unique, response);
+	}
+}
+
+fn read_in(handle: u64, offset: u64, size: u32) -> fuse_kernel::fuse_read_in {
+	fuse_kernel::fuse_read_in {
+		fh: handle,
+		offset,
+		size,
+		read_flags: 0,
+		lock_owner: 0,
+		flags: 0,
+		padding: 0,
+	}
+}
+
+// }}}
+
+// DirEntry {{{
+
+/// A directory entry returned by [`FuseClient::readdir`].
+///
+/// [`FuseClient::readdir`]: struct.FuseClient.html#method.readdir
+#[derive(Clone, Eq, PartialEq)]
+pub struct DirEntry {
+	node_id: NodeId,
+	cursor: num::NonZeroU64,
+	file_type: FileType,
+	name: Vec<u8>,
+}
+
+impl DirEntry {
+	pub fn node_id(&self) -> NodeId {
+		self.node_id
+	}
+
+	pub fn cursor(&self) -> num::NonZeroU64 {
+		self.cursor
+	}
+
+	pub fn file_type(&self) -> FileType {
+		self.file_type
+	}
+
+	pub fn name(&self) -> &[u8] {
+		&self.name
+	}
+}
+
+impl fmt::Debug for DirEntry {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("DirEntry")
+			.field("node_id", &self.node_id)
+			.field("cursor", &self.cursor)
+			.field("file_type", &self.file_type)
+			.field("name", &String::from_utf8_lossy(&self.name))
+			.finish()
+	}
+}
+
+fn parse_dirents(mut buf: &[u8]) -> Vec<DirEntry> {
+	let header_len = size_of::<fuse_kernel::fuse_dirent>();
+	let mut entries = Vec::new();
+	while !buf.is_empty() {
+		let raw: fuse_kernel::fuse_dirent = read_sized(buf);
+		let name_len = raw.namelen as usize;
+		let entry_len = (header_len + name_len + 7) & !7;
+		if buf.len() < header_len + name_len {
+			panic!("truncated dirent {:?}", raw);
+		}
+		entries.push(DirEntry {
+			node_id: NodeId::new(raw.ino)
+				.unwrap_or_else(|| panic!("invalid dirent {:?}", raw)),
+			cursor: num::NonZeroU64::new(raw.off)
+				.unwrap_or_else(|| panic!("invalid dirent {:?}", raw)),
+			file_type: FileType::from_bits(raw.r#type)
+				.unwrap_or_else(|| panic!("invalid dirent {:?}", raw)),
+			name: buf[header_len..header_len + name_len].to_vec(),
+		});
+		buf = &buf[entry_len.min(buf.len())..];
+	}
+	entries
+}
+
+// }}}
+
+fn sized_bytes<T: Sized>(t: &T) -> &[u8] {
+	unsafe { slice::from_raw_parts(t as *const T as *const u8, size_of::<T>()) }
+}
+
+// Reads a `T` from the start of `buf`, which must be at least as large.
+fn read_sized<T: Copy>(buf: &[u8]) -> T {
+	if buf.len() < size_of::<T>() {
+		panic!(
+			"expected at least {} bytes, got {}",
+			size_of::<T>(),
+			buf.len(),
+		);
+	}
+	unsafe { ptr::read_unaligned(buf.as_ptr() as *const T) }
+}
+
+// Reads a `T` from the start of `buf`, zero-filling any fields past its end.
+fn read_sized_prefix<T: Copy>(buf: &[u8]) -> T {
+	let mut padded = vec![0u8; size_of::<T>()];
+	let len = buf.len().min(padded.len());
+	padded[..len].copy_from_slice(&buf[..len]);
+	read_sized(&padded)
+}
@@ -0,0 +1,69 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::atomic::Ordering;
+
+use crate::internal::testutil::{hello_id, hello_name, HelloFS, HELLO_WORLD};
+use crate::{ErrorCode, FileType, FuseHandlers, NodeName, ROOT_ID};
+
+use super::FuseClient;
+
+#[test]
+fn serve_handlers() {
+	let fs = HelloFS::new();
+	let destroyed = fs.destroyed.clone();
+	let mut client = FuseClient::serve(fs);
+	client.fuse_init().unwrap();
+
+	let node = client.lookup(ROOT_ID, hello_name()).unwrap();
+	assert_eq!(node.id(), Some(hello_id()));
+	assert_eq!(node.attr().size(), HELLO_WORLD.len() as u64);
+
+	let missing = NodeName::from_bytes(b"missing.txt").unwrap();
+	let err = client.lookup(ROOT_ID, missing).unwrap_err();
+	assert_eq!(err, ErrorCode::ENOENT);
+
+	let handle = client.open(hello_id(), 0).unwrap();
+	assert_eq!(handle, 1001);
+	assert_eq!(client.read(hello_id(), handle, 7, 5).unwrap(), b"world");
+
+	let handle = client.opendir(ROOT_ID, 0).unwrap();
+	let entries = client.readdir(ROOT_ID, handle, None, 4096).unwrap();
+	assert_eq!(entries.len(), 1);
+	assert_eq!(entries[0].node_id(), hello_id());
+	assert_eq!(entries[0].name(), b"hello.txt");
+	assert_eq!(entries[0].file_type(), FileType::Regular);
+
+	let cursor = Some(entries[0].cursor());
+	assert!(client
+		.readdir(ROOT_ID, handle, cursor, 4096)
+		.unwrap()
+		.is_empty());
+
+	client.destroy().unwrap();
+	assert!(destroyed.load(Ordering::SeqCst));
+}
+
+#[test]
+fn unimplemented_handler() {
+	struct EmptyFS;
+	impl FuseHandlers for EmptyFS {}
+
+	let mut client = FuseClient::serve(EmptyFS);
+	client.fuse_init().unwrap();
+	assert_eq!(client.opendir(ROOT_ID, 0), Err(ErrorCode::ENOSYS));
+	client.destroy().unwrap();
+}
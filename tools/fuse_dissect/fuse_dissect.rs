@@ -0,0 +1,184 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Prints the FUSE messages in a recording or a raw byte stream.
+//!
+//! ```text
+//! fuse_dissect [--responses] [--hex] [--protocol-version MAJOR.MINOR] [FILE]
+//! ```
+//!
+//! The input is read from `FILE`, or from stdin if no file is given.
+//! Recordings written by `fuse::record::RecordingChannel` are detected
+//! automatically. Other input is treated as a stream of requests, or of
+//! responses if `--responses` is given.
+//!
+//! With `--hex`, the input is text containing hexadecimal bytes. In lines of
+//! `strace -xx` output, quoted strings of `\x` escapes are decoded and
+//! concatenated. Other lines are read as hex digits, ignoring whitespace.
+//!
+//! strace truncates strings longer than 32 bytes by default, which is
+//! reported as an error. Pass a larger limit to strace with `-s`.
+
+use std::io::{self, Read, Write};
+use std::process;
+
+use fuse::dissect::Dissector;
+use fuse::io::ProtocolVersion;
+use fuse::record::Recording;
+
+const USAGE: &str = "usage: fuse_dissect [--responses] [--hex] \
+                     [--protocol-version MAJOR.MINOR] [FILE]";
+
+struct Args {
+	responses: bool,
+	hex: bool,
+	version: Option<ProtocolVersion>,
+	path: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+	let mut args = Args {
+		responses: false,
+		hex: false,
+		version: None,
+		path: None,
+	};
+	let mut argv = std::env::args().skip(1);
+	while let Some(arg) = argv.next() {
+		match arg.as_str() {
+			"--responses" => args.responses = true,
+			"--hex" => args.hex = true,
+			"--protocol-version" => {
+				let value = argv.next().ok_or("missing protocol version")?;
+				args.version = Some(parse_version(&value)?);
+			},
+			"-h" | "--help" => return Err(String::new()),
+			_ if arg.starts_with('-') && arg != "-" => {
+				return Err(format!("unknown option {:?}", arg));
+			},
+			_ if args.path.is_some() => {
+				return Err(format!("unexpected argument {:?}", arg));
+			},
+			_ => args.path = Some(arg),
+		}
+	}
+	Ok(args)
+}
+
+fn parse_version(value: &str) -> Result<ProtocolVersion, String> {
+	let err = || format!("invalid protocol version {:?}", value);
+	let mut parts = value.splitn(2, '.');
+	let major = parts.next().and_then(|s| s.parse().ok()).ok_or_else(err)?;
+	let minor = parts.next().and_then(|s| s.parse().ok()).ok_or_else(err)?;
+	Ok(ProtocolVersion::new(major, minor))
+}
+
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+	let mut out = Vec::new();
+	for (line_idx, line) in text.lines().enumerate() {
+		let err = |msg: &str| format!("line {}: {}", line_idx + 1, msg);
+		if line.contains('"') || line.contains('(') {
+			// A line of strace output. Every other segment between quotes
+			// is a string's contents, and strace marks a truncated string
+			// with "..." after its closing quote.
+			let segments: Vec<&str> = line.split('"').collect();
+			for idx in (1..segments.len()).step_by(2) {
+				let after = segments.get(idx + 1).copied().unwrap_or("");
+				if after.starts_with("...") {
+					return Err(err(
+						"string truncated by strace (increase the limit with -s)",
+					));
+				}
+				let mut rest = segments[idx];
+				while !rest.is_empty() {
+					if !rest.starts_with("\\x") || rest.len() < 4 {
+						return Err(err("expected \\x escapes in string"));
+					}
+					let byte = u8::from_str_radix(&rest[2..4], 16)
+						.map_err(|_| err("invalid \\x escape"))?;
+					out.push(byte);
+					rest = &rest[4..];
+				}
+			}
+			continue;
+		}
+		let digits: Vec<char> =
+			line.chars().filter(|c| !c.is_whitespace()).collect();
+		if digits.len() % 2 != 0 {
+			return Err(err("odd number of hex digits"));
+		}
+		for pair in digits.chunks(2) {
+			let pair: String = pair.iter().collect();
+			let byte = u8::from_str_radix(&pair, 16)
+				.map_err(|_| err("invalid hex digits"))?;
+			out.push(byte);
+		}
+	}
+	Ok(out)
+}
+
+fn run(args: Args) -> Result<(), String> {
+	let mut input = Vec::new();
+	let read = match args.path.as_deref() {
+		None | Some("-") => io::stdin().read_to_end(&mut input),
+		Some(path) => std::fs::File::open(path)
+			.and_then(|mut file| file.read_to_end(&mut input)),
+	};
+	read.map_err(|err| format!("error reading input: {}", err))?;
+
+	if args.hex {
+		let text = String::from_utf8(input)
+			.map_err(|_| "hex input is not valid UTF-8".to_string())?;
+		input = parse_hex(&text)?;
+	}
+
+	let mut dissector = Dissector::new();
+	if let Some(version) = args.version {
+		dissector.set_version(version);
+	}
+
+	let stdout = io::stdout();
+	let mut out = io::BufWriter::new(stdout.lock());
+	let result = if input.starts_with(b"FUSEREC\0") {
+		let recording = Recording::read_from(&input[..])
+			.map_err(|err| format!("error reading recording: {}", err))?;
+		dissector.dissect_recording(&recording, &mut out)
+	} else if args.responses {
+		dissector.dissect_responses(&input, &mut out)
+	} else {
+		dissector.dissect_requests(&input, &mut out)
+	};
+	result
+		.and_then(|_| out.flush())
+		.map_err(|err| format!("error writing output: {}", err))
+}
+
+fn main() {
+	let args = match parse_args() {
+		Ok(args) => args,
+		Err(err) => {
+			if !err.is_empty() {
+				eprintln!("fuse_dissect: {}", err);
+			}
+			eprintln!("{}", USAGE);
+			process::exit(2);
+		},
+	};
+	if let Err(err) = run(args) {
+		eprintln!("fuse_dissect: {}", err);
+		process::exit(1);
+	}
+}